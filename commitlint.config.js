@@ -27,6 +27,7 @@ module.exports = {
                 'data-wrk',
                 'amqp',
                 'db',
+                'domain',
                 'eetf',
                 'passwd',
                 'snowflake',
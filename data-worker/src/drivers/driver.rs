@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+
+use crate::drivers::{HttpDeviceDriver, ResilientDeviceDriver};
+use crate::error::WorkerError;
+use crate::services::device::{Device, DeviceKind};
+use crate::services::greenhouse::{Greenhouse, GreenhouseDriver};
+
+#[derive(Clone, Debug)]
+pub struct SensorReading {
+    pub kind: DeviceKind,
+    pub external_id: Option<i16>,
+    pub data: f64,
+}
+
+/// A device the gateway reported in its inventory
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DiscoveredDevice {
+    pub kind: DeviceKind,
+    pub external_id: Option<i16>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DeviceDriverCapabilities {
+    /// Sensor kinds the driver can read, each paired with the kind of device
+    /// that has to be polled to get the reading
+    pub sensors: &'static [(DeviceKind, DeviceKind)],
+    pub controllers: &'static [DeviceKind],
+}
+
+impl DeviceDriverCapabilities {
+    pub fn get_polled_kind(&self, kind: DeviceKind) -> Option<DeviceKind> {
+        self.sensors.iter()
+            .find(|(sensor_kind, _)| *sensor_kind == kind)
+            .map(|(_, polled_kind)| *polled_kind)
+    }
+
+    pub fn is_polled(&self, kind: DeviceKind) -> bool {
+        self.sensors.iter().any(|(_, polled_kind)| *polled_kind == kind)
+    }
+
+    pub fn is_controllable(&self, kind: DeviceKind) -> bool {
+        self.controllers.contains(&kind)
+    }
+}
+
+/// A hardware gateway that sensors and controllers of a greenhouse are connected to
+#[async_trait]
+pub trait DeviceDriver: Send + Sync {
+    fn get_capabilities(&self) -> DeviceDriverCapabilities;
+
+    /// Polls the device and returns every reading the gateway gave back,
+    /// which may include readings of sibling devices with the same external ID
+    async fn read_sensor(&self, device: &Device) -> Result<Vec<SensorReading>, WorkerError>;
+
+    async fn set_controller_state(&self, device: &Device, state: u8) -> Result<(), WorkerError>;
+
+    /// Lists every sensor and controller connected to the gateway
+    async fn discover_devices(&self) -> Result<Vec<DiscoveredDevice>, WorkerError>;
+}
+
+pub fn get_driver(greenhouse: &Greenhouse) -> Box<dyn DeviceDriver> {
+    let driver: Box<dyn DeviceDriver> = match greenhouse.driver {
+        GreenhouseDriver::Http => Box::new(HttpDeviceDriver::new(greenhouse)),
+    };
+
+    Box::new(ResilientDeviceDriver::new(greenhouse.id, driver))
+}
@@ -0,0 +1,296 @@
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use reqwest::{redirect, Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::drivers::{DeviceDriver, DeviceDriverCapabilities, DiscoveredDevice, SensorReading};
+use crate::error::{WorkerError, WorkerErrorTemplate};
+use crate::{garthen, network};
+use crate::services::device::{Device, DeviceKind};
+use crate::services::greenhouse::{Greenhouse, GreenhouseGatewayAuthScheme};
+
+lazy_static! {
+    // Shared by all greenhouses, so connections to the same gateway are reused
+    static ref CLIENT: Client = {
+        Client::builder()
+            .timeout(garthen::get_external_devices_api_timeout())
+            .redirect(redirect::Policy::none())
+            .build()
+            .expect("Failed to build HTTP client")
+    };
+
+    // Gateway URLs of greenhouses are given by users, so they may reach only public addresses
+    static ref GATEWAY_CLIENT: Client = {
+        network::build_public_client(garthen::get_external_devices_api_timeout())
+    };
+}
+
+#[derive(Debug, Deserialize)]
+struct TemperatureAndHumidityData {
+    temperature: f64,
+    humidity: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SoilMoistureData {
+    humidity: f64,
+}
+
+// Parts per million
+#[derive(Debug, Deserialize)]
+struct Co2Data {
+    co2: f64,
+}
+
+// Lux
+#[derive(Debug, Deserialize)]
+struct LightData {
+    illuminance: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhData {
+    ph: f64,
+}
+
+// Millisiemens per centimetre
+#[derive(Debug, Deserialize)]
+struct EcData {
+    ec: f64,
+}
+
+// Percent of the tank
+#[derive(Debug, Deserialize)]
+struct WaterLevelData {
+    level: f64,
+}
+
+// Types are named after the paths the devices are reached with
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum InventoryDeviceType {
+    TempHum,
+    Hum,
+    TotalHum,
+    Watering,
+    ForkDrive,
+    Co2,
+    Light,
+    Ph,
+    Ec,
+    WaterLevel,
+}
+
+#[derive(Debug, Deserialize)]
+struct InventoryDevice {
+    #[serde(rename = "type")]
+    device_type: InventoryDeviceType,
+    id: Option<i16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InventoryData {
+    devices: Vec<InventoryDevice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalApiResponse {
+    code: u16,
+}
+
+/// Driver for an HTTP gateway.
+/// Greenhouses without their own gateway URL use `EXTERNAL_DEVICES_API_URL`
+pub struct HttpDeviceDriver {
+    client: Client,
+    url: String,
+    is_public: bool,
+    token: String,
+    auth_scheme: GreenhouseGatewayAuthScheme,
+}
+
+impl HttpDeviceDriver {
+    pub fn new(greenhouse: &Greenhouse) -> Self {
+        let (client, url, is_public) = match &greenhouse.gateway_url {
+            Some(url) => (GATEWAY_CLIENT.clone(), url.to_owned(), true),
+            None => (CLIENT.clone(), garthen::get_external_devices_api_url(), false),
+        };
+
+        HttpDeviceDriver {
+            client,
+            url,
+            is_public,
+            token: greenhouse.token.to_owned(),
+            auth_scheme: greenhouse.gateway_auth_scheme,
+        }
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, WorkerError> {
+        if self.is_public { network::check_public_url(&self.url)?; }
+
+        Ok(match self.auth_scheme {
+            GreenhouseGatewayAuthScheme::Token => request.header("x-auth-token", &self.token),
+            GreenhouseGatewayAuthScheme::Bearer => request.bearer_auth(&self.token),
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: String) -> Result<T, WorkerError> {
+        let request = self.client.get(format!("{}{path}", self.url));
+
+        Ok(self.authorize(request)?.send().await?.json().await?)
+    }
+
+    async fn patch(&self, path: String) -> Result<(), WorkerError> {
+        let request = self.client.patch(format!("{}{path}", self.url));
+        let response: ExternalApiResponse = self.authorize(request)?
+            .send().await?.json().await?;
+
+        match response.code {
+            200 => Ok(()),
+            _ => Err(WorkerErrorTemplate::BadGateway(None).into()),
+        }
+    }
+}
+
+#[async_trait]
+impl DeviceDriver for HttpDeviceDriver {
+    fn get_capabilities(&self) -> DeviceDriverCapabilities {
+        DeviceDriverCapabilities {
+            // DeviceKind::HumiditySensor gets data from the same path
+            sensors: &[
+                (DeviceKind::TemperatureSensor, DeviceKind::TemperatureSensor),
+                (DeviceKind::HumiditySensor, DeviceKind::TemperatureSensor),
+                (DeviceKind::SoilMoistureSensor, DeviceKind::SoilMoistureSensor),
+                (DeviceKind::Co2Sensor, DeviceKind::Co2Sensor),
+                (DeviceKind::LightSensor, DeviceKind::LightSensor),
+                (DeviceKind::PhSensor, DeviceKind::PhSensor),
+                (DeviceKind::EcSensor, DeviceKind::EcSensor),
+                (DeviceKind::WaterLevelSensor, DeviceKind::WaterLevelSensor),
+            ],
+            controllers: &[
+                DeviceKind::HumidificationController,
+                DeviceKind::IrrigationController,
+                DeviceKind::WindowsController,
+            ],
+        }
+    }
+
+    async fn read_sensor(&self, device: &Device) -> Result<Vec<SensorReading>, WorkerError> {
+        let external_id = device.external_id.unwrap_or(1);
+        let data = match device.kind {
+            DeviceKind::TemperatureSensor => {
+                let data: TemperatureAndHumidityData = self
+                    .get(format!("/temp_hum/{external_id}"))
+                    .await?;
+
+                return Ok(vec![
+                    SensorReading {
+                        kind: DeviceKind::TemperatureSensor,
+                        external_id: device.external_id,
+                        data: data.temperature,
+                    },
+                    SensorReading {
+                        kind: DeviceKind::HumiditySensor,
+                        external_id: device.external_id,
+                        data: data.humidity,
+                    },
+                ]);
+            },
+            DeviceKind::SoilMoistureSensor => {
+                let data: SoilMoistureData = self.get(format!("/hum/{external_id}")).await?;
+
+                data.humidity
+            },
+            DeviceKind::Co2Sensor => {
+                let data: Co2Data = self.get(format!("/co2/{external_id}")).await?;
+
+                data.co2
+            },
+            DeviceKind::LightSensor => {
+                let data: LightData = self.get(format!("/light/{external_id}")).await?;
+
+                data.illuminance
+            },
+            DeviceKind::PhSensor => {
+                let data: PhData = self.get(format!("/ph/{external_id}")).await?;
+
+                data.ph
+            },
+            DeviceKind::EcSensor => {
+                let data: EcData = self.get(format!("/ec/{external_id}")).await?;
+
+                data.ec
+            },
+            DeviceKind::WaterLevelSensor => {
+                let data: WaterLevelData = self.get(format!("/water_level/{external_id}")).await?;
+
+                data.level
+            },
+            _ => return Ok(vec![]),
+        };
+
+        Ok(vec![
+            SensorReading {
+                kind: device.kind,
+                external_id: device.external_id,
+                data,
+            },
+        ])
+    }
+
+    async fn set_controller_state(&self, device: &Device, state: u8) -> Result<(), WorkerError> {
+        match device.kind {
+            DeviceKind::HumidificationController => {
+                self.patch(format!("/total_hum?state={state}")).await
+            },
+            DeviceKind::IrrigationController => {
+                self.patch(format!(
+                    "/watering?id={}&state={state}",
+                    device.external_id.unwrap_or(1),
+                )).await
+            },
+            DeviceKind::WindowsController => {
+                self.patch(format!("/fork_drive?state={state}")).await
+            },
+            _ => Err(WorkerErrorTemplate::BadRequest(None).into()),
+        }
+    }
+
+    async fn discover_devices(&self) -> Result<Vec<DiscoveredDevice>, WorkerError> {
+        let request = self.client.get(format!("{}/devices", self.url));
+        let response = self.authorize(request)?.send().await?;
+
+        // Not every gateway has an inventory, that isn't a failure of the gateway
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(WorkerErrorTemplate::NotFound(None).into());
+        }
+
+        let data: InventoryData = response.json().await?;
+        let mut devices = vec![];
+
+        for device in data.devices {
+            let kinds: &[DeviceKind] = match device.device_type {
+                InventoryDeviceType::TempHum => &[
+                    DeviceKind::TemperatureSensor,
+                    DeviceKind::HumiditySensor,
+                ],
+                InventoryDeviceType::Hum => &[DeviceKind::SoilMoistureSensor],
+                InventoryDeviceType::TotalHum => &[DeviceKind::HumidificationController],
+                InventoryDeviceType::Watering => &[DeviceKind::IrrigationController],
+                InventoryDeviceType::ForkDrive => &[DeviceKind::WindowsController],
+                InventoryDeviceType::Co2 => &[DeviceKind::Co2Sensor],
+                InventoryDeviceType::Light => &[DeviceKind::LightSensor],
+                InventoryDeviceType::Ph => &[DeviceKind::PhSensor],
+                InventoryDeviceType::Ec => &[DeviceKind::EcSensor],
+                InventoryDeviceType::WaterLevel => &[DeviceKind::WaterLevelSensor],
+            };
+
+            devices.extend(kinds.iter().map(|&kind| DiscoveredDevice {
+                kind,
+                external_id: device.id,
+            }));
+        }
+
+        Ok(devices)
+    }
+}
@@ -0,0 +1,7 @@
+pub use driver::*;
+pub use http::*;
+pub use resilient::*;
+
+mod driver;
+mod http;
+mod resilient;
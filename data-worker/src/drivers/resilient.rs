@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use lazy_static::lazy_static;
+use tokio::time;
+
+use crate::drivers::{DeviceDriver, DeviceDriverCapabilities, DiscoveredDevice, SensorReading};
+use crate::error::{WorkerError, WorkerErrorTemplate};
+use crate::services::device::Device;
+
+const MAXIMUM_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const CIRCUIT_BREAKER_THRESHOLD: u32 = 5;
+const CIRCUIT_BREAKER_OPEN_DURATION: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    // Greenhouse ID -> Circuit breaker of its gateway
+    static ref CIRCUIT_BREAKERS: Mutex<HashMap<i64, CircuitBreaker>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Default)]
+struct CircuitBreaker {
+    failures: u32,
+    opened_at: Option<Instant>,
+    is_probing: bool,
+}
+
+impl CircuitBreaker {
+    // After the open duration a single call is let through (half-open) while others are refused,
+    // its failure opens the breaker again, its success closes it.
+    // The open duration restarts with the probe, so a probe that never finished is repeated later
+    fn try_acquire(&mut self) -> bool {
+        match self.opened_at {
+            None => true,
+            Some(opened_at) if opened_at.elapsed() < CIRCUIT_BREAKER_OPEN_DURATION => false,
+            Some(_) => {
+                self.opened_at = Some(Instant::now());
+                self.is_probing = true;
+
+                true
+            },
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.failures = 0;
+        self.opened_at = None;
+        self.is_probing = false;
+    }
+
+    fn record_failure(&mut self) {
+        self.failures += 1;
+
+        if self.is_probing || self.failures >= CIRCUIT_BREAKER_THRESHOLD {
+            self.opened_at = Some(Instant::now());
+            self.is_probing = false;
+        }
+    }
+}
+
+// The gateway answering with an unexpected response is reachable, so that isn't retried
+fn is_gateway_failure(error: &WorkerError) -> bool {
+    error.http_code >= 500 && error.http_code != 502
+}
+
+/// Wraps a driver with retries and a circuit breaker shared by all devices of the greenhouse
+pub struct ResilientDeviceDriver {
+    greenhouse_id: i64,
+    driver: Box<dyn DeviceDriver>,
+}
+
+impl ResilientDeviceDriver {
+    pub fn new(greenhouse_id: i64, driver: Box<dyn DeviceDriver>) -> Self {
+        ResilientDeviceDriver { greenhouse_id, driver }
+    }
+
+    async fn call<'a, T, F>(&self, operation: F) -> Result<T, WorkerError>
+        where F: Fn() -> BoxFuture<'a, Result<T, WorkerError>> + Send + Sync {
+        let is_acquired = CIRCUIT_BREAKERS.lock().unwrap()
+            .entry(self.greenhouse_id)
+            .or_default()
+            .try_acquire();
+
+        if !is_acquired { return Err(WorkerErrorTemplate::GatewayUnavailable(None).into()); }
+
+        let mut attempt = 1;
+        let mut backoff = INITIAL_BACKOFF;
+        let result = loop {
+            match operation().await {
+                // Only failures of the gateway itself are worth retrying
+                Err(error) if is_gateway_failure(&error) && attempt < MAXIMUM_ATTEMPTS => {
+                    time::sleep(backoff).await;
+
+                    attempt += 1;
+                    backoff *= 2;
+                },
+                result => break result,
+            }
+        };
+
+        let mut circuit_breakers = CIRCUIT_BREAKERS.lock().unwrap();
+        let circuit_breaker = circuit_breakers.entry(self.greenhouse_id).or_default();
+
+        // Any answer of the gateway shows it's reachable
+        match &result {
+            Err(error) if is_gateway_failure(error) => circuit_breaker.record_failure(),
+            _ => circuit_breaker.record_success(),
+        }
+
+        result
+    }
+}
+
+#[async_trait]
+impl DeviceDriver for ResilientDeviceDriver {
+    fn get_capabilities(&self) -> DeviceDriverCapabilities {
+        self.driver.get_capabilities()
+    }
+
+    async fn read_sensor(&self, device: &Device) -> Result<Vec<SensorReading>, WorkerError> {
+        self.call(|| self.driver.read_sensor(device)).await
+    }
+
+    async fn set_controller_state(&self, device: &Device, state: u8) -> Result<(), WorkerError> {
+        self.call(|| self.driver.set_controller_state(device, state)).await
+    }
+
+    async fn discover_devices(&self) -> Result<Vec<DiscoveredDevice>, WorkerError> {
+        self.call(|| self.driver.discover_devices()).await
+    }
+}
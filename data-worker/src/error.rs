@@ -2,6 +2,9 @@ use std::fmt;
 use std::io::Error;
 
 use diesel::result::Error as DieselError;
+use lettre::address::AddressError as LettreAddressError;
+use lettre::error::Error as LettreError;
+use lettre::transport::smtp::Error as LettreSmtpError;
 use r2d2::Error as R2d2Error;
 use reqwest::Error as ReqwestError;
 use serde::Deserialize;
@@ -11,6 +14,9 @@ use serde_json::Error as SerdeJsonError;
 pub enum WorkerErrorKind {
     StdError(Error),
     DieselError(DieselError),
+    LettreError(LettreError),
+    LettreAddressError(LettreAddressError),
+    LettreSmtpError(LettreSmtpError),
     R2d2Error(R2d2Error),
     ReqwestError(ReqwestError),
     SerdeJsonError(SerdeJsonError),
@@ -67,6 +73,36 @@ impl From<DieselError> for WorkerError {
     }
 }
 
+impl From<LettreError> for WorkerError {
+    fn from(error: LettreError) -> WorkerError {
+        WorkerError::new(
+            500,
+            format!("Lettre error: {error}"),
+            Some(WorkerErrorKind::LettreError(error)),
+        )
+    }
+}
+
+impl From<LettreAddressError> for WorkerError {
+    fn from(error: LettreAddressError) -> WorkerError {
+        WorkerError::new(
+            400,
+            format!("Lettre address error: {error}"),
+            Some(WorkerErrorKind::LettreAddressError(error)),
+        )
+    }
+}
+
+impl From<LettreSmtpError> for WorkerError {
+    fn from(error: LettreSmtpError) -> WorkerError {
+        WorkerError::new(
+            502,
+            format!("Lettre SMTP error: {error}"),
+            Some(WorkerErrorKind::LettreSmtpError(error)),
+        )
+    }
+}
+
 impl From<R2d2Error> for WorkerError {
     fn from(error: R2d2Error) -> WorkerError {
         WorkerError::new(
@@ -79,6 +115,33 @@ impl From<R2d2Error> for WorkerError {
 
 impl From<ReqwestError> for WorkerError {
     fn from(error: ReqwestError) -> Self {
+        if error.is_timeout() {
+            return WorkerErrorTemplate::GatewayTimeout(
+                Some(WorkerErrorKind::ReqwestError(error))
+            ).into();
+        }
+
+        // An answer that can't be used still came from a reachable gateway,
+        // only an answer of a failing gateway and no answer at all are its failures
+        match error.status() {
+            Some(status) if status.is_server_error() => {
+                return WorkerErrorTemplate::GatewayUnavailable(
+                    Some(WorkerErrorKind::ReqwestError(error))
+                ).into();
+            },
+            Some(_) => {
+                return WorkerErrorTemplate::BadGateway(
+                    Some(WorkerErrorKind::ReqwestError(error))
+                ).into();
+            },
+            None if error.is_decode() => {
+                return WorkerErrorTemplate::BadGateway(
+                    Some(WorkerErrorKind::ReqwestError(error))
+                ).into();
+            },
+            None => {},
+        }
+
         WorkerError::new(
             500,
             format!("Reqwest error: {error}"),
@@ -124,5 +187,10 @@ macro_rules! worker_error_template {
 }
 
 worker_error_template! {
+    (400, BadRequest, "Bad request");
     (404, NotFound, "Not found");
+    (502, BadGateway, "Bad gateway");
+    (503, GatewayUnavailable, "Gateway unavailable");
+    (504, GatewayTimeout, "Gateway timeout");
+    (503, SmtpNotConfigured, "SMTP isn't configured");
 }
@@ -1,4 +1,5 @@
 use std::env;
+use std::time::Duration;
 
 use lazy_static::lazy_static;
 
@@ -6,6 +7,31 @@ lazy_static! {
     static ref EXTERNAL_DEVICES_API_URL: String = {
         env::var("EXTERNAL_DEVICES_API_URL").expect("EXTERNAL_DEVICES_API_URL not set")
     };
+
+    static ref EXTERNAL_DEVICES_API_TIMEOUT: Duration = {
+        let timeout = env::var("EXTERNAL_DEVICES_API_TIMEOUT").unwrap_or_else(|_| "10".to_string())
+            .parse::<u64>().expect("EXTERNAL_DEVICES_API_TIMEOUT must be u64");
+
+        Duration::from_secs(timeout)
+    };
+
+    static ref DATA_REQUESTING_CONCURRENCY: usize = {
+        env::var("DATA_REQUESTING_CONCURRENCY").unwrap_or_else(|_| "32".to_string())
+            .parse::<usize>().expect("DATA_REQUESTING_CONCURRENCY must be usize")
+    };
+
+    static ref DEVICE_DISCOVERY_INTERVAL: Duration = {
+        let interval = env::var("DEVICE_DISCOVERY_INTERVAL").unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>().expect("DEVICE_DISCOVERY_INTERVAL must be u64");
+
+        Duration::from_secs(interval)
+    };
+
+    static ref SMTP_URL: Option<String> = env::var("SMTP_URL").ok();
+
+    static ref SMTP_FROM: String = {
+        env::var("SMTP_FROM").unwrap_or_else(|_| "Garthen <garthen@localhost>".to_string())
+    };
 }
 
 pub fn get_external_devices_api_url() -> String
@@ -13,8 +39,38 @@ pub fn get_external_devices_api_url() -> String
     EXTERNAL_DEVICES_API_URL.clone()
 }
 
+pub fn get_external_devices_api_timeout() -> Duration
+{
+    *EXTERNAL_DEVICES_API_TIMEOUT
+}
+
+pub fn get_data_requesting_concurrency() -> usize
+{
+    *DATA_REQUESTING_CONCURRENCY
+}
+
+pub fn get_device_discovery_interval() -> Duration
+{
+    *DEVICE_DISCOVERY_INTERVAL
+}
+
+pub fn get_smtp_url() -> Option<String>
+{
+    SMTP_URL.clone()
+}
+
+pub fn get_smtp_from() -> String
+{
+    SMTP_FROM.clone()
+}
+
 pub fn init() {
     info!("Initialize Garthen Environment Variables");
 
     lazy_static::initialize(&EXTERNAL_DEVICES_API_URL);
+    lazy_static::initialize(&EXTERNAL_DEVICES_API_TIMEOUT);
+    lazy_static::initialize(&DATA_REQUESTING_CONCURRENCY);
+    lazy_static::initialize(&DEVICE_DISCOVERY_INTERVAL);
+    lazy_static::initialize(&SMTP_URL);
+    lazy_static::initialize(&SMTP_FROM);
 }
@@ -4,11 +4,14 @@ extern crate snowflake_generator as snowflake;
 
 use dotenv::dotenv;
 
-use crate::services::{device, device_record};
+use crate::services::{command, device, device_record, processed_message, schedule};
 
 mod amqp_client;
+mod drivers;
 mod error;
 mod garthen;
+mod network;
+mod notifications;
 mod services;
 
 fn main() {
@@ -17,7 +20,6 @@ fn main() {
 
     db::init();
     amqp::init();
-    amqp_client::init();
     snowflake::init();
     garthen::init();
 
@@ -27,8 +29,22 @@ fn main() {
         = device_record::start_data_requesting_with_interval();
     let data_requester_consumer_thread
         = device_record::start_data_request_consumer();
+    let device_records_rolling_up_thread
+        = device_record::start_device_records_rolling_up_with_interval();
+    let device_records_partitioning_thread
+        = device_record::start_device_records_partitioning_with_interval();
     let change_controller_state_consumer_thread
         = device::start_change_controller_state_consumer();
+    let device_discovering_thread
+        = device::start_device_discovering_with_interval();
+    let controller_reverting_thread
+        = device::start_controller_reverting_with_interval();
+    let schedule_executing_thread
+        = schedule::start_schedule_executing_with_interval();
+    let processed_messages_cleaning_thread
+        = processed_message::start_processed_messages_cleaning_with_interval();
+    let commands_sweeping_thread
+        = command::start_commands_sweeping_with_interval();
 
     data_requesting_thread.join()
         .expect("Couldn't join on the data requesting thread")
@@ -36,7 +52,28 @@ fn main() {
     data_requester_consumer_thread.join()
         .expect("Couldn't join on the data-requester consumer thread")
         .expect("Failed to successfully finish data-requester consumer thread");
+    device_records_rolling_up_thread.join()
+        .expect("Couldn't join on the device records rolling up thread")
+        .expect("Failed to successfully finish device records rolling up thread");
+    device_records_partitioning_thread.join()
+        .expect("Couldn't join on the device records partitioning thread")
+        .expect("Failed to successfully finish device records partitioning thread");
     change_controller_state_consumer_thread.join()
         .expect("Couldn't join on the controller-state-changer consumer thread")
         .expect("Failed to successfully finish controller-state-changer consumer thread");
+    device_discovering_thread.join()
+        .expect("Couldn't join on the device discovering thread")
+        .expect("Failed to successfully finish device discovering thread");
+    controller_reverting_thread.join()
+        .expect("Couldn't join on the controller reverting thread")
+        .expect("Failed to successfully finish controller reverting thread");
+    schedule_executing_thread.join()
+        .expect("Couldn't join on the schedule executing thread")
+        .expect("Failed to successfully finish schedule executing thread");
+    processed_messages_cleaning_thread.join()
+        .expect("Couldn't join on the processed messages cleaning thread")
+        .expect("Failed to successfully finish processed messages cleaning thread");
+    commands_sweeping_thread.join()
+        .expect("Couldn't join on the commands sweeping thread")
+        .expect("Failed to successfully finish commands sweeping thread");
 }
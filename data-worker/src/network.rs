@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use domain::network::is_public_ip;
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::{redirect, Client};
+use tokio::task;
+use url::{Host, Url};
+
+use crate::error::{WorkerError, WorkerErrorTemplate};
+
+/// Resolves domains only to public addresses.
+/// URLs are checked when they are saved, but a domain can point somewhere else later
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(resolve_public(name))
+    }
+}
+
+async fn resolve_public(name: Name) -> Result<Addrs, Box<dyn Error + Send + Sync>> {
+    let addresses: Vec<SocketAddr> = task::spawn_blocking(move || (name.as_str(), 0).to_socket_addrs())
+        .await??
+        .filter(|address| is_public_ip(&address.ip()))
+        .collect();
+
+    if addresses.is_empty() { return Err("The domain has no public addresses".into()) }
+
+    Ok(Box::new(addresses.into_iter()))
+}
+
+/// Builds a client for URLs of users.
+/// Redirects aren't followed, they could lead to an internal address
+pub fn build_public_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .redirect(redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+// Hosts that are addresses aren't resolved, so they are checked before every request
+pub fn check_public_url(url: &str) -> Result<(), WorkerError> {
+    let Ok(url) = Url::parse(url) else {
+        return Err(WorkerErrorTemplate::BadRequest(None).into());
+    };
+    let ip: IpAddr = match url.host() {
+        Some(Host::Domain(_)) => return Ok(()),
+        Some(Host::Ipv4(ip)) => ip.into(),
+        Some(Host::Ipv6(ip)) => ip.into(),
+        None => return Err(WorkerErrorTemplate::BadRequest(None).into()),
+    };
+
+    match is_public_ip(&ip) {
+        true => Ok(()),
+        false => Err(WorkerErrorTemplate::BadRequest(None).into()),
+    }
+}
@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::error::WorkerError;
+use crate::notifications::{EmailNotificationChannel, WebhookNotificationChannel};
+use crate::services::alert::{Alert, AlertChannel, AlertChannelKind, AlertChannelModel, AlertKind};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+
+#[derive(Copy, Clone, Debug, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    AlertCreate,
+    AlertResolve,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+    pub event: NotificationEvent,
+    pub greenhouse_name: String,
+    pub alert: Alert,
+}
+
+impl Notification {
+    pub fn get_subject(&self) -> String {
+        let action = match self.event {
+            NotificationEvent::AlertCreate => "Alert",
+            NotificationEvent::AlertResolve => "Resolved",
+        };
+
+        format!("[Garthen] {action} in {}", self.greenhouse_name)
+    }
+
+    pub fn get_text(&self) -> String {
+        let description = match self.alert.kind {
+            AlertKind::DataTooHigh => "The sensor data is above the maximum",
+            AlertKind::AverageHumidityTooHigh => "The average humidity is above the maximum",
+            AlertKind::AverageTemperatureTooLow => "The average temperature is below the minimum",
+        };
+
+        match self.event {
+            NotificationEvent::AlertCreate => format!(
+                "{description}: {:.2} (threshold {:.2}).",
+                self.alert.data,
+                self.alert.threshold,
+            ),
+            NotificationEvent::AlertResolve => format!("{description} no more."),
+        }
+    }
+}
+
+/// Delivers alert notifications to a place outside of Garthen
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<(), WorkerError>;
+}
+
+pub fn get_channel(alert_channel: &AlertChannel) -> Box<dyn NotificationChannel> {
+    let target = alert_channel.target.to_owned();
+
+    match alert_channel.kind {
+        AlertChannelKind::Email => Box::new(EmailNotificationChannel::new(target)),
+        AlertChannelKind::Webhook => Box::new(WebhookNotificationChannel::new(target)),
+    }
+}
+
+/// Sends the notification through every channel of the alert's greenhouse
+pub async fn notify(event: NotificationEvent, alert: Alert) {
+    let Ok(greenhouse) = Greenhouse::find(alert.greenhouse_id) else { return };
+    let Ok(alert_channels)
+        = AlertChannel::find_all_by_greenhouse_id(greenhouse.id) else { return };
+    let notification = Notification {
+        event,
+        greenhouse_name: greenhouse.name,
+        alert,
+    };
+
+    for alert_channel in alert_channels {
+        if let Err(error) = get_channel(&alert_channel).send(&notification).await {
+            warn!("Failed to send notification to alert channel {}: {error}", alert_channel.id);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::error::{WorkerError, WorkerErrorTemplate};
+use crate::garthen;
+use crate::notifications::{Notification, NotificationChannel};
+
+lazy_static! {
+    static ref TRANSPORT: Option<AsyncSmtpTransport<Tokio1Executor>> = {
+        garthen::get_smtp_url().map(|url| {
+            AsyncSmtpTransport::<Tokio1Executor>::from_url(&url)
+                .expect("SMTP_URL must be a valid SMTP URL")
+                .build()
+        })
+    };
+}
+
+pub struct EmailNotificationChannel {
+    address: String,
+}
+
+impl EmailNotificationChannel {
+    pub fn new(address: String) -> Self {
+        EmailNotificationChannel { address }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailNotificationChannel {
+    async fn send(&self, notification: &Notification) -> Result<(), WorkerError> {
+        let Some(transport)
+            = TRANSPORT.as_ref() else { return Err(WorkerErrorTemplate::SmtpNotConfigured(None).into()) };
+
+        let message = Message::builder()
+            .from(garthen::get_smtp_from().parse()?)
+            .to(self.address.parse()?)
+            .subject(notification.get_subject())
+            .body(notification.get_text())?;
+
+        transport.send(message).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::SystemTime;
+
+    use tokio::runtime::Runtime;
+
+    use super::*;
+    use crate::notifications::NotificationEvent;
+    use crate::services::alert::{Alert, AlertKind};
+
+    // Speaks just enough SMTP to accept a single message and returns the conversation
+    fn start_server() -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("smtp://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut conversation = String::new();
+            let mut is_data = false;
+
+            write!(stream, "220 localhost\r\n").unwrap();
+
+            loop {
+                let mut line = String::new();
+
+                if reader.read_line(&mut line).unwrap() == 0 { break; }
+
+                conversation.push_str(&line);
+
+                let reply = match line.trim_end() {
+                    "." if is_data => {
+                        is_data = false;
+
+                        "250 Queued"
+                    },
+                    _ if is_data => continue,
+                    "DATA" => {
+                        is_data = true;
+
+                        "354 Start mail input"
+                    },
+                    "QUIT" => {
+                        write!(stream, "221 Bye\r\n").unwrap();
+
+                        break;
+                    },
+                    _ => "250 OK",
+                };
+
+                write!(stream, "{reply}\r\n").unwrap();
+            }
+
+            conversation
+        });
+
+        (url, server)
+    }
+
+    #[test]
+    fn test_email_delivery() {
+        let (url, server) = start_server();
+
+        // The transport is built from the environment once, this is the only test using it
+        env::set_var("SMTP_URL", url);
+
+        let notification = Notification {
+            event: NotificationEvent::AlertResolve,
+            greenhouse_name: "Greenhouse".to_string(),
+            alert: Alert {
+                id: 1,
+                greenhouse_id: 2,
+                device_id: None,
+                kind: AlertKind::AverageHumidityTooHigh,
+                data: 85.0,
+                threshold: 80.0,
+                created_at: SystemTime::now(),
+                resolved_at: Some(SystemTime::now()),
+            },
+        };
+        let result = Runtime::new().unwrap().block_on(async {
+            let result = EmailNotificationChannel::new("owner@example.com".to_string())
+                .send(&notification).await;
+
+            if let Some(transport) = TRANSPORT.as_ref() { transport.shutdown().await; }
+
+            result
+        });
+        let conversation = server.join().unwrap();
+
+        assert!(result.is_ok());
+        assert!(conversation.contains("RCPT TO:<owner@example.com>"));
+        assert!(conversation.contains("Subject: [Garthen] Resolved in Greenhouse"));
+        assert!(conversation.contains("The average humidity is above the maximum no more."));
+    }
+}
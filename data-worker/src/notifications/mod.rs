@@ -0,0 +1,7 @@
+pub use channel::*;
+pub use email::*;
+pub use webhook::*;
+
+mod channel;
+mod email;
+mod webhook;
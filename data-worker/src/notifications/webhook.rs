@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use reqwest::Client;
+
+use crate::error::WorkerError;
+use crate::network;
+use crate::notifications::{Notification, NotificationChannel};
+
+lazy_static! {
+    // Webhook URLs are given by users, so they may reach only public addresses
+    static ref CLIENT: Client = network::build_public_client(Duration::from_secs(10));
+}
+
+/// Posts notifications as JSON to the URL
+pub struct WebhookNotificationChannel {
+    client: Client,
+    url: String,
+    is_public: bool,
+}
+
+impl WebhookNotificationChannel {
+    pub fn new(url: String) -> Self {
+        WebhookNotificationChannel {
+            client: CLIENT.clone(),
+            url,
+            is_public: true,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookNotificationChannel {
+    async fn send(&self, notification: &Notification) -> Result<(), WorkerError> {
+        if self.is_public { network::check_public_url(&self.url)?; }
+
+        self.client.post(&self.url)
+            .json(notification)
+            .send().await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::SystemTime;
+
+    use tokio::runtime::Runtime;
+
+    use super::*;
+    use crate::notifications::NotificationEvent;
+    use crate::services::alert::{Alert, AlertKind};
+
+    fn get_notification() -> Notification {
+        Notification {
+            event: NotificationEvent::AlertCreate,
+            greenhouse_name: "Greenhouse".to_string(),
+            alert: Alert {
+                id: 1,
+                greenhouse_id: 2,
+                device_id: Some(3),
+                kind: AlertKind::DataTooHigh,
+                data: 42.0,
+                threshold: 40.0,
+                created_at: SystemTime::now(),
+                resolved_at: None,
+            },
+        }
+    }
+
+    // Test servers are local, which the channel of a user can't reach
+    fn get_local_channel(url: String) -> WebhookNotificationChannel {
+        WebhookNotificationChannel {
+            client: Client::new(),
+            url,
+            is_public: false,
+        }
+    }
+
+    // Answers a single request with the status and returns the request
+    fn start_server(status: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 4096];
+
+            // The body is the last part of the request, it's complete once it has the whole JSON
+            while !request.ends_with(b"}") {
+                let length = stream.read(&mut buffer).unwrap();
+
+                if length == 0 { break; }
+
+                request.extend_from_slice(&buffer[..length]);
+            }
+
+            write!(stream, "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        (url, server)
+    }
+
+    #[test]
+    fn test_webhook_delivery() {
+        let (url, server) = start_server("200 OK");
+        let result = Runtime::new().unwrap()
+            .block_on(get_local_channel(url).send(&get_notification()));
+        let request = server.join().unwrap();
+
+        assert!(result.is_ok());
+        assert!(request.starts_with("POST /hook HTTP/1.1"));
+        assert!(request.contains(r#""event":"alert_create""#));
+        assert!(request.contains(r#""greenhouse_name":"Greenhouse""#));
+    }
+
+    #[test]
+    fn test_webhook_delivery_failure() {
+        let (url, server) = start_server("500 Internal Server Error");
+        let result = Runtime::new().unwrap()
+            .block_on(get_local_channel(url).send(&get_notification()));
+
+        server.join().unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_webhook_local_address() {
+        let runtime = Runtime::new().unwrap();
+
+        for url in ["http://127.0.0.1:8080/hook", "http://[::1]/hook", "http://localhost/hook"] {
+            let result = runtime
+                .block_on(WebhookNotificationChannel::new(url.to_string()).send(&get_notification()));
+
+            assert!(result.is_err());
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub use model::*;
+pub use monitor::*;
+
+mod model;
+mod monitor;
@@ -0,0 +1,112 @@
+pub use domain::alert::{Alert, AlertChannel, AlertChannelKind, AlertKind};
+
+use std::time::SystemTime;
+
+use db::schema::{alert_channels, alerts};
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+
+use crate::error::WorkerError;
+use crate::services::greenhouse::Threshold;
+
+pub trait AlertModel: Sized {
+    fn create(alert: NewAlert) -> Result<Self, WorkerError>;
+    fn find_open(
+        greenhouse_id: i64,
+        kind: AlertKind,
+        device_id: Option<i64>,
+    ) -> Result<Self, WorkerError>;
+    fn resolve(id: i64) -> Result<Self, WorkerError>;
+}
+
+impl AlertModel for Alert {
+    fn create(alert: NewAlert) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let alert = Alert {
+            id: snowflake::generate(),
+            greenhouse_id: alert.greenhouse_id,
+            device_id: alert.device_id,
+            kind: alert.kind,
+            data: alert.data,
+            threshold: alert.threshold,
+            created_at: SystemTime::now(),
+            resolved_at: None,
+        };
+
+        let alert = diesel::insert_into(alerts::table)
+            .values(alert)
+            .get_result(connection)?;
+
+        Ok(alert)
+    }
+
+    fn find_open(
+        greenhouse_id: i64,
+        kind: AlertKind,
+        device_id: Option<i64>,
+    ) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let mut query = alerts::table
+            .filter(alerts::greenhouse_id.eq(greenhouse_id))
+            .filter(alerts::kind.eq(kind))
+            .filter(alerts::resolved_at.is_null())
+            .into_boxed();
+
+        query = match device_id {
+            Some(device_id) => query.filter(alerts::device_id.eq(device_id)),
+            None => query.filter(alerts::device_id.is_null()),
+        };
+
+        let alert = query.first(connection)?;
+
+        Ok(alert)
+    }
+
+    fn resolve(id: i64) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let alert = diesel::update(alerts::table)
+            .filter(alerts::id.eq(id))
+            .filter(alerts::resolved_at.is_null())
+            .set(alerts::resolved_at.eq(SystemTime::now()))
+            .get_result(connection)?;
+
+        Ok(alert)
+    }
+}
+
+pub struct NewAlert {
+    pub greenhouse_id: i64,
+    pub device_id: Option<i64>,
+    pub kind: AlertKind,
+    pub data: f64,
+    pub threshold: f64,
+}
+
+impl From<Threshold> for AlertKind {
+    fn from(threshold: Threshold) -> Self {
+        match threshold {
+            Threshold::MaximumDataValue => AlertKind::DataTooHigh,
+            Threshold::MaximumAverageHumidity => AlertKind::AverageHumidityTooHigh,
+            Threshold::MinimumAverageTemperature => AlertKind::AverageTemperatureTooLow,
+        }
+    }
+}
+
+pub trait AlertChannelModel: Sized {
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WorkerError>;
+}
+
+impl AlertChannelModel for AlertChannel {
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let alert_channels = alert_channels::table
+            .filter(alert_channels::greenhouse_id.eq(greenhouse_id))
+            .load(connection)?;
+
+        Ok(alert_channels)
+    }
+}
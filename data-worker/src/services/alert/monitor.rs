@@ -0,0 +1,57 @@
+use amqp::routes::{AlertCreated, AlertResolved};
+use tokio::task;
+
+use crate::{amqp_client, notifications};
+use crate::amqp_client::{DispatchAlertCreate, DispatchAlertResolve};
+use crate::notifications::NotificationEvent;
+use crate::services::alert::{Alert, AlertKind, AlertModel, NewAlert};
+use crate::services::device::Device;
+use crate::services::greenhouse::{Threshold, ThresholdCheck};
+
+/// Opens an incident when the threshold is crossed and resolves it once the threshold is cleared
+pub async fn evaluate(sensor: &Device, check: &ThresholdCheck) {
+    let kind = AlertKind::from(check.threshold);
+    let device_id = match check.threshold {
+        Threshold::MaximumDataValue => Some(sensor.id),
+        _ => None,
+    };
+
+    let (alert, event) = match Alert::find_open(sensor.greenhouse_id, kind, device_id) {
+        Ok(alert) if check.is_cleared => {
+            let Ok(alert) = Alert::resolve(alert.id) else { return };
+
+            amqp_client::publish::<AlertResolved>(
+                DispatchAlertResolve {
+                    id: alert.id,
+                    greenhouse_id: alert.greenhouse_id,
+                },
+                None,
+            ).await;
+
+            (alert, NotificationEvent::AlertResolve)
+        },
+        Err(error) if error.http_code == 404 && check.is_crossed => {
+            // Fails if another reading has just opened the same incident
+            let Ok(alert) = Alert::create(NewAlert {
+                greenhouse_id: sensor.greenhouse_id,
+                device_id,
+                kind,
+                data: check.data,
+                threshold: check.limit,
+            }) else { return };
+
+            amqp_client::publish::<AlertCreated>(
+                DispatchAlertCreate {
+                    id: alert.id,
+                    greenhouse_id: alert.greenhouse_id,
+                },
+                None,
+            ).await;
+
+            (alert, NotificationEvent::AlertCreate)
+        },
+        _ => return,
+    };
+
+    task::spawn(notifications::notify(event, alert));
+}
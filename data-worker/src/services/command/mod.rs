@@ -0,0 +1,7 @@
+pub use model::*;
+pub use status::*;
+pub use threads::*;
+
+mod model;
+mod status;
+mod threads;
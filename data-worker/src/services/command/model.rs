@@ -0,0 +1,93 @@
+pub use domain::command::{Command, CommandStatus};
+
+use std::time::SystemTime;
+
+use db::schema::commands;
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+
+use crate::error::WorkerError;
+
+pub trait CommandModel: Sized {
+    fn create(command: NewCommand) -> Result<Self, WorkerError>;
+    fn find(id: i64) -> Result<Self, WorkerError>;
+    fn find_all_pending_before(time: SystemTime) -> Result<Vec<Self>, WorkerError>;
+    fn update_status(id: i64, new_status: CommandStatus) -> Result<Self, WorkerError>;
+    fn delete_all_finished_before(time: SystemTime) -> Result<usize, WorkerError>;
+}
+
+impl CommandModel for Command {
+    fn create(command: NewCommand) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
+        let now = SystemTime::now();
+
+        let command = Command {
+            id: snowflake::generate(),
+            device_id: command.device_id,
+            state: command.state,
+            status: CommandStatus::Pending,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let command = diesel::insert_into(commands::table)
+            .values(&command)
+            .get_result(connection)?;
+
+        Ok(command)
+    }
+
+    fn find(id: i64) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let command = commands::table
+            .filter(commands::id.eq(id))
+            .first(connection)?;
+
+        Ok(command)
+    }
+
+    fn find_all_pending_before(time: SystemTime) -> Result<Vec<Self>, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let commands = commands::table
+            .filter(commands::status.eq(CommandStatus::Pending))
+            .filter(commands::created_at.lt(time))
+            .load(connection)?;
+
+        Ok(commands)
+    }
+
+    // Only a pending command is updated, so the sweeper and the consumer can't both finish it
+    fn update_status(id: i64, new_status: CommandStatus) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let command = diesel::update(commands::table)
+            .filter(commands::id.eq(id))
+            .filter(commands::status.eq(CommandStatus::Pending))
+            .set((
+                commands::status.eq(new_status),
+                commands::updated_at.eq(SystemTime::now()),
+            ))
+            .get_result(connection)?;
+
+        Ok(command)
+    }
+
+    fn delete_all_finished_before(time: SystemTime) -> Result<usize, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let result = diesel::delete(
+            commands::table
+                .filter(commands::status.ne(CommandStatus::Pending))
+                .filter(commands::updated_at.lt(time))
+        ).execute(connection)?;
+
+        Ok(result)
+    }
+}
+
+pub struct NewCommand {
+    pub device_id: i64,
+    pub state: i16,
+}
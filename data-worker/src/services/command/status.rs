@@ -0,0 +1,20 @@
+use amqp::routes::DeviceCommandFinished;
+
+use crate::amqp_client;
+use crate::amqp_client::DispatchCommand;
+use crate::services::command::{Command, CommandModel, CommandStatus};
+
+/// Stores the outcome of the command and dispatches it to the requester
+pub async fn update_status(command: &Command, new_status: CommandStatus) {
+    if command.status != CommandStatus::Pending { return; }
+
+    if Command::update_status(command.id, new_status).is_ok() {
+        amqp_client::publish::<DeviceCommandFinished>(
+            DispatchCommand {
+                id: command.id,
+                device_id: command.device_id,
+            },
+            None,
+        ).await;
+    }
+}
@@ -0,0 +1,46 @@
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use tokio::runtime::Runtime;
+use tokio::time;
+use tokio::time::MissedTickBehavior;
+
+use crate::error::WorkerError;
+use crate::services::command;
+use crate::services::command::{Command, CommandModel, CommandStatus};
+
+// Commands that waited in the queue for longer are not applied anymore
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+// Requesters get the outcome right away, finished commands are kept for a while for history
+const COMMAND_RETENTION: Duration = Duration::from_secs(86400 * 7);
+
+pub fn start_commands_sweeping_with_interval() -> JoinHandle<Result<(), WorkerError>> {
+    info!("Starting commands sweeping thread");
+
+    let runtime = Runtime::new().unwrap();
+
+    thread::spawn(move || -> Result<(), WorkerError> {
+        runtime.block_on(async move {
+            let mut interval = time::interval(COMMAND_TIMEOUT);
+
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let now = SystemTime::now();
+
+                // Messages of these commands were lost or are still waiting in the queue,
+                // the consumer skips the latter once they are timed out
+                if let Ok(commands) = Command::find_all_pending_before(now - COMMAND_TIMEOUT) {
+                    for command in commands {
+                        command::update_status(&command, CommandStatus::Timeout).await;
+                    }
+                }
+
+                let _ = Command::delete_all_finished_before(now - COMMAND_RETENTION);
+            }
+        })
+    })
+}
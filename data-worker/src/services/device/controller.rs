@@ -0,0 +1,29 @@
+use amqp::routes::DeviceControllerStateChange;
+
+use crate::amqp_client;
+use crate::amqp_client::ChangeControllerState;
+use crate::services::command::{Command, CommandModel, NewCommand};
+use crate::services::device_record::{DeviceRecord, DeviceRecordModel};
+
+/// Asks the controller consumer to change the state, unless the controller is already in it.
+/// With a duration the controller gets its previous state back once the duration is over
+pub async fn change_controller_state(controller_id: i64, state: u8, duration: Option<u32>) {
+    if let (Ok(record), None) = (DeviceRecord::find_latest_by_device_id(controller_id), duration) {
+        if record.data == f64::from(state) { return; }
+    }
+
+    let Ok(command) = Command::create(NewCommand {
+        device_id: controller_id,
+        state: i16::from(state),
+    }) else { return };
+
+    amqp_client::publish::<DeviceControllerStateChange>(
+        ChangeControllerState {
+            command_id: command.id,
+            device_id: controller_id,
+            state,
+            duration,
+        },
+        None,
+    ).await;
+}
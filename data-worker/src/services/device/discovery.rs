@@ -0,0 +1,110 @@
+use std::thread;
+use std::thread::JoinHandle;
+
+use amqp::routes::DeviceCreated;
+use futures::future::join_all;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task;
+use tokio::time;
+use tokio::time::MissedTickBehavior;
+
+use crate::{amqp_client, drivers, garthen};
+use crate::amqp_client::DispatchDeviceCreate;
+use crate::drivers::DiscoveredDevice;
+use crate::error::WorkerError;
+use crate::services::device;
+use crate::services::device::{Device, DeviceKind, DeviceModel, DeviceStatus, NewDevice};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+
+fn is_discovered(discovered_device: &DiscoveredDevice, kind: DeviceKind, external_id: Option<i16>) -> bool {
+    discovered_device.kind == kind && discovered_device.external_id == external_id
+}
+
+async fn discover(greenhouse: Greenhouse) {
+    let driver = drivers::get_driver(&greenhouse);
+    // Gateways without an inventory are left as they are
+    let Ok(discovered_devices) = driver.discover_devices().await else { return };
+    let greenhouse_id = greenhouse.id;
+    let Ok(Ok((devices, ignored_devices))) = task::spawn_blocking(move || {
+        Ok::<_, WorkerError>((
+            Device::find_all_by_greenhouse_id(greenhouse_id)?,
+            Device::find_all_ignored_by_greenhouse_id(greenhouse_id)?,
+        ))
+    }).await else { return };
+
+    for discovered_device in &discovered_devices {
+        let device = devices.iter().find(|&device|
+            is_discovered(discovered_device, device.kind, device.external_id)
+        );
+
+        match device {
+            // A device that vanished before is back, it becomes online with its next reading
+            Some(device) => device::set_vanished(device, false),
+            None => {
+                // Devices deleted by the owner are created only by the owner
+                if ignored_devices.iter().any(|ignored_device|
+                    is_discovered(discovered_device, ignored_device.kind, ignored_device.external_id)
+                ) { continue; }
+
+                let new_device = NewDevice {
+                    external_id: discovered_device.external_id,
+                    kind: discovered_device.kind,
+                    greenhouse_id,
+                };
+                let Ok(Ok(device))
+                    = task::spawn_blocking(move || Device::create(new_device)).await else { continue };
+
+                amqp_client::publish::<DeviceCreated>(
+                    DispatchDeviceCreate {
+                        id: device.id,
+                        greenhouse_id: device.greenhouse_id,
+                    },
+                    None,
+                ).await;
+            },
+        }
+    }
+
+    let vanished_devices = devices.iter().filter(|&device|
+        !discovered_devices.iter().any(|discovered_device|
+            is_discovered(discovered_device, device.kind, device.external_id)
+        )
+    );
+
+    for device in vanished_devices {
+        device::set_vanished(device, true);
+        device::update_status(device, DeviceStatus::Offline).await;
+    }
+}
+
+pub fn start_device_discovering_with_interval() -> JoinHandle<Result<(), WorkerError>> {
+    info!("Starting device discovering thread");
+
+    let runtime = Runtime::new().unwrap();
+
+    thread::spawn(move || -> Result<(), WorkerError> {
+        runtime.block_on(async move {
+            let mut interval = time::interval(garthen::get_device_discovery_interval());
+            let permits = Semaphore::new(garthen::get_data_requesting_concurrency());
+
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let greenhouses = match task::spawn_blocking(Greenhouse::find_all).await {
+                    Ok(Ok(greenhouses)) => greenhouses,
+                    _ => continue,
+                };
+
+                // Gateways are asked at the same time, so a slow gateway doesn't hold up others
+                join_all(greenhouses.into_iter().map(|greenhouse| async {
+                    let _permit = permits.acquire().await.unwrap();
+
+                    discover(greenhouse).await;
+                })).await;
+            }
+        })
+    })
+}
@@ -1,5 +1,11 @@
+pub use controller::*;
+pub use discovery::*;
 pub use model::*;
+pub use status::*;
 pub use threads::*;
 
+mod controller;
+mod discovery;
 mod model;
+mod status;
 mod threads;
@@ -1,75 +1,66 @@
-use std::mem::transmute;
+pub use domain::device::{Device, DeviceKind, DeviceStatus, IgnoredDevice};
+
 use std::time::SystemTime;
 
-use db::schema::devices;
-use diesel::{deserialize, ExpressionMethods, Insertable, Queryable, RunQueryDsl};
-use diesel::deserialize::FromStaticSqlRow;
-use diesel::expression::AsExpression;
-use diesel::helper_types::AsExprOf;
-use diesel::pg::Pg;
+use db::schema::{devices, ignored_devices};
+use diesel::{ExpressionMethods, RunQueryDsl};
 use diesel::prelude::*;
-use diesel::row::Row;
-use diesel::sql_types::SmallInt;
-use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::error::WorkerError;
 
-#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
-#[diesel(table_name = devices)]
-pub struct Device {
-    pub id: i64,
-    pub external_id: Option<i16>,
-    pub name: Option<String>,
-    pub status: DeviceStatus,
-    pub kind: DeviceKind,
-    pub greenhouse_id: i64,
-    pub created_at: SystemTime,
-    pub maximum_data_value: Option<f64>,
+pub trait DeviceModel: Sized {
+    fn create(device: NewDevice) -> Result<Self, WorkerError>;
+    fn find(id: i64) -> Result<Self, WorkerError>;
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WorkerError>;
+    fn find_all_ignored_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<IgnoredDevice>, WorkerError>;
+    fn find_all_by_revert_at_before(time: SystemTime) -> Result<Vec<Self>, WorkerError>;
+    fn update_revert(
+        id: i64,
+        new_revert_state: Option<i16>,
+        new_revert_at: Option<SystemTime>,
+    ) -> Result<Self, WorkerError>;
+    fn update_status(id: i64, new_status: DeviceStatus) -> Result<Self, WorkerError>;
 }
 
-impl Device {
-    pub fn find(id: i64) -> Result<Self, WorkerError> {
+impl DeviceModel for Device {
+    fn create(device: NewDevice) -> Result<Self, WorkerError> {
         let connection = &mut db::get_connection()?;
 
-        let device = devices::table
-            .filter(devices::id.eq(id))
-            .first(connection)?;
+        let device = Device {
+            id: snowflake::generate(),
+            external_id: device.external_id,
+            name: None,
+            // The device becomes online with its first reading
+            status: DeviceStatus::Offline,
+            kind: device.kind,
+            greenhouse_id: device.greenhouse_id,
+            created_at: SystemTime::now(),
+            maximum_data_value: None,
+            polling_interval: 60,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            revert_state: None,
+            revert_at: None,
+        };
+
+        let device = diesel::insert_into(devices::table)
+            .values(&device)
+            .get_result(connection)?;
 
         Ok(device)
     }
 
-    pub fn find_humidity_sensor_by_external_id_and_greenhouse_id(
-        external_id: Option<i16>,
-        greenhouse_id: i64,
-    ) -> Result<Self, WorkerError> {
+    fn find(id: i64) -> Result<Self, WorkerError> {
         let connection = &mut db::get_connection()?;
 
         let device = devices::table
-            .filter(devices::external_id.eq(external_id))
-            .filter(devices::kind.eq(DeviceKind::HumiditySensor))
-            .filter(devices::greenhouse_id.eq(greenhouse_id))
-            .first(connection)?;
-
-        Ok(device)
-    }
-
-    pub fn find_temperature_sensor_by_external_id_and_greenhouse_id(
-        external_id: Option<i16>,
-        greenhouse_id: i64,
-    ) -> Result<Self, WorkerError> {
-        let connection = &mut db::get_connection()?;
-
-        let device = devices::table
-            .filter(devices::external_id.eq(external_id))
-            .filter(devices::kind.eq(DeviceKind::TemperatureSensor))
-            .filter(devices::greenhouse_id.eq(greenhouse_id))
+            .filter(devices::id.eq(id))
             .first(connection)?;
 
         Ok(device)
     }
 
-    pub fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WorkerError> {
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WorkerError> {
         let connection = &mut db::get_connection()?;
 
         let devices = devices::table
@@ -78,83 +69,62 @@ impl Device {
 
         Ok(devices)
     }
-}
 
-#[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
-#[repr(i16)]
-pub enum DeviceStatus {
-    Offline = 0,
-    Online = 1,
-    Disabled = 2,
-}
-
-impl FromStaticSqlRow<SmallInt, Pg> for DeviceStatus {
-    fn build_from_row<'a>(row: &impl Row<'a, Pg>) -> deserialize::Result<Self> {
-        Ok(unsafe { transmute(i16::build_from_row(row)?) })
-    }
-}
+    fn find_all_ignored_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<IgnoredDevice>, WorkerError> {
+        let connection = &mut db::get_connection()?;
 
-impl AsExpression<SmallInt> for DeviceStatus {
-    type Expression = AsExprOf<i16, SmallInt>;
+        let ignored_devices = ignored_devices::table
+            .filter(ignored_devices::greenhouse_id.eq(greenhouse_id))
+            .load(connection)?;
 
-    fn as_expression(self) -> Self::Expression {
-        <i16 as AsExpression<SmallInt>>::as_expression(self as i16)
+        Ok(ignored_devices)
     }
-}
-
-impl<'a> AsExpression<SmallInt> for &'a DeviceStatus {
-    type Expression = AsExprOf<i16, SmallInt>;
 
-    fn as_expression(self) -> Self::Expression {
-        <i16 as AsExpression<SmallInt>>::as_expression(*self as i16)
-    }
-}
+    fn find_all_by_revert_at_before(time: SystemTime) -> Result<Vec<Self>, WorkerError> {
+        let connection = &mut db::get_connection()?;
 
-impl Queryable<SmallInt, Pg> for DeviceStatus {
-    type Row = i16;
+        let devices = devices::table
+            .filter(devices::revert_at.le(time))
+            .load(connection)?;
 
-    fn build(row: Self::Row) -> deserialize::Result<Self> {
-        Ok(unsafe { transmute(row) })
+        Ok(devices)
     }
-}
-
-#[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
-#[repr(i16)]
-pub enum DeviceKind {
-    HumiditySensor = 0,
-    SoilMoistureSensor = 1,
-    TemperatureSensor = 2,
-    HumidificationController = 3,
-    IrrigationController = 4,
-    WindowsController = 5,
-}
 
-impl FromStaticSqlRow<SmallInt, Pg> for DeviceKind {
-    fn build_from_row<'a>(row: &impl Row<'a, Pg>) -> deserialize::Result<Self> {
-        Ok(unsafe { transmute(i16::build_from_row(row)?) })
-    }
-}
+    fn update_revert(
+        id: i64,
+        new_revert_state: Option<i16>,
+        new_revert_at: Option<SystemTime>,
+    ) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
 
-impl AsExpression<SmallInt> for DeviceKind {
-    type Expression = AsExprOf<i16, SmallInt>;
+        let device = diesel::update(devices::table)
+            .filter(devices::id.eq(id))
+            .set((
+                devices::revert_state.eq(new_revert_state),
+                devices::revert_at.eq(new_revert_at),
+            ))
+            .get_result(connection)?;
 
-    fn as_expression(self) -> Self::Expression {
-        <i16 as AsExpression<SmallInt>>::as_expression(self as i16)
+        Ok(device)
     }
-}
 
-impl<'a> AsExpression<SmallInt> for &'a DeviceKind {
-    type Expression = AsExprOf<i16, SmallInt>;
+    // Disabled devices and devices that already have the status aren't updated
+    fn update_status(id: i64, new_status: DeviceStatus) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let device = diesel::update(devices::table)
+            .filter(devices::id.eq(id))
+            .filter(devices::status.ne(DeviceStatus::Disabled))
+            .filter(devices::status.ne(new_status))
+            .set(devices::status.eq(new_status))
+            .get_result(connection)?;
 
-    fn as_expression(self) -> Self::Expression {
-        <i16 as AsExpression<SmallInt>>::as_expression(*self as i16)
+        Ok(device)
     }
 }
 
-impl Queryable<SmallInt, Pg> for DeviceKind {
-    type Row = i16;
-
-    fn build(row: Self::Row) -> deserialize::Result<Self> {
-        Ok(unsafe { transmute(row) })
-    }
+pub struct NewDevice {
+    pub external_id: Option<i16>,
+    pub kind: DeviceKind,
+    pub greenhouse_id: i64,
 }
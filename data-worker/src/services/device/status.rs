@@ -0,0 +1,34 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use amqp::routes::DeviceStatusChanged;
+use lazy_static::lazy_static;
+
+use crate::amqp_client;
+use crate::amqp_client::DispatchDevice;
+use crate::services::device::{Device, DeviceModel, DeviceStatus};
+
+lazy_static! {
+    // IDs of devices that the inventory of their gateway doesn't list anymore
+    static ref VANISHED_DEVICES: Mutex<HashSet<i64>> = Mutex::new(HashSet::new());
+}
+
+pub fn set_vanished(device: &Device, is_vanished: bool) {
+    let mut vanished_devices = VANISHED_DEVICES.lock().unwrap();
+
+    match is_vanished {
+        true => vanished_devices.insert(device.id),
+        false => vanished_devices.remove(&device.id),
+    };
+}
+
+// Vanished devices stay offline until discovery finds them again
+pub async fn update_status(device: &Device, new_status: DeviceStatus) {
+    if device.status == new_status || device.status == DeviceStatus::Disabled { return; }
+    if new_status == DeviceStatus::Online
+        && VANISHED_DEVICES.lock().unwrap().contains(&device.id) { return; }
+
+    if Device::update_status(device.id, new_status).is_ok() {
+        amqp_client::publish::<DeviceStatusChanged>(DispatchDevice { id: device.id }, None).await;
+    }
+}
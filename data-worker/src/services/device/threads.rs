@@ -1,24 +1,135 @@
-use std::str::from_utf8;
+use std::collections::HashMap;
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 
-use futures::executor::block_on;
-use futures::StreamExt;
-use lapin::options::{BasicAckOptions, BasicConsumeOptions};
-use lapin::types::FieldTable;
-use serde::Deserialize;
-use tokio::runtime::Runtime;
-
-use crate::{amqp_client, garthen};
-use crate::amqp_client::{AmqpPayload, AmqpPublisherMessage};
-use crate::error::WorkerError;
-use crate::services::device::{Device, DeviceKind};
-use crate::services::device_record::{DeviceRecord, NewDeviceRecord};
-use crate::services::greenhouse::Greenhouse;
-
-#[derive(Debug, Deserialize)]
-struct ExternalApiResponse {
-    code: u16,
+use amqp::routes::{ChangeControllerStateQueue, DeviceControllerStateChanged};
+use tokio::runtime::{Handle, Runtime};
+use tokio::task;
+use tokio::time;
+use tokio::time::MissedTickBehavior;
+
+use crate::{amqp_client, drivers};
+use crate::amqp_client::{ChangeControllerState, DispatchDevice};
+use crate::error::{WorkerError, WorkerErrorTemplate};
+use crate::services::{command, device};
+use crate::services::command::{Command, CommandModel, CommandStatus, COMMAND_TIMEOUT};
+use crate::services::device::{change_controller_state, Device, DeviceModel, DeviceStatus};
+use crate::services::device_record::{DeviceRecord, DeviceRecordModel, NewDeviceRecord};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+
+// Any state set without a duration cancels the pending revert. A pending revert keeps its state,
+// the current one may be a timed state itself, a timed state set meanwhile only moves the revert
+fn get_revert(
+    device: &Device,
+    duration: Option<u32>,
+    now: SystemTime,
+    get_current_state: impl FnOnce() -> i16,
+) -> Option<(i16, SystemTime)> {
+    let duration = duration?;
+    let revert_state = match (device.revert_state, device.revert_at) {
+        (Some(revert_state), Some(_)) => revert_state,
+        _ => get_current_state(),
+    };
+
+    Some((revert_state, now + Duration::from_secs(duration as u64)))
+}
+
+async fn apply_controller_state(
+    message_id: i64,
+    command_id: i64,
+    device_id: i64,
+    state: u8,
+    duration: Option<u32>,
+) -> Result<(), WorkerError> {
+    let command = match Command::find(command_id) {
+        Ok(command) => command,
+        // The command is deleted along with its device
+        Err(error) if error.http_code == 404 => return Ok(()),
+        Err(error) => return Err(error),
+    };
+
+    // A redelivered command could have been applied before
+    if command.status != CommandStatus::Pending { return Ok(()); }
+
+    let remaining_time = match SystemTime::now().duration_since(command.created_at) {
+        Ok(elapsed) => COMMAND_TIMEOUT.checked_sub(elapsed).filter(|remaining| !remaining.is_zero()),
+        Err(_) => Some(COMMAND_TIMEOUT),
+    };
+    let Some(remaining_time) = remaining_time else {
+        command::update_status(&command, CommandStatus::Timeout).await;
+
+        return Ok(());
+    };
+
+    let device_and_greenhouse = Device::find(device_id)
+        .and_then(|device| Ok((Greenhouse::find(device.greenhouse_id)?, device)));
+    let (greenhouse, device) = match device_and_greenhouse {
+        Ok(device_and_greenhouse) => device_and_greenhouse,
+        Err(error) if error.http_code == 404 => {
+            command::update_status(&command, CommandStatus::Failed).await;
+
+            return Ok(());
+        },
+        Err(error) => return Err(error),
+    };
+    let driver = drivers::get_driver(&greenhouse);
+
+    if !driver.get_capabilities().is_controllable(device.kind) || !device.kind.is_valid_state(&state) {
+        command::update_status(&command, CommandStatus::Failed).await;
+
+        return Ok(());
+    }
+
+    // Retries of the gateway can take longer than the command has left,
+    // the sweeper would time it out meanwhile and the outcome couldn't be stored anymore
+    let result = match time::timeout(remaining_time, driver.set_controller_state(&device, state)).await {
+        Ok(result) => result,
+        Err(_) => Err(WorkerErrorTemplate::GatewayTimeout(None).into()),
+    };
+
+    if let Err(error) = result {
+        device::update_status(&device, DeviceStatus::Offline).await;
+
+        let status = match error.http_code {
+            504 => CommandStatus::Timeout,
+            _ => CommandStatus::Failed,
+        };
+
+        command::update_status(&command, status).await;
+
+        return Ok(());
+    }
+
+    device::update_status(&device, DeviceStatus::Online).await;
+    command::update_status(&command, CommandStatus::Applied).await;
+
+    let revert = get_revert(&device, duration, SystemTime::now(), || {
+        match DeviceRecord::find_latest_by_device_id(device_id) {
+            Ok(record) => record.data.round() as i16,
+            Err(_) => 0,
+        }
+    });
+
+    if revert.is_some() || device.revert_at.is_some() {
+        let _ = Device::update_revert(
+            device_id,
+            revert.map(|(revert_state, _)| revert_state),
+            revert.map(|(_, revert_at)| revert_at),
+        );
+    }
+
+    if DeviceRecord::create(NewDeviceRecord {
+        device_id,
+        data: state as f64,
+    }).is_ok() {
+        amqp_client::publish::<DeviceControllerStateChanged>(
+            DispatchDevice { id: device_id },
+            Some(message_id),
+        ).await;
+    }
+
+    Ok(())
 }
 
 pub fn start_change_controller_state_consumer() -> JoinHandle<Result<(), WorkerError>> {
@@ -27,90 +138,145 @@ pub fn start_change_controller_state_consumer() -> JoinHandle<Result<(), WorkerE
     info!("Starting AMQP {consumer_name} consumer thread");
 
     let runtime = Runtime::new().unwrap();
-    let channel = block_on(async move {
-        amqp_client::get_connection().create_channel()
-            .await.expect("Failed to create AMQP channel in {consumer_name} consumer")
-    });
+
+    thread::spawn(move || -> Result<(), WorkerError> {
+        // Commands are applied one by one, so commands to the same controller stay in order
+        runtime.block_on(amqp::consume::<ChangeControllerStateQueue, _, _>(
+            consumer_name,
+            &format!("data-worker-{consumer_name}"),
+            |delivery, message| async move {
+                let handle = Handle::current();
+
+                // Commands query the database synchronously, so they run on the blocking pool
+                let _ = task::spawn_blocking(move || handle.block_on(async move {
+                    if amqp_client::is_processed(&message, consumer_name) {
+                        return amqp_client::settle::<ChangeControllerStateQueue, _>(&delivery, &message, consumer_name, Ok(())).await;
+                    }
+
+                    let ChangeControllerState {
+                        command_id,
+                        device_id,
+                        state,
+                        duration,
+                    } = message.payload;
+                    let result
+                        = apply_controller_state(message.id, command_id, device_id, state, duration).await;
+
+                    amqp_client::settle::<ChangeControllerStateQueue, _>(&delivery, &message, consumer_name, result).await;
+                })).await;
+            },
+        ));
+
+        Ok(())
+    })
+}
+
+// Reverts are stored with the device, so they are done even if the worker was restarted meanwhile
+pub fn start_controller_reverting_with_interval() -> JoinHandle<Result<(), WorkerError>> {
+    info!("Starting controller reverting thread");
+
+    let runtime = Runtime::new().unwrap();
 
     thread::spawn(move || -> Result<(), WorkerError> {
         runtime.block_on(async move {
-            let mut consumer = channel.basic_consume(
-                "change-controller-state",
-                format!("data-worker-{consumer_name}").as_str(),
-                BasicConsumeOptions::default(),
-                FieldTable::default(),
-            ).await.unwrap_or_else(|_| panic!("Failed to create AMQP {consumer_name} consumer"));
-
-            while let Some(delivery) = consumer.next().await {
-                let delivery = delivery.unwrap();
-
-                delivery.ack(BasicAckOptions::default())
-                    .await.unwrap_or_else(|_| panic!("Failed to ACK in {consumer_name} consumer"));
-
-                if let Ok(AmqpPayload::ChangeControllerState {
-                              device_id,
-                              state,
-                          }) = serde_json::from_str::<AmqpPayload>(
-                    from_utf8(delivery.data.as_slice()).unwrap_or("")
-                ) {
-                    let device = match Device::find(device_id) {
-                        Ok(device) => device,
-                        Err(_) => continue,
-                    };
-                    let greenhouse = match Greenhouse::find(device.greenhouse_id) {
-                        Ok(greenhouse) => greenhouse,
-                        Err(_) => continue,
+            let mut interval = time::interval(Duration::from_secs(5));
+            // Device ID -> When its revert was requested, it's requested again if it hasn't been applied
+            let mut requested_at: HashMap<i64, Instant> = HashMap::new();
+
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let devices = match Device::find_all_by_revert_at_before(SystemTime::now()) {
+                    Ok(devices) => devices,
+                    Err(_) => continue,
+                };
+
+                requested_at.retain(|id, _| devices.iter().any(|device| device.id == *id));
+
+                for device in devices {
+                    let Some(revert_state) = device.revert_state else { continue };
+                    let is_requested = match requested_at.get(&device.id) {
+                        Some(requested_at) => requested_at.elapsed() < COMMAND_TIMEOUT * 2,
+                        None => false,
                     };
-                    let client = reqwest::Client::new();
-
-                    let response: ExternalApiResponse = match device.kind {
-                        DeviceKind::HumidificationController => {
-                            client.patch(format!(
-                                "{}/total_hum?state={}",
-                                garthen::get_external_devices_api_url(),
-                                state,
-                            ))
-                                .header("x-auth-token", greenhouse.token)
-                                .send().await.unwrap().json().await.unwrap()
-                        },
-                        DeviceKind::IrrigationController => {
-                            client.patch(format!(
-                                "{}/watering?id={}&state={}",
-                                garthen::get_external_devices_api_url(),
-                                device.external_id.unwrap_or(1),
-                                state,
-                            ))
-                                .header("x-auth-token", greenhouse.token)
-                                .send().await.unwrap().json().await.unwrap()
-                        }
-                        DeviceKind::WindowsController => {
-                            client.patch(format!(
-                                "{}/fork_drive?state={}",
-                                garthen::get_external_devices_api_url(),
-                                state,
-                            ))
-                                .header("x-auth-token", greenhouse.token)
-                                .send().await.unwrap().json().await.unwrap()
-                        },
-                        _ => continue,
+
+                    if is_requested { continue; }
+
+                    let is_reverted = match DeviceRecord::find_latest_by_device_id(device.id) {
+                        Ok(record) => record.data == f64::from(revert_state),
+                        Err(_) => false,
                     };
 
-                    if response.code != 200 { continue; }
+                    // Disabled controllers aren't switched, the revert is just dropped
+                    if is_reverted || device.status == DeviceStatus::Disabled {
+                        if Device::update_revert(device.id, None, None).is_ok() {
+                            amqp_client::publish::<DeviceControllerStateChanged>(
+                                DispatchDevice { id: device.id },
+                                None,
+                            ).await;
+                        }
 
-                    if DeviceRecord::create(NewDeviceRecord {
-                        device_id,
-                        data: state as f64,
-                    }).is_ok() {
-                        amqp_client::publish(AmqpPublisherMessage {
-                            exchange: Some("device"),
-                            routing_key: Some("device.controller.state.changed"),
-                            payload: AmqpPayload::DispatchDevice { id: device_id },
-                        }).await;
+                        continue;
                     }
+
+                    // A revert state the controller doesn't accept anymore is dropped
+                    let Some(revert_state) = device.kind.get_checked_state(revert_state) else {
+                        let _ = Device::update_revert(device.id, None, None);
+
+                        continue;
+                    };
+
+                    requested_at.insert(device.id, Instant::now());
+                    change_controller_state(device.id, revert_state, None).await;
                 }
             }
-        });
-
-        Ok(())
+        })
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::device::DeviceKind;
+
+    fn get_device(revert: Option<(i16, SystemTime)>) -> Device {
+        Device {
+            id: 1,
+            external_id: None,
+            name: None,
+            status: DeviceStatus::Online,
+            kind: DeviceKind::IrrigationController,
+            greenhouse_id: 2,
+            created_at: SystemTime::now(),
+            maximum_data_value: None,
+            polling_interval: 10,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            revert_state: revert.map(|(revert_state, _)| revert_state),
+            revert_at: revert.map(|(_, revert_at)| revert_at),
+        }
+    }
+
+    #[test]
+    fn test_two_timed_states_in_a_row() {
+        let now = SystemTime::now();
+        let first_revert = get_revert(&get_device(None), Some(60), now, || 0);
+
+        assert_eq!(first_revert, Some((0, now + Duration::from_secs(60))));
+
+        // The controller is in the first timed state meanwhile
+        let second_revert = get_revert(&get_device(first_revert), Some(120), now, || 1);
+
+        assert_eq!(second_revert, Some((0, now + Duration::from_secs(120))));
+    }
+
+    #[test]
+    fn test_state_without_duration() {
+        let now = SystemTime::now();
+        let revert = Some((0, now + Duration::from_secs(60)));
+
+        assert_eq!(get_revert(&get_device(revert), None, now, || 1), None);
+    }
+}
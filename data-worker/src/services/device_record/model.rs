@@ -1,22 +1,24 @@
-use std::time::SystemTime;
+pub use domain::device_record::DeviceRecord;
 
-use db::schema::device_records;
-use diesel::{Insertable, Queryable, RunQueryDsl};
-use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use db::schema::{device_records, device_records_hourly};
+use diesel::{Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::sql_types::{BigInt, Timestamp};
 
 use crate::error::WorkerError;
 
-#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
-#[diesel(table_name = device_records)]
-pub struct DeviceRecord {
-    pub id: i64,
-    pub device_id: i64,
-    pub data: f64,
-    pub created_at: SystemTime,
+pub trait DeviceRecordModel: Sized {
+    fn create(device_record: NewDeviceRecord) -> Result<Self, WorkerError>;
+    fn find_latest_by_device_id(device_id: i64) -> Result<Self, WorkerError>;
+    fn find_last_rolled_up_id() -> Result<Option<i64>, WorkerError>;
+    fn roll_up_after(id: i64, not_before: Option<SystemTime>) -> Result<usize, WorkerError>;
+    fn create_partitions(range: (SystemTime, SystemTime)) -> Result<(), WorkerError>;
+    fn delete_all_before(time: SystemTime) -> Result<usize, WorkerError>;
 }
 
-impl DeviceRecord {
-    pub fn create(device_record: NewDeviceRecord) -> Result<Self, WorkerError> {
+impl DeviceRecordModel for DeviceRecord {
+    fn create(device_record: NewDeviceRecord) -> Result<Self, WorkerError> {
         let connection = &mut db::get_connection()?;
 
         let device_record = DeviceRecord {
@@ -32,6 +34,124 @@ impl DeviceRecord {
 
         Ok(device_record)
     }
+
+    fn find_latest_by_device_id(device_id: i64) -> Result<Self, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let device_record = device_records::table
+            .filter(device_records::device_id.eq(device_id))
+            .order(device_records::created_at.desc())
+            .first(connection)?;
+
+        Ok(device_record)
+    }
+
+    fn find_last_rolled_up_id() -> Result<Option<i64>, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let id = device_records_hourly::table
+            .select(diesel::dsl::max(device_records_hourly::last_record_id))
+            .get_result(connection)?;
+
+        Ok(id)
+    }
+
+    // Recomputes every hour and day that got records with a greater ID, late records included.
+    // Days that began before `not_before` have lost records to the retention, they are kept
+    fn roll_up_after(id: i64, not_before: Option<SystemTime>) -> Result<usize, WorkerError> {
+        let connection = &mut db::get_connection()?;
+        let not_before = not_before.unwrap_or(UNIX_EPOCH);
+
+        let result = connection.transaction::<_, WorkerError, _>(|connection| {
+            let hours = diesel::sql_query(
+                "INSERT INTO device_records_hourly \
+                    (device_id, bucket, sum, count, minimum, maximum, last, last_created_at, last_record_id) \
+                SELECT records.device_id, hours.bucket, \
+                    sum(data), count(*), min(data), max(data), \
+                    (array_agg(data ORDER BY created_at DESC, id DESC))[1], max(created_at), max(id) \
+                FROM ( \
+                    SELECT DISTINCT device_id, date_trunc('hour', created_at) AS bucket \
+                    FROM device_records \
+                    WHERE id > $1 AND date_trunc('day', created_at) >= $2 \
+                ) AS hours \
+                    JOIN device_records AS records \
+                        ON records.device_id = hours.device_id \
+                            AND records.created_at >= hours.bucket \
+                            AND records.created_at < hours.bucket + INTERVAL '1 hour' \
+                GROUP BY records.device_id, hours.bucket \
+                ON CONFLICT (device_id, bucket) DO UPDATE SET \
+                    sum = excluded.sum, \
+                    count = excluded.count, \
+                    minimum = excluded.minimum, \
+                    maximum = excluded.maximum, \
+                    last = excluded.last, \
+                    last_created_at = excluded.last_created_at, \
+                    last_record_id = excluded.last_record_id"
+            )
+                .bind::<BigInt, _>(id)
+                .bind::<Timestamp, _>(not_before)
+                .execute(connection)?;
+
+            // Days are rolled up from hours, so they outlive the records too
+            diesel::sql_query(
+                "INSERT INTO device_records_daily \
+                    (device_id, bucket, sum, count, minimum, maximum, last, last_created_at) \
+                SELECT hourly.device_id, days.bucket, \
+                    sum(sum), sum(count)::BIGINT, min(minimum), max(maximum), \
+                    (array_agg(last ORDER BY last_created_at DESC))[1], max(last_created_at) \
+                FROM ( \
+                    SELECT DISTINCT device_id, date_trunc('day', bucket) AS bucket \
+                    FROM device_records_hourly \
+                    WHERE last_record_id > $1 \
+                ) AS days \
+                    JOIN device_records_hourly AS hourly \
+                        ON hourly.device_id = days.device_id \
+                            AND hourly.bucket >= days.bucket \
+                            AND hourly.bucket < days.bucket + INTERVAL '1 day' \
+                GROUP BY hourly.device_id, days.bucket \
+                ON CONFLICT (device_id, bucket) DO UPDATE SET \
+                    sum = excluded.sum, \
+                    count = excluded.count, \
+                    minimum = excluded.minimum, \
+                    maximum = excluded.maximum, \
+                    last = excluded.last, \
+                    last_created_at = excluded.last_created_at"
+            )
+                .bind::<BigInt, _>(id)
+                .execute(connection)?;
+
+            Ok(hours)
+        })?;
+
+        Ok(result)
+    }
+
+    // Months between the timestamps get their partitions, existing ones are skipped
+    fn create_partitions(range: (SystemTime, SystemTime)) -> Result<(), WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        diesel::sql_query("SELECT create_device_records_partitions($1, $2)")
+            .bind::<Timestamp, _>(range.0)
+            .bind::<Timestamp, _>(range.1)
+            .execute(connection)?;
+
+        Ok(())
+    }
+
+    // Whole months are dropped with their partitions, the rest is deleted row by row
+    fn delete_all_before(time: SystemTime) -> Result<usize, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        diesel::sql_query("SELECT drop_device_records_partitions_before($1)")
+            .bind::<Timestamp, _>(time)
+            .execute(connection)?;
+
+        let result = diesel::delete(
+            device_records::table.filter(device_records::created_at.lt(time))
+        ).execute(connection)?;
+
+        Ok(result)
+    }
 }
 
 pub struct NewDeviceRecord {
@@ -1,226 +1,361 @@
-use std::str::from_utf8;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::Duration;
-
-use futures::executor::block_on;
-use futures::StreamExt;
-use lapin::options::{BasicAckOptions, BasicConsumeOptions};
-use lapin::types::FieldTable;
-use serde::Deserialize;
-use tokio::runtime::Runtime;
-
-use crate::{amqp_client, garthen};
-use crate::amqp_client::{AmqpPayload, AmqpPublisherMessage};
+use std::time::{Duration, Instant, SystemTime};
+
+use amqp::routes::{DataCreated, RequestDataQueue};
+use futures::future::join_all;
+use lazy_static::lazy_static;
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::Semaphore;
+use tokio::task;
+use tokio::time;
+use tokio::time::MissedTickBehavior;
+
+use crate::{amqp_client, drivers, garthen};
+use crate::drivers::DeviceDriver;
+use crate::amqp_client::{DispatchData, RequestData};
 use crate::error::WorkerError;
-use crate::services::device::{Device, DeviceKind, DeviceStatus};
-use crate::services::device_record::{DeviceRecord, NewDeviceRecord};
-use crate::services::greenhouse::Greenhouse;
-
-#[derive(Debug, Deserialize)]
-struct TemperatureAndHumidityData {
-    temperature: f64,
-    humidity: f64,
+use crate::services::{alert, device, greenhouse};
+use crate::services::device::{Device, DeviceModel, DeviceStatus};
+use crate::services::device_record::{DeviceRecord, DeviceRecordModel, NewDeviceRecord};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::rule;
+
+// Longer than records of an import take to be committed
+const ROLLING_UP_OVERLAP: Duration = Duration::from_secs(600);
+
+lazy_static! {
+    static ref REQUEST_PERMITS: Semaphore = {
+        Semaphore::new(garthen::get_data_requesting_concurrency())
+    };
 }
 
-#[derive(Debug, Deserialize)]
-struct SoilMoistureData {
-    humidity: f64,
+// Readings are stored only for `due_devices`, other devices that get data with this device
+// keep their own polling intervals
+async fn request(driver: &dyn DeviceDriver, device: &Device, devices: &[Device], due_devices: &[Device]) {
+    let capabilities = driver.get_capabilities();
+
+    if !capabilities.is_polled(device.kind) { return; }
+
+    let due_devices: Vec<&Device> = due_devices.iter()
+        .filter(|&found|
+            found.status != DeviceStatus::Disabled
+                && found.external_id == device.external_id
+                && capabilities.get_polled_kind(found.kind) == Some(device.kind)
+        )
+        .collect();
+
+    if due_devices.is_empty() { return; }
+
+    let readings = {
+        let _permit = REQUEST_PERMITS.acquire().await.unwrap();
+
+        driver.read_sensor(device).await
+    };
+    let readings = match readings {
+        Ok(readings) => readings,
+        Err(_) => {
+            // Devices that get data with this device stop answering along with it
+            let offline_devices: Vec<Device> = devices.iter()
+                .filter(|&found|
+                    found.external_id == device.external_id
+                        && capabilities.get_polled_kind(found.kind) == Some(device.kind)
+                )
+                .cloned()
+                .collect();
+            let handle = Handle::current();
+
+            let _ = task::spawn_blocking(move || handle.block_on(async move {
+                for device in &offline_devices {
+                    device::update_status(device, DeviceStatus::Offline).await;
+                }
+            })).await;
+
+            return;
+        },
+    };
+
+    for reading in readings {
+        let device = match due_devices.iter().find(|&found|
+            found.kind == reading.kind
+                && found.external_id == reading.external_id
+        ) {
+            Some(&device) => device.clone(),
+            None => continue,
+        };
+        let handle = Handle::current();
+
+        // Readings are stored and evaluated with synchronous queries,
+        // so every one runs on the blocking pool instead of stalling polls of other greenhouses
+        let _ = task::spawn_blocking(move || {
+            handle.block_on(store_reading(device, reading.data))
+        }).await;
+    }
 }
 
-fn request(device: Device, token: String, devices: Option<Vec<Device>>) -> JoinHandle<()> {
-    thread::spawn(move || {
-        let client = reqwest::Client::new();
-        let runtime = Runtime::new().unwrap();
+async fn store_reading(device: Device, data: f64) {
+    device::update_status(&device, DeviceStatus::Online).await;
 
-        if device.status == DeviceStatus::Disabled { return; }
+    // A reading out of the range of the kind is a fault of the sensor, not a real value
+    match device.kind.get_data_range() {
+        Some(data_range) if data_range.contains(&data) => {},
+        _ => {
+            warn!("Skipped reading {data} of device {} out of range", device.id);
 
-        runtime.block_on(async move {
-            match device.kind {
-                // DeviceKind::HumiditySensor gets data from the same path
-                DeviceKind::TemperatureSensor => {
-                    let data: TemperatureAndHumidityData = client
-                        .get(format!(
-                            "{}/temp_hum/{}",
-                            garthen::get_external_devices_api_url(),
-                            device.external_id.unwrap_or(1),
-                        ))
-                        .header("x-auth-token", token)
-                        .send().await.unwrap().json().await.unwrap();
-
-                    DeviceRecord::create(NewDeviceRecord {
-                        device_id: device.id,
-                        data: data.temperature,
-                    }).ok();
-
-                    amqp_client::publish(AmqpPublisherMessage {
-                        exchange: Some("data"),
-                        routing_key: Some("data.created"),
-                        payload: AmqpPayload::DispatchData { device_id: device.id },
-                    }).await;
-
-                    let device = match devices {
-                        Some(devices) => {
-                            match devices.iter().find(|&found|
-                                found.kind == DeviceKind::HumiditySensor
-                                    && found.external_id == device.external_id
-                            ) {
-                                Some(device) => device.to_owned(),
-                                None => return,
-                            }
-                        },
-                        None => {
-                            match Device::find_humidity_sensor_by_external_id_and_greenhouse_id(
-                                device.external_id,
-                                device.greenhouse_id,
-                            ) {
-                                Ok(device) => device,
-                                Err(_) => return,
-                            }
-                        },
-                    };
-
-                    DeviceRecord::create(NewDeviceRecord {
-                        device_id: device.id,
-                        data: data.humidity,
-                    }).ok();
-
-                    amqp_client::publish(AmqpPublisherMessage {
-                        exchange: Some("data"),
-                        routing_key: Some("data.created"),
-                        payload: AmqpPayload::DispatchData { device_id: device.id },
-                    }).await;
-                },
-                DeviceKind::SoilMoistureSensor => {
-                    let data: SoilMoistureData = client
-                        .get(format!(
-                            "{}/hum/{}",
-                            garthen::get_external_devices_api_url(),
-                            device.external_id.unwrap(),
-                        ))
-                        .header("x-auth-token", token)
-                        .send().await.unwrap().json().await.unwrap();
-
-                    DeviceRecord::create(NewDeviceRecord {
-                        device_id: device.id,
-                        data: data.humidity,
-                    }).unwrap();
-
-                    amqp_client::publish(AmqpPublisherMessage {
-                        exchange: Some("data"),
-                        routing_key: Some("data.created"),
-                        payload: AmqpPayload::DispatchData { device_id: device.id },
-                    }).await;
-                },
-                _ => {},
-            };
-        })
-    })
+            return;
+        },
+    }
+
+    if DeviceRecord::create(NewDeviceRecord {
+        device_id: device.id,
+        data,
+    }).is_err() { return; }
+
+    amqp_client::publish::<DataCreated>(DispatchData { device_id: device.id }, None).await;
+
+    let checks = greenhouse::check_thresholds(&device, data);
+
+    for check in &checks {
+        alert::evaluate(&device, check).await;
+    }
+
+    rule::evaluate(&device, data, &checks).await;
+}
+
+// `all_devices` are all devices of the greenhouse, readings of polled devices are matched with them
+async fn request_greenhouse(greenhouse: Greenhouse, due_devices: Vec<Device>, all_devices: Vec<Device>) {
+    let driver = drivers::get_driver(&greenhouse);
+    let capabilities = driver.get_capabilities();
+    let mut polled_devices: Vec<Device> = vec![];
+
+    // A sensor that isn't polled itself is read by polling the device of its polled kind,
+    // which is polled once for all due sensors that get data with it
+    for device in &due_devices {
+        let Some(polled_kind) = capabilities.get_polled_kind(device.kind) else { continue };
+
+        if polled_devices.iter().any(|found|
+            found.kind == polled_kind && found.external_id == device.external_id
+        ) { continue; }
+
+        let polled_device = all_devices.iter()
+            .find(|&found| found.kind == polled_kind && found.external_id == device.external_id)
+            .cloned()
+            .unwrap_or_else(|| Device { kind: polled_kind, ..device.clone() });
+
+        polled_devices.push(polled_device);
+    }
+
+    join_all(
+        polled_devices.iter()
+            .map(|device| request(driver.as_ref(), device, &all_devices, &due_devices))
+    ).await;
 }
 
 pub fn start_data_requesting_with_interval() -> JoinHandle<Result<(), WorkerError>> {
     info!("Starting data requesting thread");
 
-    thread::spawn(|| -> Result<(), WorkerError> {
-        loop {
-            thread::sleep(Duration::from_secs(60));
+    let runtime = Runtime::new().unwrap();
 
-            let greenhouses = Greenhouse::find_all()?;
+    thread::spawn(move || -> Result<(), WorkerError> {
+        runtime.block_on(async move {
+            // Polling intervals of devices are multiples of this tick
+            let mut interval = time::interval(Duration::from_secs(10));
+            let mut polls: HashMap<i64, task::JoinHandle<()>> = HashMap::new();
+            let mut polled_at: HashMap<i64, Instant> = HashMap::new();
+
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                polls.retain(|_, poll| !poll.is_finished());
+
+                // All greenhouses are loaded at once on the blocking pool, so the tick isn't stalled
+                let greenhouses = match task::spawn_blocking(Greenhouse::find_all_with_devices).await {
+                    Ok(Ok(greenhouses)) => greenhouses,
+                    _ => continue,
+                };
+                let device_ids: HashSet<i64> = greenhouses.iter()
+                    .flat_map(|(_, devices)| devices.iter().map(|device| device.id))
+                    .collect();
+
+                // Deleted devices are forgotten
+                polled_at.retain(|device_id, _| device_ids.contains(device_id));
+
+                for (greenhouse, devices) in greenhouses {
+                    // Don't stack polls on a gateway that hasn't answered the previous one yet,
+                    // every greenhouse is polled in its own task, so others aren't delayed
+                    if polls.contains_key(&greenhouse.id) { continue; }
+
+                    let now = Instant::now();
+                    let due_devices: Vec<Device> = devices.iter()
+                        .filter(|device| {
+                            if device.status == DeviceStatus::Disabled { return false; }
+
+                            let interval = Duration::from_secs(device.polling_interval as u64);
+                            let is_due = match polled_at.get(&device.id) {
+                                Some(&polled_at) => now - polled_at >= interval,
+                                None => true,
+                            };
 
-            for greenhouse in greenhouses {
-                let devices = Device::find_all_by_greenhouse_id(greenhouse.id)?;
-                let mut threads = vec![];
+                            is_due && !device.is_in_quiet_hours(SystemTime::now())
+                        })
+                        .cloned()
+                        .collect();
 
-                for device in devices.clone() {
-                    let devices = devices.clone();
-                    let token = greenhouse.token.to_owned();
+                    if due_devices.is_empty() { continue; }
 
-                    threads.push(request(device, token, Some(devices)));
-                }
+                    for device in &due_devices {
+                        polled_at.insert(device.id, now);
+                    }
 
-                for thread in threads {
-                    thread.join().unwrap();
+                    polls.insert(
+                        greenhouse.id,
+                        task::spawn(request_greenhouse(greenhouse, due_devices, devices)),
+                    );
                 }
             }
-        }
+        })
     })
 }
 
+async fn request_by_message(device_id: Option<i64>, greenhouse_id: Option<i64>) -> Result<(), WorkerError> {
+    // Devices and greenhouses deleted meanwhile have nothing to request
+    let ignore_not_found = |error: WorkerError| match error.http_code {
+        404 => Ok(()),
+        _ => Err(error),
+    };
+
+    if let Some(device_id) = device_id {
+        let device = match Device::find(device_id) {
+            Ok(device) => device,
+            Err(error) => return ignore_not_found(error),
+        };
+        let greenhouse_and_devices = Greenhouse::find(device.greenhouse_id)
+            .and_then(|greenhouse| {
+                Ok((greenhouse, Device::find_all_by_greenhouse_id(device.greenhouse_id)?))
+            });
+        let (greenhouse, devices) = match greenhouse_and_devices {
+            Ok(greenhouse_and_devices) => greenhouse_and_devices,
+            Err(error) => return ignore_not_found(error),
+        };
+
+        request_greenhouse(greenhouse, vec![device], devices).await;
+    } else if let Some(greenhouse_id) = greenhouse_id {
+        let greenhouse_and_devices = Greenhouse::find(greenhouse_id)
+            .and_then(|greenhouse| {
+                Ok((greenhouse, Device::find_all_by_greenhouse_id(greenhouse_id)?))
+            });
+        let (greenhouse, devices) = match greenhouse_and_devices {
+            Ok(greenhouse_and_devices) => greenhouse_and_devices,
+            Err(error) => return ignore_not_found(error),
+        };
+
+        request_greenhouse(greenhouse, devices.clone(), devices).await;
+    }
+
+    Ok(())
+}
+
 pub fn start_data_request_consumer() -> JoinHandle<Result<(), WorkerError>> {
     let consumer_name = "data-requester";
 
     info!("Starting AMQP {consumer_name} consumer thread");
 
     let runtime = Runtime::new().unwrap();
-    let channel = block_on(async move {
-        amqp_client::get_connection().create_channel()
-            .await.expect("Failed to create AMQP channel in {consumer_name} consumer")
-    });
+
+    thread::spawn(move || -> Result<(), WorkerError> {
+        // No more messages are taken while this many requests are running
+        let permits = Arc::new(Semaphore::new(amqp::get_prefetch_count(consumer_name) as usize));
+
+        runtime.block_on(amqp::consume::<RequestDataQueue, _, _>(
+            consumer_name,
+            &format!("data-worker-{consumer_name}"),
+            |delivery, message| {
+                let permits = permits.clone();
+
+                async move {
+                    let permit = permits.acquire_owned().await.unwrap();
+                    let handle = Handle::current();
+
+                    // Requests query the database synchronously,
+                    // so every one runs on the blocking pool instead of stalling the consumer
+                    task::spawn_blocking(move || handle.block_on(async move {
+                        let _permit = permit;
+
+                        if amqp_client::is_processed(&message, consumer_name) {
+                            return amqp_client::settle::<RequestDataQueue, _>(&delivery, &message, consumer_name, Ok(())).await;
+                        }
+
+                        let RequestData { device_id, greenhouse_id } = message.payload;
+                        let result = request_by_message(device_id, greenhouse_id).await;
+
+                        amqp_client::settle::<RequestDataQueue, _>(&delivery, &message, consumer_name, result).await;
+                    }));
+                }
+            },
+        ));
+
+        Ok(())
+    })
+}
+
+pub fn start_device_records_rolling_up_with_interval() -> JoinHandle<Result<(), WorkerError>> {
+    info!("Starting device records rolling up thread");
+
+    let runtime = Runtime::new().unwrap();
 
     thread::spawn(move || -> Result<(), WorkerError> {
         runtime.block_on(async move {
-            let mut consumer = channel.basic_consume(
-                "request-data",
-                format!("data-worker-{consumer_name}").as_str(),
-                BasicConsumeOptions::default(),
-                FieldTable::default(),
-            ).await.unwrap_or_else(|_| panic!("Failed to create AMQP {consumer_name} consumer"));
-
-            while let Some(delivery) = consumer.next().await {
-                let delivery = delivery.unwrap();
-
-                delivery.ack(BasicAckOptions::default())
-                    .await.unwrap_or_else(|_| panic!("Failed to ACK in {consumer_name} consumer"));
-
-                if let Ok(AmqpPayload::RequestData {
-                              device_id,
-                              greenhouse_id,
-                          }) = serde_json::from_str::<AmqpPayload>(
-                    from_utf8(delivery.data.as_slice()).unwrap_or("")
-                ) {
-                    if let Some(device_id) = device_id {
-                        if let Ok(device) = Device::find(device_id) {
-                            let device = match device.kind {
-                                DeviceKind::HumiditySensor => {
-                                    Device::find_temperature_sensor_by_external_id_and_greenhouse_id(
-                                        device.external_id,
-                                        device.greenhouse_id,
-                                    ).unwrap_or(device)
-                                },
-                                _ => device,
-                            };
-                            let greenhouse
-                                = Greenhouse::find(device.greenhouse_id);
+            let mut interval = time::interval(Duration::from_secs(60));
 
-                            if let Ok(greenhouse) = greenhouse {
-                                request(device, greenhouse.token, None).join().ok();
-                            }
-                        }
-                    } else if let Some(greenhouse_id) = greenhouse_id {
-                        let greenhouse
-                            = Greenhouse::find(greenhouse_id);
-                        let devices
-                            = Device::find_all_by_greenhouse_id(greenhouse_id);
-                        let mut threads = vec![];
-
-                        if let (Ok(greenhouse), Ok(devices))
-                            = (greenhouse, devices) {
-                            for device in devices.clone() {
-                                let devices = devices.clone();
-                                let token = greenhouse.token.to_owned();
-
-                                threads.push(request(device, token, Some(devices)));
-                            }
-
-                            for thread in threads {
-                                thread.join().ok();
-                            }
-                        }
-                    }
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let Ok(last_rolled_up_id) = DeviceRecord::find_last_rolled_up_id() else { continue };
+                let not_before = db::get_device_records_retention()
+                    .map(|retention| SystemTime::now() - retention);
+
+                // IDs are generated before records are committed, so records of the overlap
+                // are rolled up again, which catches the ones that were committed after a greater ID
+                let after_id = last_rolled_up_id
+                    .map(|id| id - snowflake::get_span(ROLLING_UP_OVERLAP))
+                    .unwrap_or(0);
+
+                if let Err(error) = DeviceRecord::roll_up_after(after_id, not_before) {
+                    warn!("Failed to roll up device records: {error}");
                 }
             }
-        });
+        })
+    })
+}
 
-        Ok(())
+pub fn start_device_records_partitioning_with_interval() -> JoinHandle<Result<(), WorkerError>> {
+    info!("Starting device records partitioning thread");
+
+    let runtime = Runtime::new().unwrap();
+
+    thread::spawn(move || -> Result<(), WorkerError> {
+        runtime.block_on(async move {
+            let mut interval = time::interval(Duration::from_secs(3600));
+
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                // Partitions are kept a month ahead, records never fall into the default one
+                let now = SystemTime::now();
+                let _ = DeviceRecord::create_partitions((now, now + Duration::from_secs(86400 * 31)));
+
+                if let Some(retention) = db::get_device_records_retention() {
+                    let _ = DeviceRecord::delete_all_before(now - retention);
+                }
+            }
+        })
     })
 }
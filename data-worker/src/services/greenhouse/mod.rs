@@ -1,3 +1,5 @@
 pub use model::*;
+pub use threshold::*;
 
 mod model;
+mod threshold;
@@ -1,26 +1,20 @@
-use std::time::SystemTime;
+pub use domain::greenhouse::{Greenhouse, GreenhouseDriver, GreenhouseGatewayAuthScheme};
 
-use db::schema::greenhouses;
-use diesel::{Insertable, Queryable, RunQueryDsl};
+use db::schema::{devices, greenhouses};
+use diesel::RunQueryDsl;
 use diesel::prelude::*;
-use serde::{Deserialize, Serialize};
 
 use crate::error::WorkerError;
+use crate::services::device::Device;
 
-#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
-#[diesel(table_name = greenhouses)]
-pub struct Greenhouse {
-    pub id: i64,
-    pub name: String,
-    pub token: String,
-    pub owner_id: i64,
-    pub created_at: SystemTime,
-    pub maximum_average_humidity: Option<f64>,
-    pub minimum_average_temperature: Option<f64>,
+pub trait GreenhouseModel: Sized {
+    fn find(id: i64) -> Result<Self, WorkerError>;
+    fn find_all() -> Result<Vec<Self>, WorkerError>;
+    fn find_all_with_devices() -> Result<Vec<(Self, Vec<Device>)>, WorkerError>;
 }
 
-impl Greenhouse {
-    pub fn find(id: i64) -> Result<Self, WorkerError> {
+impl GreenhouseModel for Greenhouse {
+    fn find(id: i64) -> Result<Self, WorkerError> {
         let connection = &mut db::get_connection()?;
 
         let greenhouse = greenhouses::table
@@ -30,7 +24,7 @@ impl Greenhouse {
         Ok(greenhouse)
     }
 
-    pub fn find_all() -> Result<Vec<Self>, WorkerError> {
+    fn find_all() -> Result<Vec<Self>, WorkerError> {
         let connection = &mut db::get_connection()?;
 
         let greenhouses = greenhouses::table
@@ -38,4 +32,23 @@ impl Greenhouse {
 
         Ok(greenhouses)
     }
+
+    fn find_all_with_devices() -> Result<Vec<(Self, Vec<Device>)>, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let rows: Vec<(Greenhouse, Option<Device>)> = greenhouses::table
+            .left_join(devices::table)
+            .order(greenhouses::id)
+            .load(connection)?;
+        let mut greenhouses: Vec<(Greenhouse, Vec<Device>)> = vec![];
+
+        for (greenhouse, device) in rows {
+            match greenhouses.last_mut() {
+                Some((last, devices)) if last.id == greenhouse.id => devices.extend(device),
+                _ => greenhouses.push((greenhouse, device.into_iter().collect())),
+            }
+        }
+
+        Ok(greenhouses)
+    }
 }
@@ -0,0 +1,108 @@
+use crate::services::device::{Device, DeviceKind, DeviceModel, DeviceStatus};
+use crate::services::device_record::{DeviceRecord, DeviceRecordModel};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+
+// How far data has to move back from a threshold to clear it, as a share of the data range of the kind,
+// so a threshold clears as easily for pH as for light
+const THRESHOLD_HYSTERESIS_SHARE: f64 = 0.01;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Threshold {
+    MaximumDataValue,
+    MaximumAverageHumidity,
+    MinimumAverageTemperature,
+}
+
+#[derive(Clone, Debug)]
+pub struct ThresholdCheck {
+    pub threshold: Threshold,
+    /// All devices of the greenhouse
+    pub devices: Vec<Device>,
+    /// The checked data, which is the greenhouse average for average thresholds
+    pub data: f64,
+    pub limit: f64,
+    pub is_crossed: bool,
+    pub is_cleared: bool,
+}
+
+/// Checks a new record of the sensor against every threshold that applies to it:
+/// the maximum data value of the sensor itself and the average threshold of its kind
+pub fn check_thresholds(sensor: &Device, data: f64) -> Vec<ThresholdCheck> {
+    let Ok(greenhouse) = Greenhouse::find(sensor.greenhouse_id) else { return vec![] };
+    let Ok(devices) = Device::find_all_by_greenhouse_id(greenhouse.id) else { return vec![] };
+    let hysteresis = get_hysteresis(sensor.kind);
+    let mut checks = vec![];
+
+    if let Some(maximum) = sensor.maximum_data_value {
+        checks.push(ThresholdCheck {
+            threshold: Threshold::MaximumDataValue,
+            devices: devices.clone(),
+            data,
+            limit: maximum,
+            is_crossed: data >= maximum,
+            is_cleared: data < maximum - hysteresis,
+        });
+    }
+
+    let average_check = match sensor.kind {
+        DeviceKind::HumiditySensor => greenhouse.maximum_average_humidity
+            .zip(get_latest_average(&devices, sensor.kind))
+            .map(|(maximum, average)| (
+                Threshold::MaximumAverageHumidity,
+                average,
+                maximum,
+                average >= maximum,
+                average < maximum - hysteresis,
+            )),
+        DeviceKind::TemperatureSensor => greenhouse.minimum_average_temperature
+            .zip(get_latest_average(&devices, sensor.kind))
+            .map(|(minimum, average)| (
+                Threshold::MinimumAverageTemperature,
+                average,
+                minimum,
+                average <= minimum,
+                average > minimum + hysteresis,
+            )),
+        _ => None,
+    };
+
+    if let Some((threshold, data, limit, is_crossed, is_cleared)) = average_check {
+        checks.push(ThresholdCheck { threshold, devices, data, limit, is_crossed, is_cleared });
+    }
+
+    checks
+}
+
+fn get_hysteresis(kind: DeviceKind) -> f64 {
+    match kind.get_data_range() {
+        Some(data_range) => (data_range.maximum - data_range.minimum) * THRESHOLD_HYSTERESIS_SHARE,
+        None => 0.0,
+    }
+}
+
+fn get_latest_average(devices: &[Device], kind: DeviceKind) -> Option<f64> {
+    let data: Vec<f64> = devices.iter()
+        .filter(|&device| device.kind == kind && device.status != DeviceStatus::Disabled)
+        .filter_map(|device| DeviceRecord::find_latest_by_device_id(device.id).ok())
+        .map(|record| record.data)
+        .collect();
+
+    match data.is_empty() {
+        true => None,
+        false => Some(data.iter().sum::<f64>() / data.len() as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hysteresis() {
+        assert_eq!(get_hysteresis(DeviceKind::TemperatureSensor), 2.0);
+        assert_eq!(get_hysteresis(DeviceKind::HumiditySensor), 1.0);
+        assert_eq!(get_hysteresis(DeviceKind::Co2Sensor), 100.0);
+        assert!((get_hysteresis(DeviceKind::PhSensor) - 0.14).abs() < f64::EPSILON);
+        assert_eq!(get_hysteresis(DeviceKind::IrrigationController), 0.0);
+    }
+}
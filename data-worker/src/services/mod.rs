@@ -1,3 +1,8 @@
+pub(crate) mod alert;
+pub(crate) mod command;
 pub(crate) mod device;
 pub(crate) mod device_record;
 pub(crate) mod greenhouse;
+pub(crate) mod processed_message;
+pub(crate) mod rule;
+pub(crate) mod schedule;
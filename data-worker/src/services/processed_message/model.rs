@@ -0,0 +1,58 @@
+use std::time::SystemTime;
+
+use db::schema::processed_messages;
+use diesel::{ExpressionMethods, Insertable, Queryable, RunQueryDsl};
+use diesel::dsl::exists;
+use diesel::prelude::*;
+
+use crate::error::WorkerError;
+
+#[derive(Clone, Debug, Insertable, Queryable)]
+#[diesel(table_name = processed_messages)]
+pub struct ProcessedMessage {
+    pub id: i64,
+    pub consumer: String,
+    pub created_at: SystemTime,
+}
+
+impl ProcessedMessage {
+    // A message that is already stored is skipped
+    pub fn create(id: i64, consumer: &str) -> Result<usize, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let processed_message = ProcessedMessage {
+            id,
+            consumer: consumer.to_string(),
+            created_at: SystemTime::now(),
+        };
+
+        let result = diesel::insert_into(processed_messages::table)
+            .values(processed_message)
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+
+        Ok(result)
+    }
+
+    pub fn exists(id: i64, consumer: &str) -> Result<bool, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let is_existing = diesel::select(exists(
+            processed_messages::table
+                .filter(processed_messages::id.eq(id))
+                .filter(processed_messages::consumer.eq(consumer))
+        )).get_result(connection)?;
+
+        Ok(is_existing)
+    }
+
+    pub fn delete_all_before(time: SystemTime) -> Result<usize, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let result = diesel::delete(
+            processed_messages::table.filter(processed_messages::created_at.lt(time))
+        ).execute(connection)?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,35 @@
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use tokio::runtime::Runtime;
+use tokio::time;
+use tokio::time::MissedTickBehavior;
+
+use crate::error::WorkerError;
+use crate::services::processed_message::ProcessedMessage;
+
+// Redeliveries come soon after the failure, older IDs are not needed anymore
+const PROCESSED_MESSAGE_RETENTION: Duration = Duration::from_secs(86400);
+
+pub fn start_processed_messages_cleaning_with_interval() -> JoinHandle<Result<(), WorkerError>> {
+    info!("Starting processed messages cleaning thread");
+
+    let runtime = Runtime::new().unwrap();
+
+    thread::spawn(move || -> Result<(), WorkerError> {
+        runtime.block_on(async move {
+            let mut interval = time::interval(Duration::from_secs(3600));
+
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let _ = ProcessedMessage::delete_all_before(
+                    SystemTime::now() - PROCESSED_MESSAGE_RETENTION,
+                );
+            }
+        })
+    })
+}
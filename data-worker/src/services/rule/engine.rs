@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+use crate::services::device::{change_controller_state, Device, DeviceKind, DeviceModel, DeviceStatus};
+use crate::services::greenhouse::{Threshold, ThresholdCheck};
+use crate::services::rule::{Rule, RuleModel};
+
+lazy_static! {
+    static ref RULE_STATES: Mutex<HashMap<i64, RuleState>> = Mutex::new(HashMap::new());
+    // Controller ID -> Whether a greenhouse threshold is crossed for it
+    static ref THRESHOLD_STATES: Mutex<HashMap<i64, bool>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Default)]
+struct RuleState {
+    met_since: Option<Instant>,
+    is_fired: bool,
+}
+
+/// Evaluates a new record of the sensor against greenhouse thresholds and user rules
+pub async fn evaluate(sensor: &Device, data: f64, checks: &[ThresholdCheck]) {
+    for check in checks {
+        evaluate_threshold(sensor, check).await;
+    }
+
+    evaluate_rules(sensor, data).await;
+}
+
+async fn evaluate_threshold(sensor: &Device, check: &ThresholdCheck) {
+    // The same conditions block controllers in the web client
+    let (controller_kind, external_id) = match (check.threshold, sensor.kind) {
+        (Threshold::MaximumDataValue, DeviceKind::SoilMoistureSensor) => {
+            (DeviceKind::IrrigationController, sensor.external_id)
+        },
+        // Other sensors have no controller to block, their maximum only raises alerts
+        (Threshold::MaximumDataValue, _) => return,
+        (Threshold::MaximumAverageHumidity, _) => (DeviceKind::HumidificationController, None),
+        (Threshold::MinimumAverageTemperature, _) => (DeviceKind::WindowsController, None),
+    };
+
+    let controllers = check.devices.iter().filter(|&device|
+        device.kind == controller_kind
+            && device.status != DeviceStatus::Disabled
+            && (external_id.is_none() || device.external_id == external_id)
+    );
+    let mut crossed_controllers = vec![];
+
+    {
+        let mut threshold_states = THRESHOLD_STATES.lock().unwrap();
+
+        for controller in controllers {
+            let is_blocked = threshold_states.entry(controller.id).or_default();
+
+            if !*is_blocked && check.is_crossed {
+                *is_blocked = true;
+                crossed_controllers.push(controller.id);
+            } else if *is_blocked && check.is_cleared {
+                *is_blocked = false;
+            }
+        }
+    }
+
+    for controller_id in crossed_controllers {
+        change_controller_state(controller_id, 0, None).await;
+    }
+}
+
+async fn evaluate_rules(sensor: &Device, data: f64) {
+    let Ok(rules) = Rule::find_all_by_sensor_id(sensor.id) else { return };
+
+    for rule in rules {
+        let is_fired = {
+            let mut rule_states = RULE_STATES.lock().unwrap();
+            let rule_state = rule_states.entry(rule.id).or_default();
+
+            if rule.condition.is_met(data, rule.threshold) {
+                let met_since = *rule_state.met_since.get_or_insert_with(Instant::now);
+                let duration = Duration::from_secs(rule.duration as u64);
+
+                if !rule_state.is_fired && met_since.elapsed() >= duration {
+                    rule_state.is_fired = true;
+
+                    true
+                } else {
+                    false
+                }
+            } else {
+                rule_state.met_since = None;
+
+                if rule.condition.is_cleared(data, rule.threshold, rule.hysteresis) {
+                    rule_state.is_fired = false;
+                }
+
+                false
+            }
+        };
+
+        if is_fired { fire_rule(rule).await; }
+    }
+}
+
+async fn fire_rule(rule: Rule) {
+    let Ok(controller) = Device::find(rule.controller_id) else { return };
+
+    if controller.status == DeviceStatus::Disabled { return; }
+
+    let Some(state) = controller.kind.get_checked_state(rule.state) else {
+        warn!("Skipped rule {} with state {} the controller doesn't accept", rule.id, rule.state);
+
+        return;
+    };
+    let action_duration = rule.action_duration
+        .and_then(|action_duration| u32::try_from(action_duration).ok());
+
+    change_controller_state(controller.id, state, action_duration).await;
+}
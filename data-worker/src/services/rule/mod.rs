@@ -0,0 +1,5 @@
+pub use engine::*;
+pub use model::*;
+
+mod engine;
+mod model;
@@ -0,0 +1,23 @@
+pub use domain::rule::Rule;
+
+use db::schema::rules;
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+
+use crate::error::WorkerError;
+
+pub trait RuleModel: Sized {
+    fn find_all_by_sensor_id(sensor_id: i64) -> Result<Vec<Self>, WorkerError>;
+}
+
+impl RuleModel for Rule {
+    fn find_all_by_sensor_id(sensor_id: i64) -> Result<Vec<Self>, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let rules = rules::table
+            .filter(rules::sensor_id.eq(sensor_id))
+            .load(connection)?;
+
+        Ok(rules)
+    }
+}
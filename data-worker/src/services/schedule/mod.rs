@@ -0,0 +1,5 @@
+pub use model::*;
+pub use threads::*;
+
+mod model;
+mod threads;
@@ -0,0 +1,21 @@
+pub use domain::schedule::Schedule;
+
+use db::schema::schedules;
+use diesel::RunQueryDsl;
+
+use crate::error::WorkerError;
+
+pub trait ScheduleModel: Sized {
+    fn find_all() -> Result<Vec<Self>, WorkerError>;
+}
+
+impl ScheduleModel for Schedule {
+    fn find_all() -> Result<Vec<Self>, WorkerError> {
+        let connection = &mut db::get_connection()?;
+
+        let schedules = schedules::table
+            .load(connection)?;
+
+        Ok(schedules)
+    }
+}
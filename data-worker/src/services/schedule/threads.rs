@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use amqp::routes::DeviceScheduleFired;
+use tokio::runtime::Runtime;
+use tokio::time;
+use tokio::time::MissedTickBehavior;
+
+use crate::amqp_client;
+use crate::amqp_client::DispatchScheduleFire;
+use crate::error::WorkerError;
+use crate::services::device::{change_controller_state, Device, DeviceModel, DeviceStatus};
+use crate::services::schedule::{Schedule, ScheduleModel};
+
+// The controller is switched for the remaining time of the schedule, its end is reverted with the controller,
+// which is stored, so the end isn't missed even if the worker was restarted meanwhile
+async fn fire(schedule: &Schedule, now: SystemTime) {
+    let Some(remaining_time) = schedule.get_remaining_time(now) else { return };
+    let Ok(controller) = Device::find(schedule.device_id) else { return };
+
+    if controller.status == DeviceStatus::Disabled { return; }
+
+    let Some(state) = controller.kind.get_checked_state(schedule.state) else {
+        warn!("Skipped schedule {} with state {} the controller doesn't accept", schedule.id, schedule.state);
+
+        return;
+    };
+    let duration = remaining_time.as_secs().max(1).try_into().unwrap_or(u32::MAX);
+
+    change_controller_state(controller.id, state, Some(duration)).await;
+
+    amqp_client::publish::<DeviceScheduleFired>(
+        DispatchScheduleFire {
+            id: schedule.id,
+            device_id: controller.id,
+            state,
+        },
+        None,
+    ).await;
+}
+
+pub fn start_schedule_executing_with_interval() -> JoinHandle<Result<(), WorkerError>> {
+    info!("Starting schedule executing thread");
+
+    let runtime = Runtime::new().unwrap();
+
+    thread::spawn(move || -> Result<(), WorkerError> {
+        runtime.block_on(async move {
+            let mut interval = time::interval(Duration::from_secs(10));
+            // Schedule ID -> Whether the schedule was active on the previous tick
+            let mut schedule_states: HashMap<i64, bool> = HashMap::new();
+
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let schedules = match Schedule::find_all() {
+                    Ok(schedules) => schedules,
+                    Err(_) => continue,
+                };
+                let now = SystemTime::now();
+
+                schedule_states.retain(|id, _| schedules.iter().any(|schedule| schedule.id == *id));
+
+                for schedule in &schedules {
+                    let is_active = schedule.is_active(now);
+                    let was_active = schedule_states.insert(schedule.id, is_active);
+
+                    // Only starts are fired, the state set by hand isn't reset by an inactive schedule
+                    if is_active && was_active != Some(true) { fire(schedule, now).await; }
+                }
+            }
+        })
+    })
+}
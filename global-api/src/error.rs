@@ -3,13 +3,16 @@ use std::io::Error;
 use std::string::ToString;
 
 use actix_web::{HttpResponse, ResponseError};
+use actix_web::error::{BlockingError, PayloadError};
 use actix_web::http::StatusCode as HttpStatusCode;
 use argon2::Error as Argon2Error;
 use argon2::password_hash::Error as Argon2PasswordHashError;
 use diesel::result::Error as DieselError;
+use domain::device_record::DeviceRecordError;
 use r2d2::Error as R2d2Error;
 use serde::Deserialize;
 use serde_json::json;
+use serde_json::Error as SerdeJsonError;
 
 const UNKNOWN_JSON_ERROR_CODE: u32 = 0;
 
@@ -20,6 +23,9 @@ pub enum ApiErrorKind {
     Argon2PasswordHashError(Argon2PasswordHashError),
     DieselError(DieselError),
     R2d2Error(R2d2Error),
+    BlockingError(BlockingError),
+    PayloadError(PayloadError),
+    SerdeJsonError(SerdeJsonError),
     Other(Option<String>),
 }
 
@@ -99,6 +105,39 @@ impl From<R2d2Error> for ApiError {
     }
 }
 
+impl From<BlockingError> for ApiError {
+    fn from(error: BlockingError) -> ApiError {
+        ApiError::new(
+            500,
+            None,
+            format!("Blocking error: {error}"),
+            Some(ApiErrorKind::BlockingError(error)),
+        )
+    }
+}
+
+impl From<PayloadError> for ApiError {
+    fn from(error: PayloadError) -> ApiError {
+        ApiError::new(
+            400,
+            None,
+            format!("Payload error: {error}"),
+            Some(ApiErrorKind::PayloadError(error)),
+        )
+    }
+}
+
+impl From<SerdeJsonError> for ApiError {
+    fn from(error: SerdeJsonError) -> ApiError {
+        ApiError::new(
+            500,
+            None,
+            format!("serde_json error: {error}"),
+            Some(ApiErrorKind::SerdeJsonError(error)),
+        )
+    }
+}
+
 impl From<DieselError> for ApiError {
     fn from(error: DieselError) -> ApiError {
         match error {
@@ -117,6 +156,20 @@ impl From<DieselError> for ApiError {
     }
 }
 
+impl From<DeviceRecordError> for ApiError {
+    fn from(error: DeviceRecordError) -> ApiError {
+        match error {
+            DeviceRecordError::DeviceIsNotSensor => ApiErrorTemplate::DeviceIsNotSensor(None),
+            DeviceRecordError::DataTooSmall => ApiErrorTemplate::DeviceRecordDataTooSmall(None),
+            DeviceRecordError::DataTooBig => ApiErrorTemplate::DeviceRecordDataTooBig(None),
+            DeviceRecordError::TooLongAgo => ApiErrorTemplate::TooLongAgo(None),
+            DeviceRecordError::FutureTime => ApiErrorTemplate::FutureTime(None),
+            DeviceRecordError::ImportTooSmall => ApiErrorTemplate::DeviceRecordsImportTooSmall(None),
+            DeviceRecordError::ImportTooBig => ApiErrorTemplate::DeviceRecordsImportTooBig(None),
+        }.into()
+    }
+}
+
 impl ResponseError for ApiError {
     fn error_response(&self) -> HttpResponse {
         let status_code = match HttpStatusCode::from_u16(self.http_code) {
@@ -161,6 +214,7 @@ macro_rules! api_error_template {
 
 api_error_template! {
     // Default HTTP errors
+    (401, None, Unauthorized, "Unauthorized");
     (404, None, NotFound, "Not found");
 
     // Minimum / Maximum number of ... reached
@@ -169,8 +223,17 @@ api_error_template! {
     (400, Some(30003), PasswordTooLong, "The password is too long");
     (400, Some(30004), UsernameTooShort, "The username is too short");
     (400, Some(30005), UsernameTooLong, "The username is too long");
+    (400, Some(30006), DeviceRecordDataTooSmall, "The data is too small");
+    (400, Some(30007), DeviceRecordDataTooBig, "The data is too big");
+    (400, Some(30008), TooLongAgo, "Too long ago");
+    (400, Some(30009), FutureTime, "Can't be the future");
+    (400, Some(30010), DeviceRecordsImportTooSmall, "There are no records to import");
+    (400, Some(30011), DeviceRecordsImportTooBig, "There are too many records to import");
 
     // Invalid payload or something else
     (400, Some(40001), EmailInvalid, "Invalid email");
     (400, Some(40002), UsernameInvalidOrTaken, "The username is either invalid or taken");
+    (400, Some(40003), InvalidTimeRange, "Invalid time range");
+    (400, Some(40004), DeviceIsNotSensor, "The device is not a sensor");
+    (400, Some(40005), InvalidCsv, "Invalid CSV");
 }
@@ -18,6 +18,8 @@ async fn main() -> std::io::Result<()> {
     env_logger::init();
 
     db::init();
+    // Imports are stored without the broker, their dispatches are buffered until it's reachable
+    amqp::init_in_background();
     snowflake::init();
 
     let ip = env::var("GLOBAL_API_IP").unwrap_or_else(|_| "127.0.0.1".to_string());
@@ -36,6 +38,7 @@ async fn main() -> std::io::Result<()> {
                         web::scope("")
                             .wrap(services::session::middleware::CheckSession)
                             .configure(services::auth::init_routes)
+                            .configure(services::device_record::init_routes)
                     )
             )
     })
@@ -0,0 +1,23 @@
+pub use domain::device::Device;
+
+use db::schema::devices;
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+
+use crate::error::ApiError;
+
+pub trait DeviceModel: Sized {
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, ApiError>;
+}
+
+impl DeviceModel for Device {
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, ApiError> {
+        let connection = &mut db::get_connection()?;
+
+        let devices = devices::table
+            .filter(devices::greenhouse_id.eq(greenhouse_id))
+            .load(connection)?;
+
+        Ok(devices)
+    }
+}
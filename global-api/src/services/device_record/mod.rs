@@ -0,0 +1,5 @@
+pub use model::*;
+pub use routes::init_routes;
+
+mod model;
+mod routes;
@@ -0,0 +1,310 @@
+pub use domain::device_record::DeviceRecord;
+
+use std::mem;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use db::schema::device_records;
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ApiError, ApiErrorTemplate};
+use crate::services::device::Device;
+
+pub trait DeviceRecordModel: Sized {
+    fn find_all_by_device_ids_between(
+        device_ids: &[i64],
+        range: (SystemTime, SystemTime),
+        after: Option<(SystemTime, i64)>,
+        limit: i64,
+    ) -> Result<Vec<Self>, ApiError>;
+}
+
+impl DeviceRecordModel for DeviceRecord {
+    // Records go in the order of their time, the last record of a page is the cursor of the next
+    fn find_all_by_device_ids_between(
+        device_ids: &[i64],
+        range: (SystemTime, SystemTime),
+        after: Option<(SystemTime, i64)>,
+        limit: i64,
+    ) -> Result<Vec<Self>, ApiError> {
+        let connection = &mut db::get_connection()?;
+
+        let mut query = device_records::table
+            .filter(device_records::device_id.eq_any(device_ids))
+            .filter(device_records::created_at.between(range.0, range.1))
+            .into_boxed();
+
+        if let Some((created_at, id)) = after {
+            query = query.filter(
+                device_records::created_at.gt(created_at)
+                    .or(device_records::created_at.eq(created_at).and(device_records::id.gt(id)))
+            );
+        }
+
+        let device_records = query
+            .order((device_records::created_at.asc(), device_records::id.asc()))
+            .limit(limit)
+            .load(connection)?;
+
+        Ok(device_records)
+    }
+}
+
+// Enough for the maximum number of exported records with long device names
+pub const DEVICE_RECORDS_MAXIMUM_IMPORT_SIZE: usize = 2 * 1024 * 1024;
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceRecordsExportFormat {
+    #[default]
+    Csv,
+    Ndjson,
+}
+
+impl DeviceRecordsExportFormat {
+    pub fn get_content_type(&self) -> &'static str {
+        match self {
+            DeviceRecordsExportFormat::Csv => "text/csv; charset=utf-8",
+            DeviceRecordsExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            DeviceRecordsExportFormat::Csv => "csv",
+            DeviceRecordsExportFormat::Ndjson => "ndjson",
+        }
+    }
+
+    // Only CSV has a header
+    pub fn get_header(&self) -> Option<&'static str> {
+        match self {
+            DeviceRecordsExportFormat::Csv => Some("device_id,device_name,created_at,data,unit\n"),
+            DeviceRecordsExportFormat::Ndjson => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeviceRecordsExportRequest {
+    #[serde(default)]
+    pub format: DeviceRecordsExportFormat,
+    // Comma separated, all devices of the greenhouse without it
+    pub device_ids: Option<String>,
+    // Seconds since the Unix epoch, both inclusive
+    pub from: Option<u64>,
+    pub until: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct DeviceRecordsExportRow<'a> {
+    pub device_id: i64,
+    pub device_name: Option<&'a str>,
+    // ISO 8601 in UTC, spreadsheets recognise it.
+    // Fractions of a second are kept, so an imported export matches the times of existing records
+    pub created_at: String,
+    pub data: f64,
+    pub unit: Option<&'static str>,
+}
+
+impl<'a> DeviceRecordsExportRow<'a> {
+    pub fn new(device_record: &DeviceRecord, device: &'a Device) -> Self {
+        let created_at: DateTime<Utc> = device_record.created_at.into();
+
+        DeviceRecordsExportRow {
+            device_id: device.id,
+            device_name: device.name.as_deref(),
+            created_at: created_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            data: device_record.data,
+            unit: device.kind.get_unit(),
+        }
+    }
+
+    pub fn to_line(&self, format: DeviceRecordsExportFormat) -> Result<String, ApiError> {
+        let line = match format {
+            DeviceRecordsExportFormat::Csv => format!(
+                "{},{},{},{},{}\n",
+                self.device_id,
+                escape_csv_field(self.device_name.unwrap_or("")),
+                self.created_at,
+                self.data,
+                escape_csv_field(self.unit.unwrap_or("")),
+            ),
+            DeviceRecordsExportFormat::Ndjson => format!("{}\n", serde_json::to_string(self)?),
+        };
+
+        Ok(line)
+    }
+}
+
+// Fields with separators, quotes or line breaks are quoted, quotes inside are doubled.
+// Spreadsheets run fields that start like a formula, so these are prefixed to stay text
+fn escape_csv_field(field: &str) -> String {
+    let field = match field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        true => format!("'{field}"),
+        false => field.to_string(),
+    };
+
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field,
+    }
+}
+
+// Columns are found by the header, so a file of the export can be imported back
+pub struct DeviceRecordImportRow {
+    pub device_id: i64,
+    pub created_at: SystemTime,
+    pub data: f64,
+}
+
+impl DeviceRecordImportRow {
+    pub fn parse_csv(body: &str) -> Result<Vec<Self>, ApiError> {
+        let mut rows = parse_csv(body)?.into_iter();
+        let Some(header) = rows.next() else { return Ok(Vec::new()) };
+        let find_column = |name: &str| header.iter()
+            .position(|column| column.trim() == name)
+            .ok_or(ApiErrorTemplate::InvalidCsv(None));
+        let (device_id, created_at, data)
+            = (find_column("device_id")?, find_column("created_at")?, find_column("data")?);
+
+        rows.map(|row| {
+            let field = |index: usize| row.get(index)
+                .map(|field| field.trim())
+                .ok_or(ApiErrorTemplate::InvalidCsv(None));
+
+            let device_id = field(device_id)?.parse::<i64>()
+                .map_err(|_| ApiErrorTemplate::InvalidCsv(None))?;
+            let created_at = field(created_at)?;
+            // ISO 8601 as in the export or seconds since the Unix epoch
+            let created_at = match created_at.parse::<u64>() {
+                Ok(created_at) => UNIX_EPOCH.checked_add(Duration::from_secs(created_at))
+                    .ok_or(ApiErrorTemplate::InvalidCsv(None))?,
+                Err(_) => DateTime::parse_from_rfc3339(created_at)
+                    .map_err(|_| ApiErrorTemplate::InvalidCsv(None))?
+                    .with_timezone(&Utc)
+                    .into(),
+            };
+            let data = field(data)?.parse::<f64>().ok()
+                .filter(|data| data.is_finite())
+                .ok_or(ApiErrorTemplate::InvalidCsv(None))?;
+
+            Ok(DeviceRecordImportRow {
+                device_id,
+                created_at,
+                data,
+            })
+        }).collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct DeviceRecordsImportResponse {
+    pub created: usize,
+    // Records whose time the device already has
+    pub skipped: usize,
+}
+
+// Quoted fields may have separators, doubled quotes and line breaks. Empty lines are skipped.
+// Spreadsheets may start the file with a byte order mark, which isn't part of the header
+fn parse_csv(body: &str) -> Result<Vec<Vec<String>>, ApiError> {
+    let body = body.strip_prefix('\u{feff}').unwrap_or(body);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut is_quoted = false;
+    let mut chars = body.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match (char, is_quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            ('"', _) => is_quoted = !is_quoted,
+            (',', false) => row.push(mem::take(&mut field)),
+            ('\r', false) => {},
+            ('\n', false) => {
+                row.push(mem::take(&mut field));
+                rows.push(mem::take(&mut row));
+            },
+            (char, _) => field.push(char),
+        }
+    }
+
+    if is_quoted { return Err(ApiErrorTemplate::InvalidCsv(None).into()) }
+
+    row.push(field);
+    rows.push(row);
+    rows.retain(|row| row.len() > 1 || !row[0].trim().is_empty());
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::device::{DeviceKind, DeviceStatus};
+
+    use super::*;
+
+    fn get_device(name: &str) -> Device {
+        Device {
+            id: 1,
+            external_id: None,
+            name: Some(name.to_string()),
+            status: DeviceStatus::Online,
+            kind: DeviceKind::TemperatureSensor,
+            greenhouse_id: 2,
+            created_at: UNIX_EPOCH,
+            maximum_data_value: None,
+            polling_interval: 10,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            revert_state: None,
+            revert_at: None,
+        }
+    }
+
+    #[test]
+    fn test_escape_csv_field() {
+        assert_eq!(escape_csv_field("Sensor"), "Sensor");
+        assert_eq!(escape_csv_field("North, left"), "\"North, left\"");
+        assert_eq!(escape_csv_field("The \"big\" one"), "\"The \"\"big\"\" one\"");
+        assert_eq!(escape_csv_field("First\nSecond"), "\"First\nSecond\"");
+        assert_eq!(escape_csv_field("=1+1"), "'=1+1");
+        assert_eq!(escape_csv_field("-1,5"), "\"'-1,5\"");
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let rows = parse_csv("\u{feff}a,b\r\n\"North, left\",\"The \"\"big\"\"\none\"\n\n").unwrap();
+
+        assert_eq!(rows, vec![
+            vec!["a".to_string(), "b".to_string()],
+            vec!["North, left".to_string(), "The \"big\"\none".to_string()],
+        ]);
+        assert!(parse_csv("a,\"b\n").is_err());
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let device = get_device("North, \"big\"\nsensor");
+        let device_record = DeviceRecord {
+            id: 3,
+            device_id: device.id,
+            data: -12.5,
+            created_at: UNIX_EPOCH + Duration::from_micros(1700000000123456),
+        };
+        let format = DeviceRecordsExportFormat::Csv;
+        let line = DeviceRecordsExportRow::new(&device_record, &device).to_line(format).unwrap();
+        let body = format!("\u{feff}{}{line}", format.get_header().unwrap());
+        let rows = DeviceRecordImportRow::parse_csv(&body).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].device_id, device_record.device_id);
+        assert_eq!(rows[0].created_at, device_record.created_at);
+        assert_eq!(rows[0].data, device_record.data);
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, HttpResponse, post, web};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use amqp::routes::{DataCreated, Route};
+use amqp::RoutedMessage;
+use diesel::Connection;
+use domain::amqp::DispatchData;
+use futures_util::{stream, StreamExt, TryStreamExt};
+
+use crate::error::{ApiError, ApiErrorTemplate};
+use crate::services::device::{Device, DeviceModel};
+use crate::services::device_record::{DeviceRecord, DeviceRecordImportRow, DeviceRecordModel, DeviceRecordsExportRequest, DeviceRecordsExportRow, DeviceRecordsImportResponse, DEVICE_RECORDS_MAXIMUM_IMPORT_SIZE};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::session::Session;
+
+// Records are read from the database page by page, so exports of any size take little memory
+const EXPORT_PAGE_SIZE: i64 = 1000;
+
+#[get("/greenhouses/{greenhouse_id}/device-records")]
+pub async fn export(
+    session: web::ReqData<Session>,
+    greenhouse_id: web::Path<i64>,
+    request: web::Query<DeviceRecordsExportRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let Some(user_id) = session.user_id else {
+        return Err(ApiErrorTemplate::Unauthorized(None).into())
+    };
+    let request = request.into_inner();
+    let format = request.format;
+
+    let from = UNIX_EPOCH.checked_add(Duration::from_secs(request.from.unwrap_or(0)))
+        .ok_or(ApiErrorTemplate::InvalidTimeRange(None))?;
+    let until = match request.until {
+        Some(until) => UNIX_EPOCH.checked_add(Duration::from_secs(until))
+            .ok_or(ApiErrorTemplate::InvalidTimeRange(None))?,
+        None => SystemTime::now(),
+    };
+
+    if from > until {
+        return Err(ApiErrorTemplate::InvalidTimeRange(None).into());
+    }
+
+    let greenhouse_id = greenhouse_id.into_inner();
+    let devices = web::block(move || -> Result<Vec<Device>, ApiError> {
+        let greenhouse = Greenhouse::find_by_id_and_owner_id(greenhouse_id, user_id)?;
+
+        Device::find_all_by_greenhouse_id(greenhouse.id)
+    }).await??;
+    let mut devices: HashMap<i64, Device> = devices.into_iter()
+        .map(|device| (device.id, device))
+        .collect();
+
+    if let Some(device_ids) = request.device_ids {
+        let mut selected_devices = HashMap::new();
+
+        for device_id in device_ids.split(',') {
+            let Ok(device_id) = device_id.trim().parse::<i64>() else {
+                return Err(ApiErrorTemplate::NotFound(None).into())
+            };
+
+            // A device that is listed more than once is exported once
+            if selected_devices.contains_key(&device_id) { continue; }
+
+            // Devices of other greenhouses are as unknown as devices that don't exist
+            let Some(device) = devices.remove(&device_id) else {
+                return Err(ApiErrorTemplate::NotFound(None).into())
+            };
+
+            selected_devices.insert(device_id, device);
+        }
+
+        devices = selected_devices;
+    }
+
+    let device_ids: Vec<i64> = devices.keys().copied().collect();
+    let header = format.get_header().map(|header| Ok(web::Bytes::from(header)));
+
+    // The state is the cursor of the next page, `None` when all pages are sent
+    let records = stream::try_unfold(Some(None), move |cursor| {
+        let device_ids = device_ids.clone();
+        let devices = devices.clone();
+
+        async move {
+            let Some(after) = cursor else { return Ok(None) };
+
+            let records = web::block(move || DeviceRecord::find_all_by_device_ids_between(
+                &device_ids,
+                (from, until),
+                after,
+                EXPORT_PAGE_SIZE,
+            )).await??;
+
+            let mut chunk = String::new();
+
+            for record in &records {
+                let Some(device) = devices.get(&record.device_id) else { continue };
+
+                chunk.push_str(&DeviceRecordsExportRow::new(record, device).to_line(format)?);
+            }
+
+            let cursor = match records.len() as i64 == EXPORT_PAGE_SIZE {
+                true => records.last().map(|record| Some((record.created_at, record.id))),
+                false => None,
+            };
+
+            Ok::<_, ApiError>(Some((web::Bytes::from(chunk), cursor)))
+        }
+    });
+
+    let body = stream::iter(header)
+        .chain(records)
+        .map_err(actix_web::Error::from);
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.get_content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(
+                format!("device-records-{greenhouse_id}.{}", format.get_extension())
+            )],
+        })
+        .streaming(body))
+}
+
+// Takes a CSV file with `device_id`, `created_at` and `data` columns
+#[post("/greenhouses/{greenhouse_id}/device-records")]
+pub async fn import(
+    session: web::ReqData<Session>,
+    greenhouse_id: web::Path<i64>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, ApiError> {
+    let Some(user_id) = session.user_id else {
+        return Err(ApiErrorTemplate::Unauthorized(None).into())
+    };
+
+    let mut body = web::BytesMut::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+
+        if body.len() + chunk.len() > DEVICE_RECORDS_MAXIMUM_IMPORT_SIZE {
+            return Err(ApiErrorTemplate::DeviceRecordsImportTooBig(None).into());
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    let body = std::str::from_utf8(&body).map_err(|_| ApiErrorTemplate::InvalidCsv(None))?;
+    let rows = DeviceRecordImportRow::parse_csv(body)?;
+
+    DeviceRecord::check_import_size(&rows.len())?;
+
+    let greenhouse_id = greenhouse_id.into_inner();
+    let (records_count, created_records) = web::block(move || -> Result<_, ApiError> {
+        let greenhouse = Greenhouse::find_by_id_and_owner_id(greenhouse_id, user_id)?;
+        let devices: HashMap<i64, Device> = Device::find_all_by_greenhouse_id(greenhouse.id)?
+            .into_iter()
+            .map(|device| (device.id, device))
+            .collect();
+        let records_count = rows.len();
+        let mut records: HashMap<i64, Vec<(SystemTime, f64)>> = HashMap::new();
+
+        // Nothing is created unless every record is valid
+        for row in rows {
+            let Some(device) = devices.get(&row.device_id) else {
+                return Err(ApiErrorTemplate::NotFound(None).into())
+            };
+
+            DeviceRecord::check_custom_time(&row.created_at)?;
+            DeviceRecord::check_data_size(&device.kind, &row.data)?;
+
+            records.entry(device.id).or_default().push((row.created_at, row.data));
+        }
+
+        let connection = &mut db::get_connection()?;
+
+        // Records of all devices are created together or not at all
+        let created_records = connection.transaction::<_, ApiError, _>(|connection| {
+            records.into_iter()
+                .map(|(device_id, records)| Ok((
+                    device_id,
+                    DeviceRecord::create_all_with_custom_time(connection, device_id, records)?.len(),
+                )))
+                .collect::<Result<HashMap<i64, usize>, ApiError>>()
+        })?;
+
+        Ok((records_count, created_records))
+    }).await??;
+
+    // Subscribers of every device get a single update for the whole import
+    for (device_id, _) in created_records.iter().filter(|(_, created)| **created > 0) {
+        let message = RoutedMessage::new::<DataCreated>(DispatchData { device_id: *device_id }, None);
+
+        if let Err(error) = amqp::publish(message).await {
+            warn!("Failed to publish message with {} routing key: {error}", DataCreated::ROUTING_KEY);
+        }
+    }
+
+    let created = created_records.values().sum();
+
+    Ok(HttpResponse::Ok().json(DeviceRecordsImportResponse {
+        created,
+        skipped: records_count - created,
+    }))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(export);
+    cfg.service(import);
+}
@@ -0,0 +1,24 @@
+pub use domain::greenhouse::Greenhouse;
+
+use db::schema::greenhouses;
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+
+use crate::error::ApiError;
+
+pub trait GreenhouseModel: Sized {
+    fn find_by_id_and_owner_id(id: i64, owner_id: i64) -> Result<Self, ApiError>;
+}
+
+impl GreenhouseModel for Greenhouse {
+    fn find_by_id_and_owner_id(id: i64, owner_id: i64) -> Result<Self, ApiError> {
+        let connection = &mut db::get_connection()?;
+
+        let greenhouse = greenhouses::table
+            .filter(greenhouses::id.eq(id))
+            .filter(greenhouses::owner_id.eq(owner_id))
+            .first(connection)?;
+
+        Ok(greenhouse)
+    }
+}
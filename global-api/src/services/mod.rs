@@ -1,4 +1,7 @@
 pub(crate) mod auth;
+pub(crate) mod device;
+pub(crate) mod device_record;
+pub(crate) mod greenhouse;
 pub(crate) mod session;
 pub(crate) mod system;
 pub(crate) mod user;
@@ -6,6 +6,7 @@ use actix_web_actors::ws::{CloseCode, CloseReason, WebsocketContext};
 use argon2::Error as Argon2Error;
 use argon2::password_hash::Error as Argon2PasswordHashError;
 use diesel::result::Error as DieselError;
+use domain::device_record::DeviceRecordError;
 use r2d2::Error as R2d2Error;
 use serde::Deserialize;
 use serde_eetf::Error as SerdeEetfError;
@@ -157,6 +158,20 @@ impl From<SerdeJsonError> for WebSocketError {
     }
 }
 
+impl From<DeviceRecordError> for WebSocketError {
+    fn from(error: DeviceRecordError) -> WebSocketError {
+        match error {
+            DeviceRecordError::DeviceIsNotSensor => WebSocketErrorTemplate::DeviceIsNotSensor(None),
+            DeviceRecordError::DataTooSmall => WebSocketErrorTemplate::DeviceRecordDataTooSmall(None),
+            DeviceRecordError::DataTooBig => WebSocketErrorTemplate::DeviceRecordDataTooBig(None),
+            DeviceRecordError::TooLongAgo => WebSocketErrorTemplate::TooLongAgo(None),
+            DeviceRecordError::FutureTime => WebSocketErrorTemplate::FutureTime(None),
+            DeviceRecordError::ImportTooSmall => WebSocketErrorTemplate::DeviceRecordsImportTooSmall(None),
+            DeviceRecordError::ImportTooBig => WebSocketErrorTemplate::DeviceRecordsImportTooBig(None),
+        }.into()
+    }
+}
+
 impl fmt::Display for WebSocketError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(self.message.as_str())
@@ -190,6 +205,7 @@ websocket_error_template! {
     (403, None, Forbidden, "Forbidden");
     (404, None, NotFound, "Not found");
     (405, None, MethodNotAllowed, "Method not allowed");
+    (503, None, ServiceUnavailable, "Service unavailable");
 
     // Minimum / Maximum number of ... reached
     (400, Some(30001), GreenhousesTooMany, "There are too many greenhouses");
@@ -208,6 +224,23 @@ websocket_error_template! {
     (400, Some(30014), DeviceRecordDataTooBig, "The data is too big");
     (400, Some(30015), TooLongAgo, "Too long ago");
     (400, Some(30016), FutureTime, "Can't be the future");
+    (400, Some(30017), GreenhouseGatewayUrlTooLong, "Greenhouse gateway URL is too long");
+    (400, Some(30018), DevicePollingIntervalTooShort, "The polling interval is too short");
+    (400, Some(30019), DevicePollingIntervalTooLong, "The polling interval is too long");
+    (400, Some(30020), SchedulesTooMany, "There are too many schedules");
+    (400, Some(30021), SchedulePeriodTooShort, "The schedule period is too short");
+    (400, Some(30022), SchedulePeriodTooLong, "The schedule period is too long");
+    (400, Some(30023), DeviceStateDurationTooShort, "The state duration is too short");
+    (400, Some(30024), DeviceStateDurationTooLong, "The state duration is too long");
+    (400, Some(30025), DevicesTooMany, "There are too many devices");
+    (400, Some(30026), DeviceRecordsLimitTooSmall, "The limit is too small");
+    (400, Some(30027), DeviceRecordsLimitTooBig, "The limit is too big");
+    (400, Some(30028), DeviceRecordsBucketsTooMany, "There are too many buckets");
+    (400, Some(30029), DeviceRecordsImportTooSmall, "There are no records to import");
+    (400, Some(30030), DeviceRecordsImportTooBig, "There are too many records to import");
+    (400, Some(30031), RulesTooMany, "There are too many rules");
+    (400, Some(30032), AlertChannelsTooMany, "There are too many alert channels");
+    (400, Some(30033), AlertChannelTargetTooLong, "The alert channel target is too long");
 
     // Invalid body or something else
     (400, Some(40001), InvalidRequestField, "Invalid request");
@@ -218,6 +251,16 @@ websocket_error_template! {
     (400, Some(40006), InvalidDeviceState, "Invalid device state");
     (400, Some(40007), DeviceIsNotSensor, "The device is not a sensor");
     (400, Some(40008), DeviceIsNotController, "The device is not a controller");
+    (400, Some(40009), GreenhouseGatewayUrlInvalid, "Invalid greenhouse gateway URL");
+    (400, Some(40010), InvalidDeviceQuietHours, "Invalid quiet hours");
+    (400, Some(40011), InvalidScheduleTime, "Invalid schedule time");
+    (400, Some(40012), InvalidScheduleDuration, "Invalid schedule duration");
+    (400, Some(40013), DeviceTaken, "A device with this external ID and kind already exists");
+    (400, Some(40014), InvalidTimeRange, "Invalid time range");
+    (400, Some(40015), InvalidTimezone, "Invalid timezone");
+    (400, Some(40016), InvalidRuleDuration, "Invalid rule duration");
+    (400, Some(40017), InvalidRuleHysteresis, "Invalid rule hysteresis");
+    (400, Some(40018), InvalidAlertChannelTarget, "Invalid alert channel target");
 }
 
 macro_rules! close_error {
@@ -10,7 +10,6 @@ use actix_web::middleware::{NormalizePath, TrailingSlash};
 use actix_web::web::{Data, get, Payload, Query, scope};
 use actix_web_actors::ws;
 use dotenv::dotenv;
-use futures::executor::block_on;
 use serde::{Deserialize, Serialize};
 
 use crate::server::{AmqpClient, Encoding, Socket, WebSocketConnection};
@@ -53,9 +52,7 @@ async fn main() -> std::io::Result<()> {
     amqp::init();
     snowflake::init();
 
-    let amqp_client = block_on(async {
-        Data::new(AmqpClient::new().await.start())
-    });
+    let amqp_client = Data::new(AmqpClient.start());
     let socket = Data::new(Socket::default().start());
 
     let ip = env::var("GLOBAL_WS_IP").unwrap_or_else(|_| "127.0.0.1".to_string());
@@ -1,11 +1,15 @@
 use std::time::UNIX_EPOCH;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_variant::to_variant_name;
 
-use crate::services::device::{Device, DeviceKind, DeviceStatus};
-use crate::services::device_record::{DeviceRecord, DeviceRecordsAverage, DeviceRecordsTimestampRange};
-use crate::services::greenhouse::Greenhouse;
+use crate::services::alert::{Alert, AlertChannelEntry, AlertChannelKind, AlertKind};
+use crate::services::command::{Command, CommandStatus};
+use crate::services::device::{Device, DeviceDataRange, DeviceKind, DeviceQuietHours, DeviceStateRange, DeviceStatus};
+use crate::services::device_record::{DeviceRecord, DeviceRecordEntry, DeviceRecordImportEntry, DeviceRecordModel, DeviceRecordsAggregate, DeviceRecordsAverage, DeviceRecordsBucket, DeviceRecordsBucketEntry, DeviceRecordsTimestampRange};
+use crate::services::greenhouse::{Greenhouse, GreenhouseGatewayAuthScheme};
+use crate::services::rule::{RuleCondition, RuleEntry};
+use crate::services::schedule::{Schedule, ScheduleKind};
 use crate::services::user::{UserMe, UserPublic, UserTheme};
 
 // Tag `a` from the word `action`
@@ -31,6 +35,9 @@ pub enum WebSocketMessageData {
         token: String,
         maximum_average_humidity: Option<f64>,
         minimum_average_temperature: Option<f64>,
+        // An empty URL resets the gateway to the default one
+        gateway_url: Option<String>,
+        gateway_auth_scheme: Option<GreenhouseGatewayAuthScheme>,
     },
     RequestDeleteGreenhouse {
         id: i64,
@@ -41,12 +48,28 @@ pub enum WebSocketMessageData {
         greenhouse_id: i64,
         name: Option<String>,
         maximum_data_value: Option<f64>,
+        polling_interval: Option<i32>,
+        // Absent quiet hours are kept as they are, `null` removes them
+        #[serde(default, deserialize_with = "deserialize_some")]
+        quiet_hours: Option<Option<DeviceQuietHours>>,
+    },
+    RequestPostDevice {
+        greenhouse_id: i64,
+        external_id: Option<i16>,
+        kind: DeviceKind,
+        name: Option<String>,
+    },
+    RequestDeleteDevice {
+        id: i64,
+        greenhouse_id: i64,
     },
     RequestPatchDevicesResetNames { greenhouse_id: i64 },
     RequestPatchDeviceState {
         id: i64,
         greenhouse_id: i64,
         state: u8,
+        // Seconds after which the previous state is restored
+        duration: Option<u32>,
     },
     RequestPostDeviceCustomData {
         id: i64,
@@ -54,6 +77,11 @@ pub enum WebSocketMessageData {
         data: f64,
         time: u64,
     },
+    RequestPostDeviceCustomDataBulk {
+        id: i64,
+        greenhouse_id: i64,
+        records: Vec<DeviceRecordImportEntry>,
+    },
     RequestPostDeviceRequestData {
         id: Option<i64>,
         greenhouse_id: i64,
@@ -66,6 +94,73 @@ pub enum WebSocketMessageData {
         id: i64,
         greenhouse_id: i64,
     },
+    RequestPostDeviceSchedule {
+        device_id: i64,
+        greenhouse_id: i64,
+        kind: ScheduleKind,
+        state: u8,
+        // Minutes since midnight in UTC, required by daily schedules
+        start_time: Option<i16>,
+        end_time: Option<i16>,
+        // Seconds, required by periodic schedules
+        period: Option<i32>,
+        duration: Option<i32>,
+    },
+    RequestDeleteDeviceSchedule {
+        id: i64,
+        device_id: i64,
+        greenhouse_id: i64,
+    },
+    RequestGetDeviceRecords {
+        device_id: i64,
+        greenhouse_id: i64,
+        // Seconds since the Unix epoch, both inclusive
+        from: Option<u64>,
+        until: Option<u64>,
+        // ID of the last record of the previous page
+        before_id: Option<i64>,
+        limit: Option<i64>,
+    },
+    RequestGetDeviceRecordsAggregates {
+        device_id: i64,
+        greenhouse_id: i64,
+        // Seconds since the Unix epoch, both inclusive
+        from: u64,
+        until: Option<u64>,
+        bucket: DeviceRecordsBucket,
+        aggregates: Vec<DeviceRecordsAggregate>,
+        // IANA name, UTC by default
+        timezone: Option<String>,
+    },
+    RequestGetGreenhouseRules { greenhouse_id: i64 },
+    RequestPostGreenhouseRule {
+        greenhouse_id: i64,
+        sensor_id: i64,
+        condition: RuleCondition,
+        threshold: f64,
+        hysteresis: f64,
+        // Seconds the condition has to be met before the rule fires
+        duration: u32,
+        controller_id: i64,
+        state: u8,
+        // Seconds after which the previous state is restored
+        action_duration: Option<u32>,
+    },
+    RequestDeleteGreenhouseRule {
+        id: i64,
+        greenhouse_id: i64,
+    },
+    RequestGetGreenhouseAlertChannels { greenhouse_id: i64 },
+    RequestPostGreenhouseAlertChannel {
+        greenhouse_id: i64,
+        kind: AlertChannelKind,
+        // Email address or webhook URL
+        target: String,
+    },
+    RequestDeleteGreenhouseAlertChannel {
+        id: i64,
+        greenhouse_id: i64,
+    },
 
     // Requests (Opcode: Authorize)
     Authorize { token: String },
@@ -88,6 +183,11 @@ pub enum WebSocketMessageData {
         greenhouse_id: i64,
         range: DeviceRecordsTimestampRange,
     },
+    SubscribeToAlertsUpdate { greenhouse_id: i64 },
+    SubscribeToDeviceSchedulesUpdate {
+        device_id: i64,
+        greenhouse_id: i64,
+    },
 
     // Dispatches
     DispatchUserUpdate {
@@ -113,6 +213,8 @@ pub enum WebSocketMessageData {
         created_at: u64,
         maximum_average_humidity: Option<f64>,
         minimum_average_temperature: Option<f64>,
+        gateway_url: Option<String>,
+        gateway_auth_scheme: GreenhouseGatewayAuthScheme,
     },
     DispatchGreenhouseMineDelete { id: i64 },
     DispatchDeviceUpdate {
@@ -124,8 +226,15 @@ pub enum WebSocketMessageData {
         greenhouse_id: i64,
         created_at: u64,
         maximum_data_value: Option<f64>,
+        polling_interval: i32,
+        quiet_hours: Option<DeviceQuietHours>,
         latest_data: Option<f64>,
+        unit: Option<String>,
+        data_range: Option<DeviceDataRange>,
+        state_range: Option<DeviceStateRange>,
+        state_remaining_time: Option<u64>,
     },
+    DispatchDeviceDelete { id: i64 },
     DispatchDeviceRecordsUpdate {
         device_id: i64,
         quantity: i64,
@@ -135,6 +244,69 @@ pub enum WebSocketMessageData {
         range: DeviceRecordsTimestampRange,
         records: Vec<DeviceRecordsAverage>,
     },
+    DispatchAlertUpdate {
+        id: i64,
+        greenhouse_id: i64,
+        device_id: Option<i64>,
+        kind: AlertKind,
+        data: f64,
+        threshold: f64,
+        created_at: u64,
+        resolved_at: Option<u64>,
+    },
+    DispatchCommandUpdate {
+        id: i64,
+        device_id: i64,
+        state: i16,
+        status: CommandStatus,
+        created_at: u64,
+        updated_at: u64,
+    },
+    DispatchScheduleUpdate {
+        id: i64,
+        device_id: i64,
+        kind: ScheduleKind,
+        state: i16,
+        start_time: Option<i16>,
+        end_time: Option<i16>,
+        period: Option<i32>,
+        duration: Option<i32>,
+        created_at: u64,
+    },
+    DispatchScheduleDelete { id: i64 },
+    DispatchScheduleFire {
+        id: i64,
+        device_id: i64,
+        state: u8,
+    },
+
+    // Responses (Opcode: Response)
+    ResponseDeviceRecords {
+        device_id: i64,
+        records: Vec<DeviceRecordEntry>,
+        // Absent when there are no older records
+        next_before_id: Option<i64>,
+    },
+    ResponseDeviceRecordsAggregates {
+        device_id: i64,
+        bucket: DeviceRecordsBucket,
+        timezone: String,
+        buckets: Vec<DeviceRecordsBucketEntry>,
+    },
+    ResponseDeviceRecordsImport {
+        device_id: i64,
+        created: usize,
+        // Records whose time the device already has
+        skipped: usize,
+    },
+    ResponseGreenhouseRules {
+        greenhouse_id: i64,
+        rules: Vec<RuleEntry>,
+    },
+    ResponseGreenhouseAlertChannels {
+        greenhouse_id: i64,
+        alert_channels: Vec<AlertChannelEntry>,
+    },
 
     // Other
     Response {
@@ -151,6 +323,12 @@ impl WebSocketMessageData {
     }
 }
 
+// Distinguishes an absent field from an explicit `null`
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where T: Deserialize<'de>, D: Deserializer<'de> {
+    T::deserialize(deserializer).map(Some)
+}
+
 impl From<UserPublic> for WebSocketMessageData {
     fn from(user: UserPublic) -> Self {
         WebSocketMessageData::DispatchUserUpdate {
@@ -186,6 +364,8 @@ impl From<Greenhouse> for WebSocketMessageData {
             created_at: greenhouse.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
             maximum_average_humidity: greenhouse.maximum_average_humidity,
             minimum_average_temperature: greenhouse.minimum_average_temperature,
+            gateway_url: greenhouse.gateway_url,
+            gateway_auth_scheme: greenhouse.gateway_auth_scheme,
         }
     }
 }
@@ -196,6 +376,8 @@ impl From<Device> for WebSocketMessageData {
             Ok(record) => Some(record.data),
             Err(_) => None,
         };
+        let quiet_hours = device.get_quiet_hours();
+        let state_remaining_time = device.get_state_remaining_time();
 
         WebSocketMessageData::DispatchDeviceUpdate {
             id: device.id,
@@ -206,7 +388,58 @@ impl From<Device> for WebSocketMessageData {
             greenhouse_id: device.greenhouse_id,
             created_at: device.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
             maximum_data_value: device.maximum_data_value,
+            polling_interval: device.polling_interval,
+            quiet_hours,
             latest_data,
+            unit: device.kind.get_unit().map(str::to_string),
+            data_range: device.kind.get_data_range(),
+            state_range: device.kind.get_state_range(),
+            state_remaining_time,
+        }
+    }
+}
+
+impl From<Alert> for WebSocketMessageData {
+    fn from(alert: Alert) -> Self {
+        WebSocketMessageData::DispatchAlertUpdate {
+            id: alert.id,
+            greenhouse_id: alert.greenhouse_id,
+            device_id: alert.device_id,
+            kind: alert.kind,
+            data: alert.data,
+            threshold: alert.threshold,
+            created_at: alert.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            resolved_at: alert.resolved_at
+                .map(|resolved_at| resolved_at.duration_since(UNIX_EPOCH).unwrap().as_secs()),
+        }
+    }
+}
+
+impl From<Schedule> for WebSocketMessageData {
+    fn from(schedule: Schedule) -> Self {
+        WebSocketMessageData::DispatchScheduleUpdate {
+            id: schedule.id,
+            device_id: schedule.device_id,
+            kind: schedule.kind,
+            state: schedule.state,
+            start_time: schedule.start_time,
+            end_time: schedule.end_time,
+            period: schedule.period,
+            duration: schedule.duration,
+            created_at: schedule.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        }
+    }
+}
+
+impl From<Command> for WebSocketMessageData {
+    fn from(command: Command) -> Self {
+        WebSocketMessageData::DispatchCommandUpdate {
+            id: command.id,
+            device_id: command.device_id,
+            state: command.state,
+            status: command.status,
+            created_at: command.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            updated_at: command.updated_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
         }
     }
 }
@@ -1,5 +1,25 @@
 use actix::{Message, Recipient, WeakAddr};
 use derivative::Derivative;
+pub(crate) use domain::amqp::{
+    AmqpPayload,
+    ChangeControllerState,
+    DispatchAlertCreate,
+    DispatchAlertResolve,
+    DispatchCommand,
+    DispatchData,
+    DispatchDevice,
+    DispatchDeviceCreate,
+    DispatchDeviceDelete,
+    DispatchGreenhouse,
+    DispatchGreenhouseCreate,
+    DispatchGreenhouseDelete,
+    DispatchScheduleCreate,
+    DispatchScheduleDelete,
+    DispatchScheduleFire,
+    DispatchUser,
+    DispatchUserMe,
+    RequestData,
+};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
@@ -92,16 +112,81 @@ pub enum DispatchEvent {
         #[serde(skip)]
         id: i64,
     },
+    DeviceCreate {
+        #[derivative(PartialEq = "ignore")]
+        #[derivative(Hash = "ignore")]
+        #[serde(skip)]
+        id: Option<i64>,
+        #[serde(skip)]
+        greenhouse_id: i64,
+    },
+    DeviceDelete {
+        #[derivative(PartialEq = "ignore")]
+        #[derivative(Hash = "ignore")]
+        #[serde(skip)]
+        id: Option<i64>,
+        #[serde(skip)]
+        greenhouse_id: i64,
+    },
     DeviceRecordsUpdate {
         #[serde(skip)]
         device_id: i64,
     },
+    // Only the connection that requested the command is subscribed to it
+    CommandUpdate {
+        #[serde(skip)]
+        id: i64,
+    },
     DeviceRecordsAverageUpdate {
         #[serde(skip)]
         device_id: i64,
         #[serde(skip)]
         range: DeviceRecordsTimestampRange,
     },
+    AlertCreate {
+        #[derivative(PartialEq = "ignore")]
+        #[derivative(Hash = "ignore")]
+        #[serde(skip)]
+        id: Option<i64>,
+        #[serde(skip)]
+        greenhouse_id: i64,
+    },
+    AlertResolve {
+        #[derivative(PartialEq = "ignore")]
+        #[derivative(Hash = "ignore")]
+        #[serde(skip)]
+        id: Option<i64>,
+        #[serde(skip)]
+        greenhouse_id: i64,
+    },
+    ScheduleCreate {
+        #[derivative(PartialEq = "ignore")]
+        #[derivative(Hash = "ignore")]
+        #[serde(skip)]
+        id: Option<i64>,
+        #[serde(skip)]
+        device_id: i64,
+    },
+    ScheduleDelete {
+        #[derivative(PartialEq = "ignore")]
+        #[derivative(Hash = "ignore")]
+        #[serde(skip)]
+        id: Option<i64>,
+        #[serde(skip)]
+        device_id: i64,
+    },
+    ScheduleFire {
+        #[derivative(PartialEq = "ignore")]
+        #[derivative(Hash = "ignore")]
+        #[serde(skip)]
+        id: Option<i64>,
+        #[derivative(PartialEq = "ignore")]
+        #[derivative(Hash = "ignore")]
+        #[serde(skip)]
+        state: u8,
+        #[serde(skip)]
+        device_id: i64,
+    },
 }
 
 #[derive(Debug, Message)]
@@ -132,35 +217,6 @@ pub struct DisconnectionMessage {
     pub connection_id: i64,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
-#[serde(rename_all = "snake_case")]
-pub enum AmqpPayload {
-    DispatchData {
-        device_id: i64,
-    },
-    RequestData {
-        device_id: Option<i64>,
-        greenhouse_id: Option<i64>,
-    },
-    ChangeControllerState {
-        device_id: i64,
-        state: u8,
-    },
-    DispatchDevice {
-        id: i64,
-    },
-    #[default]
-    Ping,
-}
-
 #[derive(Clone, Debug, Message)]
 #[rtype(result = "()")]
 pub struct InitAmqpConsumersMessage(pub WeakAddr<Socket>);
-
-#[derive(Clone, Debug, Default, Message)]
-#[rtype(result = "()")]
-pub struct AmqpPublisherMessage<'a> {
-    pub exchange: Option<&'a str>,
-    pub routing_key: Option<&'a str>,
-    pub payload: AmqpPayload,
-}
@@ -1,28 +1,27 @@
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::time::Instant;
 
 use actix::{ActorFutureExt, AsyncContext, ContextFutureSpawner, Message, WeakRecipient, WrapFuture};
 use actix::prelude::{Actor, Context, Handler, Recipient};
 use actix_broker::{BrokerIssue, BrokerSubscribe};
 use actix_web_actors::ws::WebsocketContext;
+use amqp::RoutedMessage;
+use chrono_tz::Tz;
 
 use crate::error::{WebSocketCloseError, WebSocketError, WebSocketErrorTemplate};
-use crate::messages::{AmqpPayload, AuthorizationMessage, DisconnectionMessage, DispatchAmqpMessage, DispatchEvent, DispatchMessage, InitAmqpConsumersMessage, Opcode, WebSocketMessage, WebSocketMessageData};
+use crate::messages::{AmqpPayload, AuthorizationMessage, DisconnectionMessage, DispatchAlertCreate, DispatchAlertResolve, DispatchAmqpMessage, DispatchCommand, DispatchData, DispatchDevice, DispatchDeviceCreate, DispatchDeviceDelete, DispatchEvent, DispatchGreenhouse, DispatchGreenhouseCreate, DispatchGreenhouseDelete, DispatchMessage, DispatchScheduleCreate, DispatchScheduleDelete, DispatchScheduleFire, DispatchUser, DispatchUserMe, InitAmqpConsumersMessage, Opcode, WebSocketMessage, WebSocketMessageData};
 use crate::server::WebSocketConnection;
-use crate::services::{device, device_record, greenhouse, user};
-use crate::services::device::Device;
-use crate::services::device_record::{DeviceRecord, DeviceRecordsAverage, DeviceRecordsTimestampRange};
-use crate::services::greenhouse::Greenhouse;
+use crate::services::{alert, device, device_record, greenhouse, rule, schedule, user};
+use crate::services::alert::{Alert, AlertModel};
+use crate::services::command::{Command, CommandModel, CommandStatus};
+use crate::services::device::{Device, DeviceModel};
+use crate::services::device_record::{DeviceRecord, DeviceRecordModel, DeviceRecordsAverage};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::schedule::{Schedule, ScheduleModel};
 use crate::services::session::Session;
 use crate::services::user::{UserMe, UserPublic};
 
-const MINUTE_AS_SECS: u64 = 60;
-const HOUR_AS_SECS: u64 = MINUTE_AS_SECS * 60;
-const DAY_AS_SECS: u64 = HOUR_AS_SECS * 24;
-const WEEK_AS_SECS: u64 = DAY_AS_SECS * 7;
-const MONTH_AS_SECS: u64 = 365 / 12 * DAY_AS_SECS;
-
 #[derive(Debug, Default)]
 pub struct Socket {
     connections: HashMap<i64, (Recipient<WebSocketMessage>, HashSet<DispatchEvent>)>,
@@ -122,10 +121,15 @@ impl Socket {
                     "device"
                     | "device/state"
                     | "device/custom-data"
+                    | "device/custom-data/bulk"
                     | "device/request-data"
                     | "device/disable"
                     | "device/enable"
                     | "devices/reset-names" => device::handle,
+                    "device/schedule" => schedule::handle,
+                    "greenhouse/rule" | "greenhouse/rules" => rule::handle,
+                    "greenhouse/alert-channel" | "greenhouse/alert-channels" => alert::handle,
+                    "device_records" | "device_records/aggregates" => device_record::handle,
                     _ => return Err(WebSocketErrorTemplate::BadRequest(None).into()),
                 };
 
@@ -147,6 +151,8 @@ impl Socket {
                     | "greenhouse-delete" => greenhouse::subscribe,
                     "device" | "devices" => device::subscribe,
                     "device_records" | "device_records/average" => device_record::subscribe,
+                    "device/schedules" => schedule::subscribe,
+                    "alerts" => alert::subscribe,
                     _ => return Err(WebSocketErrorTemplate::BadRequest(None).into()),
                 };
 
@@ -205,6 +211,70 @@ impl Socket {
         Ok(())
     }
 
+    /// Publishes the AMQP message and gives the outcome to `then`,
+    /// so a request is answered only once its message is published or buffered
+    pub fn publish_message<F>(
+        message_id: i64,
+        message: RoutedMessage,
+        connection: &mut WebSocketConnection,
+        context: &mut WebsocketContext<WebSocketConnection>,
+        then: F,
+    )
+        where
+            F: FnOnce(
+                Result<(), WebSocketError>,
+                &mut WebSocketConnection,
+                &mut WebsocketContext<WebSocketConnection>,
+            ) -> Result<(), WebSocketError> + 'static {
+        let routing_key = message.get_binding().routing_key;
+
+        async move { amqp::publish(message).await }
+            .into_actor(connection)
+            .map(move |result,
+                       connection,
+                       context,
+            | {
+                let result = result.map_err(|error| {
+                    warn!("Failed to publish message with {routing_key} routing key: {error}");
+
+                    WebSocketErrorTemplate::ServiceUnavailable(None).into()
+                });
+
+                if let Err(error) = then(result, connection, context) {
+                    context.address().do_send(WebSocketMessage {
+                        id: message_id,
+                        connection_id: connection.id,
+                        opcode: Opcode::Error,
+                        data: WebSocketMessageData::Response {
+                            code: error.json_code,
+                            message: error.get_safe_message(),
+                        },
+                        ..Default::default()
+                    });
+                }
+            })
+            .spawn(context);
+    }
+
+    /// Publishes an event caused by a request, every instance, this one too,
+    /// dispatches it to its subscribers once it's consumed
+    pub fn publish_dispatch(
+        message: RoutedMessage,
+        connection: &mut WebSocketConnection,
+        context: &mut WebsocketContext<WebSocketConnection>,
+    ) {
+        let routing_key = message.get_binding().routing_key;
+
+        // The request is done already, so subscribers just miss the event
+        async move {
+            if let Err(error) = amqp::publish(message).await {
+                warn!("Failed to publish message with {routing_key} routing key: {error}");
+            }
+        }
+            .into_actor(connection)
+            .spawn(context);
+    }
+
     fn get_connection(&self, id: &i64) -> Result<&(Recipient<WebSocketMessage>, HashSet<DispatchEvent>), WebSocketError> {
         match self.connections.get(id) {
             Some(connection) => Ok(connection),
@@ -289,6 +359,29 @@ impl Handler<DispatchMessage> for Socket {
             DispatchEvent::DeviceUpdate { id } => {
                 WebSocketMessageData::from(Device::find(id)?)
             },
+            DispatchEvent::DeviceCreate { id, greenhouse_id } => {
+                match id {
+                    Some(id) => {
+                        event = DispatchEvent::DeviceCreate { id: None, greenhouse_id };
+
+                        WebSocketMessageData::from(Device::find(id)?)
+                    },
+                    None => WebSocketMessageData::None,
+                }
+            },
+            DispatchEvent::DeviceDelete { id, greenhouse_id } => {
+                match id {
+                    Some(id) => {
+                        event = DispatchEvent::DeviceDelete { id: None, greenhouse_id };
+
+                        WebSocketMessageData::DispatchDeviceDelete { id }
+                    },
+                    None => WebSocketMessageData::None,
+                }
+            },
+            DispatchEvent::CommandUpdate { id } => {
+                WebSocketMessageData::from(Command::find(id)?)
+            },
             DispatchEvent::DeviceRecordsUpdate { device_id } => {
                 WebSocketMessageData::DispatchDeviceRecordsUpdate {
                     device_id,
@@ -299,47 +392,83 @@ impl Handler<DispatchMessage> for Socket {
                 device_id,
                 range,
             } => {
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH).unwrap()
-                    .as_secs();
-                let mut records = vec![];
-
-                let (now, iterations, interval): (u64, u64, u64) = match range {
-                    DeviceRecordsTimestampRange::Today => (now, 24, HOUR_AS_SECS),
-                    DeviceRecordsTimestampRange::Week => (now, 7, DAY_AS_SECS),
-                    DeviceRecordsTimestampRange::Month => (now, 5, WEEK_AS_SECS),
-                    DeviceRecordsTimestampRange::LastMonth =>
-                        (now - MONTH_AS_SECS, 5, WEEK_AS_SECS),
-                    DeviceRecordsTimestampRange::MonthBeforeLast =>
-                        (now - MONTH_AS_SECS * 2, 5, WEEK_AS_SECS),
-                    DeviceRecordsTimestampRange::LastThreeMoths => (now, 3, MONTH_AS_SECS),
-                };
-
-                for i in 0..iterations {
-                    let from = now - interval * (i + 1);
-                    let until = now - interval * i;
-
-                    let data
-                        = DeviceRecord::get_average_between_timestamp_by_device_id(
-                        device_id,
-                        (
-                            UNIX_EPOCH + Duration::from_secs(from),
-                            UNIX_EPOCH + Duration::from_secs(until),
-                        ),
-                    )?.map(|data| (data * 100.0).trunc() / 100.0);
-
-                    records.push(DeviceRecordsAverage {
-                        data,
-                        range: (from, until),
-                    });
-                }
+                let (timestamp_range, bucket) = range.get_range_and_bucket();
+                let averages: HashMap<u64, f64> = DeviceRecord::aggregate_between_timestamp_by_device_id(
+                    device_id,
+                    timestamp_range,
+                    bucket,
+                    Tz::UTC,
+                )?
+                    .into_iter()
+                    .map(|bucket| (bucket.bucket_start as u64, bucket.average))
+                    .collect();
+
+                // The web client lines up buckets of devices by their index,
+                // so buckets without records are sent too
+                let records = bucket.get_ranges_between(timestamp_range)
+                    .into_iter()
+                    .map(|range| DeviceRecordsAverage {
+                        data: averages.get(&range.0).map(|data| (data * 100.0).trunc() / 100.0),
+                        range,
+                    })
+                    .collect();
 
                 WebSocketMessageData::DispatchDeviceRecordsAverageUpdate {
                     device_id,
                     range,
                     records,
                 }
-            }
+            },
+            DispatchEvent::AlertCreate { id, greenhouse_id } => {
+                match id {
+                    Some(id) => {
+                        event = DispatchEvent::AlertCreate { id: None, greenhouse_id };
+
+                        WebSocketMessageData::from(Alert::find(id)?)
+                    },
+                    None => WebSocketMessageData::None,
+                }
+            },
+            DispatchEvent::AlertResolve { id, greenhouse_id } => {
+                match id {
+                    Some(id) => {
+                        event = DispatchEvent::AlertResolve { id: None, greenhouse_id };
+
+                        WebSocketMessageData::from(Alert::find(id)?)
+                    },
+                    None => WebSocketMessageData::None,
+                }
+            },
+            DispatchEvent::ScheduleCreate { id, device_id } => {
+                match id {
+                    Some(id) => {
+                        event = DispatchEvent::ScheduleCreate { id: None, device_id };
+
+                        WebSocketMessageData::from(Schedule::find(id)?)
+                    },
+                    None => WebSocketMessageData::None,
+                }
+            },
+            DispatchEvent::ScheduleDelete { id, device_id } => {
+                match id {
+                    Some(id) => {
+                        event = DispatchEvent::ScheduleDelete { id: None, device_id };
+
+                        WebSocketMessageData::DispatchScheduleDelete { id }
+                    },
+                    None => WebSocketMessageData::None,
+                }
+            },
+            DispatchEvent::ScheduleFire { id, state, device_id } => {
+                match id {
+                    Some(id) => {
+                        event = DispatchEvent::ScheduleFire { id: None, state: 0, device_id };
+
+                        WebSocketMessageData::DispatchScheduleFire { id, device_id, state }
+                    },
+                    None => WebSocketMessageData::None,
+                }
+            },
         };
 
         match new_subscribers {
@@ -390,6 +519,20 @@ impl Handler<DispatchMessage> for Socket {
             },
         };
 
+        // Finished commands aren't updated anymore, so nobody has to stay subscribed to them
+        if let WebSocketMessageData::DispatchCommandUpdate { status, .. } = data {
+            if status != CommandStatus::Pending {
+                if let Some(subscribers) = self.subscriptions.remove(&event) {
+                    for subscriber_id in subscribers {
+                        if let Some((_, subscriptions))
+                            = self.connections.get_mut(&subscriber_id) {
+                            subscriptions.remove(&event);
+                        }
+                    }
+                }
+            }
+        }
+
         Ok(())
     }
 }
@@ -403,7 +546,7 @@ impl Handler<DispatchAmqpMessage> for Socket {
         context: &mut Self::Context,
     ) -> Self::Result {
         match message.payload {
-            AmqpPayload::DispatchData { device_id } => {
+            AmqpPayload::DispatchData(DispatchData { device_id }) => {
                 context.address().do_send(DispatchMessage {
                     event: DispatchEvent::DeviceUpdate { id: device_id },
                     new_subscribers: None,
@@ -428,12 +571,90 @@ impl Handler<DispatchAmqpMessage> for Socket {
                 //     });
                 // }
             },
-            AmqpPayload::DispatchDevice { id } => {
+            AmqpPayload::DispatchDevice(DispatchDevice { id }) => {
                 context.address().do_send(DispatchMessage {
                     event: DispatchEvent::DeviceUpdate { id },
                     new_subscribers: None,
                 });
             },
+            AmqpPayload::DispatchAlertCreate(DispatchAlertCreate { id, greenhouse_id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::AlertCreate { id: Some(id), greenhouse_id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchAlertResolve(DispatchAlertResolve { id, greenhouse_id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::AlertResolve { id: Some(id), greenhouse_id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchDeviceCreate(DispatchDeviceCreate { id, greenhouse_id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::DeviceCreate { id: Some(id), greenhouse_id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchCommand(DispatchCommand { id, .. }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::CommandUpdate { id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchScheduleFire(DispatchScheduleFire { id, device_id, state }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::ScheduleFire { id: Some(id), state, device_id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchUser(DispatchUser { id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::UserUpdate { id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchUserMe(DispatchUserMe { id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::UserMeUpdate { id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchGreenhouse(DispatchGreenhouse { id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::GreenhouseUpdate { id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchGreenhouseCreate(DispatchGreenhouseCreate { id, owner_id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::GreenhouseCreate { id: Some(id), owner_id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchGreenhouseDelete(DispatchGreenhouseDelete { id, owner_id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::GreenhouseDelete { id: Some(id), owner_id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchDeviceDelete(DispatchDeviceDelete { id, greenhouse_id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::DeviceDelete { id: Some(id), greenhouse_id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchScheduleCreate(DispatchScheduleCreate { id, device_id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::ScheduleCreate { id: Some(id), device_id },
+                    new_subscribers: None,
+                });
+            },
+            AmqpPayload::DispatchScheduleDelete(DispatchScheduleDelete { id, device_id }) => {
+                context.address().do_send(DispatchMessage {
+                    event: DispatchEvent::ScheduleDelete { id: Some(id), device_id },
+                    new_subscribers: None,
+                });
+            },
             AmqpPayload::Ping => debug!("Got Ping from AMQP"),
             _ => {},
         }
@@ -0,0 +1,161 @@
+use actix_web_actors::ws::WebsocketContext;
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+use crate::messages::{Method, Opcode, WebSocketMessage, WebSocketMessageData};
+use crate::server::{Socket, WebSocketConnection};
+use crate::services::alert::{AlertChannel, AlertChannelModel, NewAlertChannel, ALERT_CHANNELS_MAXIMUM_PER_GREENHOUSE};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::session::Session;
+
+fn get_alert_channels(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestGetGreenhouseAlertChannels { greenhouse_id }
+        = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let alert_channels = AlertChannel::find_all_by_greenhouse_id(greenhouse.id)?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::ResponseGreenhouseAlertChannels {
+            greenhouse_id: greenhouse.id,
+            alert_channels: alert_channels.into_iter().map(Into::into).collect(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    Ok(())
+}
+
+fn create_alert_channel(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestPostGreenhouseAlertChannel { greenhouse_id, kind, target }
+        = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+
+    if AlertChannel::count_by_greenhouse_id(greenhouse.id)? >= ALERT_CHANNELS_MAXIMUM_PER_GREENHOUSE {
+        return Err(WebSocketErrorTemplate::AlertChannelsTooMany(None).into());
+    }
+
+    AlertChannel::check_target(&kind, &target)?;
+    AlertChannel::create(NewAlertChannel {
+        greenhouse_id: greenhouse.id,
+        kind,
+        target,
+    })?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::Response {
+            code: 200,
+            message: "Successfully created".to_string(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    Ok(())
+}
+
+fn delete_alert_channel(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestDeleteGreenhouseAlertChannel { id: alert_channel_id, greenhouse_id }
+        = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let alert_channel
+        = AlertChannel::find_by_id_and_greenhouse_id(alert_channel_id, greenhouse.id)?;
+
+    AlertChannel::delete(alert_channel.id)?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::Response {
+            code: 200,
+            message: "Successfully deleted".to_string(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    Ok(())
+}
+
+pub fn handle(
+    request: String,
+    method: Method,
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    match method {
+        Method::Get => match request.as_str() {
+            "greenhouse/alert-channels" => get_alert_channels(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        Method::Post => match request.as_str() {
+            "greenhouse/alert-channel" => create_alert_channel(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        Method::Delete => match request.as_str() {
+            "greenhouse/alert-channel" => delete_alert_channel(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        _ => return Err(WebSocketErrorTemplate::MethodNotAllowed(None).into()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+pub use domain::alert::{Alert, AlertChannel, AlertChannelKind, AlertKind};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use db::schema::{alert_channels, alerts};
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+use crate::utils::dns;
+
+pub trait AlertModel: Sized {
+    fn find(id: i64) -> Result<Self, WebSocketError>;
+    fn find_all_open_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError>;
+}
+
+impl AlertModel for Alert {
+    fn find(id: i64) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let alert = alerts::table
+            .filter(alerts::id.eq(id))
+            .first(connection)?;
+
+        Ok(alert)
+    }
+
+    fn find_all_open_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let alerts = alerts::table
+            .filter(alerts::greenhouse_id.eq(greenhouse_id))
+            .filter(alerts::resolved_at.is_null())
+            .load(connection)?;
+
+        Ok(alerts)
+    }
+}
+
+pub const ALERT_CHANNELS_MAXIMUM_PER_GREENHOUSE: i64 = 10;
+
+pub trait AlertChannelModel: Sized {
+    fn create(alert_channel: NewAlertChannel) -> Result<Self, WebSocketError>;
+    fn find_by_id_and_greenhouse_id(id: i64, greenhouse_id: i64) -> Result<Self, WebSocketError>;
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError>;
+    fn count_by_greenhouse_id(greenhouse_id: i64) -> Result<i64, WebSocketError>;
+    fn delete(id: i64) -> Result<usize, WebSocketError>;
+    fn check_target(kind: &AlertChannelKind, target: &str) -> Result<(), WebSocketError>;
+}
+
+impl AlertChannelModel for AlertChannel {
+    // CRUD
+    fn create(alert_channel: NewAlertChannel) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let alert_channel = AlertChannel {
+            id: snowflake::generate(),
+            greenhouse_id: alert_channel.greenhouse_id,
+            kind: alert_channel.kind,
+            target: alert_channel.target,
+            created_at: SystemTime::now(),
+        };
+
+        let alert_channel = diesel::insert_into(alert_channels::table)
+            .values(&alert_channel)
+            .get_result(connection)?;
+
+        Ok(alert_channel)
+    }
+
+    fn find_by_id_and_greenhouse_id(id: i64, greenhouse_id: i64) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let alert_channel = alert_channels::table
+            .filter(alert_channels::id.eq(id))
+            .filter(alert_channels::greenhouse_id.eq(greenhouse_id))
+            .first(connection)?;
+
+        Ok(alert_channel)
+    }
+
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let alert_channels = alert_channels::table
+            .filter(alert_channels::greenhouse_id.eq(greenhouse_id))
+            .order(alert_channels::id)
+            .load(connection)?;
+
+        Ok(alert_channels)
+    }
+
+    fn count_by_greenhouse_id(greenhouse_id: i64) -> Result<i64, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let alert_channels = alert_channels::table
+            .filter(alert_channels::greenhouse_id.eq(greenhouse_id))
+            .count()
+            .get_result(connection)?;
+
+        Ok(alert_channels)
+    }
+
+    fn delete(id: i64) -> Result<usize, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let result = diesel::delete(
+            alert_channels::table.filter(alert_channels::id.eq(id))
+        ).execute(connection)?;
+
+        Ok(result)
+    }
+
+    // Default implementations
+    fn check_target(kind: &AlertChannelKind, target: &str) -> Result<(), WebSocketError> {
+        if target.chars().count() > 256 {
+            return Err(WebSocketErrorTemplate::AlertChannelTargetTooLong(None).into());
+        }
+
+        let is_valid = match kind {
+            AlertChannelKind::Email => match target.split_once('@') {
+                Some((local_part, domain)) if !local_part.is_empty() && !domain.contains('@') => {
+                    dns::get_mx_records(domain)
+                        .map(|records| !records.is_empty())
+                        .unwrap_or(false)
+                },
+                _ => false,
+            },
+            // The worker posts to the webhook, so it must not be pointed at internal services
+            AlertChannelKind::Webhook => dns::is_public_http_url(target),
+        };
+
+        match is_valid {
+            true => Ok(()),
+            false => Err(WebSocketErrorTemplate::InvalidAlertChannelTarget(None).into()),
+        }
+    }
+}
+
+pub struct NewAlertChannel {
+    pub greenhouse_id: i64,
+    pub kind: AlertChannelKind,
+    pub target: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AlertChannelEntry {
+    pub(crate) id: i64,
+    pub(crate) kind: AlertChannelKind,
+    pub(crate) target: String,
+    pub(crate) created_at: u64,
+}
+
+impl From<AlertChannel> for AlertChannelEntry {
+    fn from(alert_channel: AlertChannel) -> Self {
+        AlertChannelEntry {
+            id: alert_channel.id,
+            kind: alert_channel.kind,
+            target: alert_channel.target,
+            created_at: alert_channel.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use actix_web_actors::ws::WebsocketContext;
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+use crate::messages::{DispatchEvent, DispatchMessage, WebSocketMessage, WebSocketMessageData};
+use crate::server::{Socket, WebSocketConnection};
+use crate::services::alert::{Alert, AlertModel};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::session::Session;
+
+fn alerts_update(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::SubscribeToAlertsUpdate { greenhouse_id }
+        = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+
+    // Already open incidents are sent as if they have just been created
+    let mut events: Vec<DispatchEvent> = Alert::find_all_open_by_greenhouse_id(greenhouse.id)?
+        .into_iter()
+        .map(|alert| DispatchEvent::AlertCreate { id: Some(alert.id), greenhouse_id: greenhouse.id })
+        .collect();
+
+    events.push(DispatchEvent::AlertCreate { id: None, greenhouse_id: greenhouse.id });
+    events.push(DispatchEvent::AlertResolve { id: None, greenhouse_id: greenhouse.id });
+
+    for event in events {
+        let response = DispatchMessage {
+            event,
+            new_subscribers: Some(vec![connection.id]),
+        };
+
+        Socket::send_message(
+            message.id,
+            response,
+            connection.socket.downgrade().recipient(),
+            connection,
+            context,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn subscribe(
+    to: String,
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    match to.as_str() {
+        "alerts" => alerts_update(message, connection, context)?,
+        _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,3 @@
+pub use model::*;
+
+mod model;
@@ -0,0 +1,63 @@
+pub use domain::command::{Command, CommandStatus};
+
+use std::time::SystemTime;
+
+use db::schema::commands;
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+
+use crate::error::WebSocketError;
+
+pub trait CommandModel: Sized {
+    fn create(command: NewCommand) -> Result<Self, WebSocketError>;
+    fn find(id: i64) -> Result<Self, WebSocketError>;
+    fn delete(id: i64) -> Result<usize, WebSocketError>;
+}
+
+impl CommandModel for Command {
+    // CRUD
+    fn create(command: NewCommand) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+        let now = SystemTime::now();
+
+        let command = Command {
+            id: snowflake::generate(),
+            device_id: command.device_id,
+            state: command.state,
+            status: CommandStatus::Pending,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let command = diesel::insert_into(commands::table)
+            .values(&command)
+            .get_result(connection)?;
+
+        Ok(command)
+    }
+
+    fn find(id: i64) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let command = commands::table
+            .filter(commands::id.eq(id))
+            .first(connection)?;
+
+        Ok(command)
+    }
+
+    fn delete(id: i64) -> Result<usize, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let result = diesel::delete(
+            commands::table.filter(commands::id.eq(id))
+        ).execute(connection)?;
+
+        Ok(result)
+    }
+}
+
+pub struct NewCommand {
+    pub device_id: i64,
+    pub state: i16,
+}
@@ -1,16 +1,137 @@
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-
-use actix_broker::{Broker, SystemBroker};
 use actix_web_actors::ws::WebsocketContext;
+use amqp::routes::{DataCreated, DataRequest, DeviceControllerStateChange, DeviceCreated, DeviceDeleted, DeviceUpdated};
+use amqp::RoutedMessage;
 
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
-use crate::messages::{AmqpPayload, AmqpPublisherMessage, DispatchEvent, DispatchMessage, Method, Opcode, WebSocketMessage, WebSocketMessageData};
+use crate::messages::{ChangeControllerState, DispatchData, DispatchDevice, DispatchDeviceCreate, DispatchDeviceDelete, DispatchEvent, DispatchMessage, Method, Opcode, RequestData, WebSocketMessage, WebSocketMessageData};
 use crate::server::{Socket, WebSocketConnection};
-use crate::services::device::{Device, DeviceKind, DeviceStatus};
-use crate::services::device_record::{DeviceRecord, NewDeviceRecord};
-use crate::services::greenhouse::Greenhouse;
+use crate::services::command::{Command, CommandModel, NewCommand};
+use crate::services::device::{Device, DeviceModel, DeviceStatus, NewDevice, DEVICES_MAXIMUM_PER_GREENHOUSE};
+use crate::services::device_record::{DeviceRecord, DeviceRecordModel, NewDeviceRecord};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
 use crate::services::session::Session;
 
+fn create_device(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestPostDevice {
+        greenhouse_id,
+        external_id,
+        kind,
+        name,
+    } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    if let Some(name) = &name { Device::check_name_length(name)?; }
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+
+    match Device::find_by_external_id_and_kind_and_greenhouse_id(external_id, kind, greenhouse.id) {
+        Ok(_) => return Err(WebSocketErrorTemplate::DeviceTaken(None).into()),
+        Err(error) => if error.http_code != 404 { return Err(error) },
+    };
+
+    if Device::count_by_greenhouse_id(greenhouse.id)? >= DEVICES_MAXIMUM_PER_GREENHOUSE {
+        return Err(WebSocketErrorTemplate::DevicesTooMany(None).into());
+    }
+
+    let device = Device::create(NewDevice {
+        external_id,
+        name,
+        kind,
+        greenhouse_id: greenhouse.id,
+    })?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::Response {
+            code: 200,
+            message: "Successfully created".to_string(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    // Notify all those who are subscribed to devices of this greenhouse
+    Socket::publish_dispatch(
+        RoutedMessage::new::<DeviceCreated>(
+            DispatchDeviceCreate { id: device.id, greenhouse_id: greenhouse.id },
+            None,
+        ),
+        connection,
+        context,
+    );
+
+    Ok(())
+}
+
+fn delete_device(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestDeleteDevice {
+        id: device_id,
+        greenhouse_id,
+    } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
+
+    Device::delete(device.id)?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::Response {
+            code: 200,
+            message: "Successfully deleted".to_string(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    // Notify all those who are subscribed to devices of this greenhouse
+    Socket::publish_dispatch(
+        RoutedMessage::new::<DeviceDeleted>(
+            DispatchDeviceDelete { id: device.id, greenhouse_id: greenhouse.id },
+            None,
+        ),
+        connection,
+        context,
+    );
+
+    Ok(())
+}
+
 fn patch_device(
     message: WebSocketMessage,
     connection: &mut WebSocketConnection,
@@ -21,6 +142,8 @@ fn patch_device(
         greenhouse_id,
         name: new_name,
         maximum_data_value: new_maximum_data_value,
+        polling_interval: new_polling_interval,
+        quiet_hours: new_quiet_hours,
     } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
 
     let session = Session::find(connection.session_id.unwrap())?;
@@ -34,23 +157,39 @@ fn patch_device(
     if current_device.name != new_name
         || current_device.maximum_data_value != new_maximum_data_value {
         if let Some(name) = &new_name { Device::check_name_length(name)?; }
-        if let Some(maximum_data_value)
-            = &new_maximum_data_value { DeviceRecord::check_data_size(maximum_data_value)?; }
+        if let Some(maximum_data_value) = &new_maximum_data_value {
+            DeviceRecord::check_data_size(&current_device.kind, maximum_data_value)?;
+        }
 
         let updated_device
             = Device::update_name(current_device.id, new_name, new_maximum_data_value)?;
-        let response = DispatchMessage {
-            event: DispatchEvent::DeviceUpdate { id: updated_device.id },
-            new_subscribers: None,
-        };
-
-        Socket::send_message(
-            message.id,
-            response,
-            connection.socket.downgrade().recipient(),
+        Socket::publish_dispatch(
+            RoutedMessage::new::<DeviceUpdated>(DispatchDevice { id: updated_device.id }, None),
             connection,
             context,
+        );
+    }
+
+    let new_polling_interval
+        = new_polling_interval.unwrap_or(current_device.polling_interval);
+    let new_quiet_hours
+        = new_quiet_hours.unwrap_or_else(|| current_device.get_quiet_hours());
+
+    if current_device.polling_interval != new_polling_interval
+        || current_device.get_quiet_hours() != new_quiet_hours {
+        Device::check_polling_interval(&new_polling_interval)?;
+        if let Some(quiet_hours) = &new_quiet_hours { Device::check_quiet_hours(quiet_hours)?; }
+
+        let updated_device = Device::update_polling_schedule(
+            current_device.id,
+            new_polling_interval,
+            new_quiet_hours,
         )?;
+        Socket::publish_dispatch(
+            RoutedMessage::new::<DeviceUpdated>(DispatchDevice { id: updated_device.id }, None),
+            connection,
+            context,
+        );
     }
 
     // Response to request
@@ -82,10 +221,10 @@ fn patch_device_state(
     context: &mut WebsocketContext<WebSocketConnection>,
 ) -> Result<(), WebSocketError> {
     let WebSocketMessageData::RequestPatchDeviceState {
-        id: device_id, greenhouse_id, state,
+        id: device_id, greenhouse_id, state, duration,
     } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
 
-    if state > 1 { return Err(WebSocketErrorTemplate::InvalidDeviceState(None).into()); }
+    if let Some(duration) = &duration { Device::check_state_duration(duration)?; }
 
     let session = Session::find(connection.session_id.unwrap())?;
     let Some(session_user_id)
@@ -94,40 +233,66 @@ fn patch_device_state(
         = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
     let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
 
-    if device.kind != DeviceKind::HumidificationController
-        && device.kind != DeviceKind::IrrigationController
-        && device.kind != DeviceKind::WindowsController {
-        return Err(WebSocketErrorTemplate::DeviceIsNotController(None).into());
-    }
+    Device::check_state(&device.kind, &state)?;
 
-    Broker::<SystemBroker>::issue_async(AmqpPublisherMessage {
-        exchange: Some("device"),
-        routing_key: Some("device.controller.state.change"),
-        payload: AmqpPayload::ChangeControllerState {
+    let command = Command::create(NewCommand {
+        device_id: device.id,
+        state: i16::from(state),
+    })?;
+
+    let message_id = message.id;
+    let routed_message = RoutedMessage::new::<DeviceControllerStateChange>(
+        ChangeControllerState {
+            command_id: command.id,
             device_id,
             state,
+            duration,
         },
-    });
+        None,
+    );
 
-    // Response to request
-    let response = WebSocketMessage {
-        id: message.id,
-        connection_id: connection.id,
-        opcode: Opcode::Response,
-        data: WebSocketMessageData::Response {
-            code: 200,
-            message: "Successfully requested".to_string(),
-        },
-        ..Default::default()
-    };
+    Socket::publish_message(message_id, routed_message, connection, context, move |result, connection, context| {
+        // The command is never applied, so the user is told to try again later
+        if let Err(error) = result {
+            Command::delete(command.id)?;
 
-    Socket::send_message(
-        message.id,
-        response,
-        connection.socket.downgrade().recipient(),
-        connection,
-        context,
-    )?;
+            return Err(error);
+        }
+
+        // Response to request
+        let response = WebSocketMessage {
+            id: message_id,
+            connection_id: connection.id,
+            opcode: Opcode::Response,
+            data: WebSocketMessageData::Response {
+                code: 200,
+                message: "Successfully requested".to_string(),
+            },
+            ..Default::default()
+        };
+
+        Socket::send_message(
+            message_id,
+            response,
+            connection.socket.downgrade().recipient(),
+            connection,
+            context,
+        )?;
+
+        // The requester gets the pending command now and its outcome later
+        let response = DispatchMessage {
+            event: DispatchEvent::CommandUpdate { id: command.id },
+            new_subscribers: Some(vec![connection.id]),
+        };
+
+        Socket::send_message(
+            message_id,
+            response,
+            connection.socket.downgrade().recipient(),
+            connection,
+            context,
+        )
+    });
 
     Ok(())
 }
@@ -153,18 +318,11 @@ fn reset_device_names(
         Device::update_name_by_greenhouse_id(greenhouse.id, None)?;
 
         for device in filtered_devices {
-            let response = DispatchMessage {
-                event: DispatchEvent::DeviceUpdate { id: device.id },
-                new_subscribers: None,
-            };
-
-            Socket::send_message(
-                message.id,
-                response,
-                connection.socket.downgrade().recipient(),
+            Socket::publish_dispatch(
+                RoutedMessage::new::<DeviceUpdated>(DispatchDevice { id: device.id }, None),
                 connection,
                 context,
-            )?;
+            );
         }
     }
 
@@ -200,20 +358,7 @@ fn post_device_custom_data(
         id: device_id, greenhouse_id, data, time,
     } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
 
-    DeviceRecord::check_data_size(&data)?;
-
-    let time = Duration::from_secs(time);
-    let three_month_ago = (SystemTime::now() - Duration::from_secs(2629743 * 3))
-        .duration_since(UNIX_EPOCH).unwrap();
-    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-
-    match time {
-        time if time < three_month_ago =>
-            return Err(WebSocketErrorTemplate::TooLongAgo(None).into()),
-        time if time > now =>
-            return Err(WebSocketErrorTemplate::FutureTime(None).into()),
-        _ => {},
-    }
+    let time = DeviceRecord::get_custom_time(time)?;
 
     let session = Session::find(connection.session_id.unwrap())?;
     let Some(session_user_id)
@@ -222,15 +367,11 @@ fn post_device_custom_data(
         = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
     let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
 
-    if device.kind != DeviceKind::HumiditySensor
-        && device.kind != DeviceKind::SoilMoistureSensor
-        && device.kind != DeviceKind::TemperatureSensor {
-        return Err(WebSocketErrorTemplate::DeviceIsNotSensor(None).into());
-    }
+    DeviceRecord::check_data_size(&device.kind, &data)?;
 
     let record = NewDeviceRecord { device_id: device.id, data };
     let record
-        = DeviceRecord::create_with_custom_time(record, UNIX_EPOCH + time)?;
+        = DeviceRecord::create_with_custom_time(record, time)?;
 
     // Response to request
     let response = WebSocketMessage {
@@ -254,33 +395,19 @@ fn post_device_custom_data(
 
     if record.id == DeviceRecord::find_latest_by_device_id(device.id)?.id {
         // Notify all those who are subscribed to this device
-        let response = DispatchMessage {
-            event: DispatchEvent::DeviceUpdate { id: device.id },
-            new_subscribers: None,
-        };
-
-        Socket::send_message(
-            message.id,
-            response,
-            connection.socket.downgrade().recipient(),
+        Socket::publish_dispatch(
+            RoutedMessage::new::<DeviceUpdated>(DispatchDevice { id: device.id }, None),
             connection,
             context,
-        )?;
+        );
     }
 
     // Notify all those who are subscribed to this device records
-    let response = DispatchMessage {
-        event: DispatchEvent::DeviceRecordsUpdate { device_id: device.id },
-        new_subscribers: None,
-    };
-
-    Socket::send_message(
-        message.id,
-        response,
-        connection.socket.downgrade().recipient(),
+    Socket::publish_dispatch(
+        RoutedMessage::new::<DataCreated>(DispatchData { device_id: device.id }, None),
         connection,
         context,
-    )?;
+    );
 
     // TODO: Uncomment when correct time parsing in dispatcher is done
     // let device_records_average_ranges = vec![
@@ -311,43 +438,47 @@ fn post_device_custom_data(
     Ok(())
 }
 
-fn post_device_request_data(
+fn post_device_custom_data_bulk(
     message: WebSocketMessage,
     connection: &mut WebSocketConnection,
     context: &mut WebsocketContext<WebSocketConnection>,
 ) -> Result<(), WebSocketError> {
-    let WebSocketMessageData::RequestPostDeviceRequestData {
-        id: device_id, greenhouse_id,
+    let WebSocketMessageData::RequestPostDeviceCustomDataBulk {
+        id: device_id, greenhouse_id, records,
     } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
 
+    DeviceRecord::check_import_size(&records.len())?;
+
     let session = Session::find(connection.session_id.unwrap())?;
     let Some(session_user_id)
         = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
     let greenhouse
         = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
 
-    if let Some(device_id) = device_id {
-        let device = Device::find(device_id)?;
+    // Nothing is created unless every record is valid
+    let records = records.into_iter()
+        .map(|record| {
+            let time = DeviceRecord::get_custom_time(record.time)?;
 
-        if device.greenhouse_id != greenhouse.id {
-            return Err(WebSocketErrorTemplate::NotFound(None).into());
-        }
-    }
+            DeviceRecord::check_data_size(&device.kind, &record.data)?;
 
-    Broker::<SystemBroker>::issue_async(AmqpPublisherMessage {
-        exchange: Some("data"),
-        routing_key: Some("data.request"),
-        payload: AmqpPayload::RequestData { device_id, greenhouse_id: Some(greenhouse_id) },
-    });
+            Ok((time, record.data))
+        })
+        .collect::<Result<Vec<_>, WebSocketError>>()?;
+    let records_count = records.len();
+    let records
+        = DeviceRecord::create_all_with_custom_time(&mut *db::get_connection()?, device.id, records)?;
 
     // Response to request
     let response = WebSocketMessage {
         id: message.id,
         connection_id: connection.id,
         opcode: Opcode::Response,
-        data: WebSocketMessageData::Response {
-            code: 200,
-            message: "Successfully requested".to_string(),
+        data: WebSocketMessageData::ResponseDeviceRecordsImport {
+            device_id: device.id,
+            created: records.len(),
+            skipped: records_count - records.len(),
         },
         ..Default::default()
     };
@@ -360,6 +491,82 @@ fn post_device_request_data(
         context,
     )?;
 
+    if records.is_empty() { return Ok(()) }
+
+    let latest_record_id = DeviceRecord::find_latest_by_device_id(device.id)?.id;
+
+    if records.iter().any(|record| record.id == latest_record_id) {
+        // Notify all those who are subscribed to this device
+        Socket::publish_dispatch(
+            RoutedMessage::new::<DeviceUpdated>(DispatchDevice { id: device.id }, None),
+            connection,
+            context,
+        );
+    }
+
+    // Notify all those who are subscribed to this device records once for the whole import
+    Socket::publish_dispatch(
+        RoutedMessage::new::<DataCreated>(DispatchData { device_id: device.id }, None),
+        connection,
+        context,
+    );
+
+    Ok(())
+}
+
+fn post_device_request_data(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestPostDeviceRequestData {
+        id: device_id, greenhouse_id,
+    } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+
+    if let Some(device_id) = device_id {
+        let device = Device::find(device_id)?;
+
+        if device.greenhouse_id != greenhouse.id {
+            return Err(WebSocketErrorTemplate::NotFound(None).into());
+        }
+    }
+
+    let message_id = message.id;
+    let routed_message = RoutedMessage::new::<DataRequest>(
+        RequestData { device_id, greenhouse_id: Some(greenhouse_id) },
+        None,
+    );
+
+    Socket::publish_message(message_id, routed_message, connection, context, move |result, connection, context| {
+        result?;
+
+        // Response to request
+        let response = WebSocketMessage {
+            id: message_id,
+            connection_id: connection.id,
+            opcode: Opcode::Response,
+            data: WebSocketMessageData::Response {
+                code: 200,
+                message: "Successfully requested".to_string(),
+            },
+            ..Default::default()
+        };
+
+        Socket::send_message(
+            message_id,
+            response,
+            connection.socket.downgrade().recipient(),
+            connection,
+            context,
+        )
+    });
+
     Ok(())
 }
 
@@ -388,18 +595,11 @@ fn post_device_status(
         Device::update_status(device.id, new_status)?;
 
         // Notify all those who are subscribed to this device
-        let response = DispatchMessage {
-            event: DispatchEvent::DeviceUpdate { id: device.id },
-            new_subscribers: None,
-        };
-
-        Socket::send_message(
-            message.id,
-            response,
-            connection.socket.downgrade().recipient(),
+        Socket::publish_dispatch(
+            RoutedMessage::new::<DeviceUpdated>(DispatchDevice { id: device.id }, None),
             connection,
             context,
-        )?;
+        );
     }
 
     // Response to request
@@ -444,7 +644,10 @@ pub fn handle(
             _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
         },
         Method::Post => match request.as_str() {
+            "device" => create_device(message, connection, context)?,
             "device/custom-data" => post_device_custom_data(message, connection, context)?,
+            "device/custom-data/bulk" =>
+                post_device_custom_data_bulk(message, connection, context)?,
             "device/request-data" => post_device_request_data(message, connection, context)?,
             "device/disable" =>
                 post_device_status(message, connection, context, DeviceStatus::Disabled)?,
@@ -452,6 +655,10 @@ pub fn handle(
                 post_device_status(message, connection, context, DeviceStatus::Online)?,
             _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
         },
+        Method::Delete => match request.as_str() {
+            "device" => delete_device(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
         _ => return Err(WebSocketErrorTemplate::MethodNotAllowed(None).into()),
     }
 
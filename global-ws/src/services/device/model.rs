@@ -1,35 +1,88 @@
-use std::mem::transmute;
+pub use domain::device::{Device, DeviceDataRange, DeviceKind, DeviceQuietHours, DeviceStateRange, DeviceStatus, IgnoredDevice};
+
 use std::time::SystemTime;
 
-use db::schema::devices;
-use diesel::{deserialize, ExpressionMethods, Insertable, Queryable, RunQueryDsl};
-use diesel::deserialize::FromStaticSqlRow;
-use diesel::expression::AsExpression;
-use diesel::helper_types::AsExprOf;
-use diesel::pg::Pg;
+use db::schema::{devices, ignored_devices};
+use diesel::{ExpressionMethods, RunQueryDsl};
 use diesel::prelude::*;
-use diesel::row::Row;
-use diesel::sql_types::SmallInt;
-use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
 
-#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
-#[diesel(table_name = devices)]
-pub struct Device {
-    pub id: i64,
-    pub external_id: Option<i16>,
-    pub name: Option<String>,
-    pub status: DeviceStatus,
-    pub kind: DeviceKind,
-    pub greenhouse_id: i64,
-    pub created_at: SystemTime,
-    pub maximum_data_value: Option<f64>,
+pub const DEVICES_MAXIMUM_PER_GREENHOUSE: i64 = 64;
+
+pub trait DeviceModel: Sized {
+    fn create(device: NewDevice) -> Result<Self, WebSocketError>;
+    fn find(id: i64) -> Result<Self, WebSocketError>;
+    fn find_by_id_and_greenhouse_id(id: i64, greenhouse_id: i64) -> Result<Self, WebSocketError>;
+    fn find_by_external_id_and_kind_and_greenhouse_id(
+        external_id: Option<i16>,
+        kind: DeviceKind,
+        greenhouse_id: i64,
+    ) -> Result<Self, WebSocketError>;
+    fn count_by_greenhouse_id(greenhouse_id: i64) -> Result<i64, WebSocketError>;
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError>;
+    fn update_name(
+        id: i64,
+        new_name: Option<String>,
+        new_maximum_data_value: Option<f64>,
+    ) -> Result<Self, WebSocketError>;
+    fn update_polling_schedule(
+        id: i64,
+        new_polling_interval: i32,
+        new_quiet_hours: Option<DeviceQuietHours>,
+    ) -> Result<Self, WebSocketError>;
+    fn update_name_by_greenhouse_id(
+        greenhouse_id: i64,
+        new_name: Option<String>,
+    ) -> Result<usize, WebSocketError>;
+    fn update_status(id: i64, new_status: DeviceStatus) -> Result<Self, WebSocketError>;
+    fn delete(id: i64) -> Result<usize, WebSocketError>;
+    fn check_name_length(name: &str) -> Result<(), WebSocketError>;
+    fn check_polling_interval(polling_interval: &i32) -> Result<(), WebSocketError>;
+    fn check_quiet_hours(quiet_hours: &DeviceQuietHours) -> Result<(), WebSocketError>;
+    fn check_state_duration(duration: &u32) -> Result<(), WebSocketError>;
+    fn check_state(kind: &DeviceKind, state: &u8) -> Result<(), WebSocketError>;
 }
 
-impl Device {
-    pub fn find(id: i64) -> Result<Self, WebSocketError> {
+impl DeviceModel for Device {
+    // CRUD
+    fn create(device: NewDevice) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let device = Device {
+            id: snowflake::generate(),
+            external_id: device.external_id,
+            name: device.name,
+            status: DeviceStatus::Online,
+            kind: device.kind,
+            greenhouse_id: device.greenhouse_id,
+            created_at: SystemTime::now(),
+            maximum_data_value: None,
+            polling_interval: 60,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            revert_state: None,
+            revert_at: None,
+        };
+
+        // A device that was deleted before is created by its owner again
+        connection.transaction::<_, WebSocketError, _>(|connection| {
+            diesel::delete(
+                ignored_devices::table
+                    .filter(ignored_devices::external_id.is_not_distinct_from(device.external_id))
+                    .filter(ignored_devices::kind.eq(device.kind))
+                    .filter(ignored_devices::greenhouse_id.eq(device.greenhouse_id))
+            ).execute(connection)?;
+
+            let device = diesel::insert_into(devices::table)
+                .values(&device)
+                .get_result(connection)?;
+
+            Ok(device)
+        })
+    }
+
+    fn find(id: i64) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let device = devices::table
@@ -39,7 +92,7 @@ impl Device {
         Ok(device)
     }
 
-    pub fn find_by_id_and_greenhouse_id(id: i64, greenhouse_id: i64) -> Result<Self, WebSocketError> {
+    fn find_by_id_and_greenhouse_id(id: i64, greenhouse_id: i64) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let device = devices::table
@@ -50,7 +103,35 @@ impl Device {
         Ok(device)
     }
 
-    pub fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError> {
+    // Devices without an external ID are unique by their kind as well
+    fn find_by_external_id_and_kind_and_greenhouse_id(
+        external_id: Option<i16>,
+        kind: DeviceKind,
+        greenhouse_id: i64,
+    ) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let device = devices::table
+            .filter(devices::external_id.is_not_distinct_from(external_id))
+            .filter(devices::kind.eq(kind))
+            .filter(devices::greenhouse_id.eq(greenhouse_id))
+            .first(connection)?;
+
+        Ok(device)
+    }
+
+    fn count_by_greenhouse_id(greenhouse_id: i64) -> Result<i64, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let devices = devices::table
+            .filter(devices::greenhouse_id.eq(greenhouse_id))
+            .count()
+            .get_result(connection)?;
+
+        Ok(devices)
+    }
+
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let devices = devices::table
@@ -60,7 +141,7 @@ impl Device {
         Ok(devices)
     }
 
-    pub fn update_name(
+    fn update_name(
         id: i64,
         new_name: Option<String>,
         new_maximum_data_value: Option<f64>,
@@ -78,7 +159,26 @@ impl Device {
         Ok(device)
     }
 
-    pub fn update_name_by_greenhouse_id(
+    fn update_polling_schedule(
+        id: i64,
+        new_polling_interval: i32,
+        new_quiet_hours: Option<DeviceQuietHours>,
+    ) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let device = diesel::update(devices::table)
+            .filter(devices::id.eq(id))
+            .set((
+                devices::polling_interval.eq(new_polling_interval),
+                devices::quiet_hours_start.eq(new_quiet_hours.map(|hours| hours.start)),
+                devices::quiet_hours_end.eq(new_quiet_hours.map(|hours| hours.end)),
+            ))
+            .get_result(connection)?;
+
+        Ok(device)
+    }
+
+    fn update_name_by_greenhouse_id(
         greenhouse_id: i64,
         new_name: Option<String>,
     ) -> Result<usize, WebSocketError> {
@@ -92,7 +192,7 @@ impl Device {
         Ok(result)
     }
 
-    pub fn update_status(id: i64, new_status: DeviceStatus) -> Result<Self, WebSocketError> {
+    fn update_status(id: i64, new_status: DeviceStatus) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let device = diesel::update(devices::table)
@@ -103,8 +203,35 @@ impl Device {
         Ok(device)
     }
 
+    fn delete(id: i64) -> Result<usize, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        // Device discovery must not create the deleted device again
+        connection.transaction::<_, WebSocketError, _>(|connection| {
+            let devices: Vec<Device> = diesel::delete(
+                devices::table.filter(devices::id.eq(id))
+            ).get_results(connection)?;
+
+            let ignored_devices: Vec<IgnoredDevice> = devices.iter()
+                .map(|device| IgnoredDevice {
+                    id: snowflake::generate(),
+                    external_id: device.external_id,
+                    kind: device.kind,
+                    greenhouse_id: device.greenhouse_id,
+                    created_at: SystemTime::now(),
+                })
+                .collect();
+
+            diesel::insert_into(ignored_devices::table)
+                .values(&ignored_devices)
+                .execute(connection)?;
+
+            Ok(devices.len())
+        })
+    }
+
     // Default implementations
-    pub fn check_name_length(name: &str) -> Result<(), WebSocketError> {
+    fn check_name_length(name: &str) -> Result<(), WebSocketError> {
         let name_length = name.chars().count();
 
         match name_length {
@@ -117,83 +244,57 @@ impl Device {
             _ => Ok(())
         }
     }
-}
-
-#[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
-#[repr(i16)]
-pub enum DeviceStatus {
-    Offline = 0,
-    Online = 1,
-    Disabled = 2,
-}
-
-impl FromStaticSqlRow<SmallInt, Pg> for DeviceStatus {
-    fn build_from_row<'a>(row: &impl Row<'a, Pg>) -> deserialize::Result<Self> {
-        Ok(unsafe { transmute(i16::build_from_row(row)?) })
-    }
-}
-
-impl AsExpression<SmallInt> for DeviceStatus {
-    type Expression = AsExprOf<i16, SmallInt>;
-
-    fn as_expression(self) -> Self::Expression {
-        <i16 as AsExpression<SmallInt>>::as_expression(self as i16)
-    }
-}
-
-impl<'a> AsExpression<SmallInt> for &'a DeviceStatus {
-    type Expression = AsExprOf<i16, SmallInt>;
-
-    fn as_expression(self) -> Self::Expression {
-        <i16 as AsExpression<SmallInt>>::as_expression(*self as i16)
-    }
-}
 
-impl Queryable<SmallInt, Pg> for DeviceStatus {
-    type Row = i16;
-
-    fn build(row: Self::Row) -> deserialize::Result<Self> {
-        Ok(unsafe { transmute(row) })
+    fn check_polling_interval(polling_interval: &i32) -> Result<(), WebSocketError> {
+        match polling_interval {
+            interval if *interval < 10 => Err(
+                WebSocketErrorTemplate::DevicePollingIntervalTooShort(None).into()
+            ),
+            interval if *interval > 86400 => Err(
+                WebSocketErrorTemplate::DevicePollingIntervalTooLong(None).into()
+            ),
+            _ => Ok(())
+        }
     }
-}
 
-#[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
-#[repr(i16)]
-pub enum DeviceKind {
-    HumiditySensor = 0,
-    SoilMoistureSensor = 1,
-    TemperatureSensor = 2,
-    HumidificationController = 3,
-    IrrigationController = 4,
-    WindowsController = 5,
-}
+    fn check_quiet_hours(quiet_hours: &DeviceQuietHours) -> Result<(), WebSocketError> {
+        let minutes = 0..1440;
 
-impl FromStaticSqlRow<SmallInt, Pg> for DeviceKind {
-    fn build_from_row<'a>(row: &impl Row<'a, Pg>) -> deserialize::Result<Self> {
-        Ok(unsafe { transmute(i16::build_from_row(row)?) })
+        match minutes.contains(&quiet_hours.start)
+            && minutes.contains(&quiet_hours.end)
+            && quiet_hours.start != quiet_hours.end {
+            true => Ok(()),
+            false => Err(WebSocketErrorTemplate::InvalidDeviceQuietHours(None).into()),
+        }
     }
-}
 
-impl AsExpression<SmallInt> for DeviceKind {
-    type Expression = AsExprOf<i16, SmallInt>;
-
-    fn as_expression(self) -> Self::Expression {
-        <i16 as AsExpression<SmallInt>>::as_expression(self as i16)
+    fn check_state_duration(duration: &u32) -> Result<(), WebSocketError> {
+        match duration {
+            duration if *duration < 10 => Err(
+                WebSocketErrorTemplate::DeviceStateDurationTooShort(None).into()
+            ),
+            duration if *duration > 86400 => Err(
+                WebSocketErrorTemplate::DeviceStateDurationTooLong(None).into()
+            ),
+            _ => Ok(())
+        }
     }
-}
 
-impl<'a> AsExpression<SmallInt> for &'a DeviceKind {
-    type Expression = AsExprOf<i16, SmallInt>;
+    fn check_state(kind: &DeviceKind, state: &u8) -> Result<(), WebSocketError> {
+        let Some(state_range) = kind.get_state_range() else {
+            return Err(WebSocketErrorTemplate::DeviceIsNotController(None).into())
+        };
 
-    fn as_expression(self) -> Self::Expression {
-        <i16 as AsExpression<SmallInt>>::as_expression(*self as i16)
+        match state_range.contains(state) {
+            true => Ok(()),
+            false => Err(WebSocketErrorTemplate::InvalidDeviceState(None).into()),
+        }
     }
 }
 
-impl Queryable<SmallInt, Pg> for DeviceKind {
-    type Row = i16;
-
-    fn build(row: Self::Row) -> deserialize::Result<Self> {
-        Ok(unsafe { transmute(row) })
-    }
+pub struct NewDevice {
+    pub external_id: Option<i16>,
+    pub name: Option<String>,
+    pub kind: DeviceKind,
+    pub greenhouse_id: i64,
 }
@@ -3,8 +3,8 @@ use actix_web_actors::ws::WebsocketContext;
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
 use crate::messages::{DispatchEvent, DispatchMessage, WebSocketMessage, WebSocketMessageData};
 use crate::server::{Socket, WebSocketConnection};
-use crate::services::device::Device;
-use crate::services::greenhouse::Greenhouse;
+use crate::services::device::{Device, DeviceModel};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
 use crate::services::session::Session;
 
 fn device_update(
@@ -53,9 +53,17 @@ fn devices_update(
         = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
     let devices = Device::find_all_by_greenhouse_id(greenhouse.id)?;
 
-    for device in devices {
+    let mut events: Vec<DispatchEvent> = devices.iter()
+        .map(|device| DispatchEvent::DeviceUpdate { id: device.id })
+        .collect();
+
+    // Devices added to or deleted from the greenhouse later
+    events.push(DispatchEvent::DeviceCreate { id: None, greenhouse_id: greenhouse.id });
+    events.push(DispatchEvent::DeviceDelete { id: None, greenhouse_id: greenhouse.id });
+
+    for event in events {
         let response = DispatchMessage {
-            event: DispatchEvent::DeviceUpdate { id: device.id },
+            event,
             new_subscribers: Some(vec![connection.id]),
         };
 
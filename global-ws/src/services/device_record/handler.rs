@@ -0,0 +1,191 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web_actors::ws::WebsocketContext;
+use chrono_tz::Tz;
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+use crate::messages::{Method, Opcode, WebSocketMessage, WebSocketMessageData};
+use crate::server::{Socket, WebSocketConnection};
+use crate::services::device::{Device, DeviceModel};
+use crate::services::device_record::{DeviceRecord, DeviceRecordModel, DeviceRecordsBucketEntry, DEVICE_RECORDS_DEFAULT_LIMIT};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::session::Session;
+
+fn get_device_records(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestGetDeviceRecords {
+        device_id,
+        greenhouse_id,
+        from,
+        until,
+        before_id,
+        limit,
+    } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let from = UNIX_EPOCH.checked_add(Duration::from_secs(from.unwrap_or(0)))
+        .ok_or(WebSocketErrorTemplate::InvalidTimeRange(None))?;
+    let until = match until {
+        Some(until) => UNIX_EPOCH.checked_add(Duration::from_secs(until))
+            .ok_or(WebSocketErrorTemplate::InvalidTimeRange(None))?,
+        None => SystemTime::now(),
+    };
+
+    if from > until {
+        return Err(WebSocketErrorTemplate::InvalidTimeRange(None).into());
+    }
+
+    let limit = limit.unwrap_or(DEVICE_RECORDS_DEFAULT_LIMIT);
+
+    DeviceRecord::check_limit(&limit)?;
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
+    let before = match before_id {
+        Some(before_id) => {
+            let record = DeviceRecord::find_by_id_and_device_id(before_id, device.id)?;
+
+            Some((record.created_at, record.id))
+        },
+        None => None,
+    };
+
+    // One record more than asked tells whether there is a next page
+    let mut records = DeviceRecord::find_all_by_device_id_between(
+        device.id,
+        (from, until),
+        before,
+        limit + 1,
+    )?;
+    let next_before_id = match records.len() as i64 > limit {
+        true => {
+            records.truncate(limit as usize);
+            records.last().map(|record| record.id)
+        },
+        false => None,
+    };
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::ResponseDeviceRecords {
+            device_id: device.id,
+            records: records.into_iter().map(Into::into).collect(),
+            next_before_id,
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    Ok(())
+}
+
+fn get_device_records_aggregates(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestGetDeviceRecordsAggregates {
+        device_id,
+        greenhouse_id,
+        from,
+        until,
+        bucket,
+        aggregates,
+        timezone,
+    } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    if aggregates.is_empty() {
+        return Err(WebSocketErrorTemplate::BadRequest(None).into());
+    }
+
+    let timezone = match timezone {
+        Some(timezone) => timezone.parse::<Tz>()
+            .map_err(|_| WebSocketErrorTemplate::InvalidTimezone(None))?,
+        None => Tz::UTC,
+    };
+    let from = UNIX_EPOCH.checked_add(Duration::from_secs(from))
+        .ok_or(WebSocketErrorTemplate::InvalidTimeRange(None))?;
+    let until = match until {
+        Some(until) => UNIX_EPOCH.checked_add(Duration::from_secs(until))
+            .ok_or(WebSocketErrorTemplate::InvalidTimeRange(None))?,
+        None => SystemTime::now(),
+    };
+
+    DeviceRecord::check_buckets(&(from, until), &bucket)?;
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
+
+    let buckets = DeviceRecord::aggregate_between_timestamp_by_device_id(
+        device.id,
+        (from, until),
+        bucket,
+        timezone,
+    )?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::ResponseDeviceRecordsAggregates {
+            device_id: device.id,
+            bucket,
+            timezone: timezone.name().to_string(),
+            buckets: buckets.into_iter()
+                .map(|bucket| DeviceRecordsBucketEntry::new(bucket, &aggregates))
+                .collect(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    Ok(())
+}
+
+pub fn handle(
+    request: String,
+    method: Method,
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    match method {
+        Method::Get => match request.as_str() {
+            "device_records" => get_device_records(message, connection, context)?,
+            "device_records/aggregates" =>
+                get_device_records_aggregates(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        _ => return Err(WebSocketErrorTemplate::MethodNotAllowed(None).into()),
+    }
+
+    Ok(())
+}
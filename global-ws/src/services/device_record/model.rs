@@ -1,26 +1,48 @@
-use std::time::SystemTime;
+pub use domain::device_record::DeviceRecord;
 
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Datelike, Days, DurationRound, Months, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
 use db::schema::device_records;
-use diesel::{Insertable, Queryable, RunQueryDsl};
-use diesel::dsl::avg;
+use diesel::RunQueryDsl;
 use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Text, Timestamp};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
 
-#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
-#[diesel(table_name = device_records)]
-pub struct DeviceRecord {
-    pub id: i64,
-    pub device_id: i64,
-    pub data: f64,
-    pub created_at: SystemTime,
+pub trait DeviceRecordModel: Sized {
+    fn create_with_custom_time(
+        device_record: NewDeviceRecord,
+        time: SystemTime,
+    ) -> Result<Self, WebSocketError>;
+    fn find_by_id_and_device_id(id: i64, device_id: i64) -> Result<Self, WebSocketError>;
+    fn find_latest_by_device_id(device_id: i64) -> Result<Self, WebSocketError>;
+    fn count_by_device_id(device_id: i64) -> Result<i64, WebSocketError>;
+    fn aggregate_between_timestamp_by_device_id(
+        device_id: i64,
+        range: (SystemTime, SystemTime),
+        bucket: DeviceRecordsBucket,
+        timezone: Tz,
+    ) -> Result<Vec<DeviceRecordsBucketAggregates>, WebSocketError>;
+    fn find_all_by_device_id_between(
+        device_id: i64,
+        range: (SystemTime, SystemTime),
+        before: Option<(SystemTime, i64)>,
+        limit: i64,
+    ) -> Result<Vec<Self>, WebSocketError>;
+    fn check_limit(limit: &i64) -> Result<(), WebSocketError>;
+    fn check_buckets(
+        range: &(SystemTime, SystemTime),
+        bucket: &DeviceRecordsBucket,
+    ) -> Result<(), WebSocketError>;
 }
 
-impl DeviceRecord {
+impl DeviceRecordModel for DeviceRecord {
     // CRUD
-    pub fn create_with_custom_time(
+    fn create_with_custom_time(
         device_record: NewDeviceRecord,
         time: SystemTime,
     ) -> Result<Self, WebSocketError> {
@@ -40,7 +62,18 @@ impl DeviceRecord {
         Ok(device_record)
     }
 
-    pub fn find_latest_by_device_id(device_id: i64) -> Result<Self, WebSocketError> {
+    fn find_by_id_and_device_id(id: i64, device_id: i64) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let device_record = device_records::table
+            .filter(device_records::id.eq(id))
+            .filter(device_records::device_id.eq(device_id))
+            .first(connection)?;
+
+        Ok(device_record)
+    }
+
+    fn find_latest_by_device_id(device_id: i64) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let device_record = device_records::table
@@ -51,7 +84,7 @@ impl DeviceRecord {
         Ok(device_record)
     }
 
-    pub fn count_by_device_id(device_id: i64) -> Result<i64, WebSocketError> {
+    fn count_by_device_id(device_id: i64) -> Result<i64, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let device_records = device_records::table
@@ -62,40 +95,354 @@ impl DeviceRecord {
         Ok(device_records)
     }
 
-    pub fn get_average_between_timestamp_by_device_id(
+    fn aggregate_between_timestamp_by_device_id(
+        device_id: i64,
+        range: (SystemTime, SystemTime),
+        bucket: DeviceRecordsBucket,
+        timezone: Tz,
+    ) -> Result<Vec<DeviceRecordsBucketAggregates>, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+        let sources = DeviceRecordsSources::new(range, bucket, timezone);
+
+        // Records are stored in UTC, so they are moved to the local time to be truncated
+        // and bucket bounds are moved back, which keeps days and months calendar ones.
+        // Rollups are samples that stand for many records, a record is a sample of itself
+        let buckets = diesel::sql_query(
+            "SELECT \
+                EXTRACT(EPOCH FROM bucket AT TIME ZONE $2)::BIGINT AS bucket_start, \
+                EXTRACT(EPOCH FROM (bucket + ('1 ' || $1)::INTERVAL) AT TIME ZONE $2)::BIGINT \
+                    AS bucket_end, \
+                sum(sum) / sum(count)::FLOAT AS average, \
+                min(minimum) AS minimum, \
+                max(maximum) AS maximum, \
+                sum(count)::BIGINT AS count, \
+                (array_agg(last ORDER BY last_created_at DESC))[1] AS last \
+            FROM ( \
+                SELECT sum, count, minimum, maximum, last, last_created_at, \
+                    date_trunc($1, created_at AT TIME ZONE 'UTC' AT TIME ZONE $2) AS bucket \
+                FROM ( \
+                    SELECT bucket AS created_at, sum, count, minimum, maximum, last, last_created_at \
+                    FROM device_records_daily \
+                    WHERE device_id = $3 AND bucket >= $4 AND bucket < $5 \
+                    UNION ALL \
+                    SELECT bucket, sum, count, minimum, maximum, last, last_created_at \
+                    FROM device_records_hourly \
+                    WHERE device_id = $3 AND bucket >= $6 AND bucket < $7 \
+                    UNION ALL \
+                    SELECT created_at, data, 1, data, data, data, created_at \
+                    FROM device_records \
+                    WHERE device_id = $3 AND created_at BETWEEN $8 AND $9 \
+                ) AS samples \
+            ) AS buckets \
+            GROUP BY bucket \
+            ORDER BY bucket"
+        )
+            .bind::<Text, _>(bucket.get_field())
+            .bind::<Text, _>(timezone.name())
+            .bind::<BigInt, _>(device_id)
+            .bind::<Timestamp, _>(sources.daily.0)
+            .bind::<Timestamp, _>(sources.daily.1)
+            .bind::<Timestamp, _>(sources.hourly.0)
+            .bind::<Timestamp, _>(sources.hourly.1)
+            .bind::<Timestamp, _>(sources.records.0)
+            .bind::<Timestamp, _>(sources.records.1)
+            .load(connection)?;
+
+        Ok(buckets)
+    }
+
+    // Records go in the reverse order of their time, the last record of a page is the cursor of the next
+    fn find_all_by_device_id_between(
         device_id: i64,
         range: (SystemTime, SystemTime),
-    ) -> Result<Option<f64>, WebSocketError> {
+        before: Option<(SystemTime, i64)>,
+        limit: i64,
+    ) -> Result<Vec<Self>, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
-        let data = device_records::table
-            .select(avg(device_records::data))
+        let mut query = device_records::table
             .filter(device_records::device_id.eq(device_id))
             .filter(device_records::created_at.between(range.0, range.1))
-            .get_result(connection)?;
+            .into_boxed();
+
+        // Records may be imported with a custom time, so their IDs don't follow their time
+        if let Some((created_at, id)) = before {
+            query = query.filter(
+                device_records::created_at.lt(created_at)
+                    .or(device_records::created_at.eq(created_at).and(device_records::id.lt(id)))
+            );
+        }
 
-        Ok(data)
+        let device_records = query
+            .order((device_records::created_at.desc(), device_records::id.desc()))
+            .limit(limit)
+            .load(connection)?;
+
+        Ok(device_records)
     }
 
     // Default implementations
-    pub fn check_data_size(data: &f64) -> Result<(), WebSocketError> {
-        match data {
-            size if size < &-100.0 => Err(
-                WebSocketErrorTemplate::DeviceRecordDataTooSmall(None).into()
+    fn check_limit(limit: &i64) -> Result<(), WebSocketError> {
+        match limit {
+            limit if limit < &1 => Err(
+                WebSocketErrorTemplate::DeviceRecordsLimitTooSmall(None).into()
+            ),
+            limit if limit > &DEVICE_RECORDS_MAXIMUM_LIMIT => Err(
+                WebSocketErrorTemplate::DeviceRecordsLimitTooBig(None).into()
             ),
-            size if size > &100.0 => Err(
-                WebSocketErrorTemplate::DeviceRecordDataTooBig(None).into()
+            _ => Ok(())
+        }
+    }
+
+    fn check_buckets(
+        range: &(SystemTime, SystemTime),
+        bucket: &DeviceRecordsBucket,
+    ) -> Result<(), WebSocketError> {
+        let Ok(duration) = range.1.duration_since(range.0) else {
+            return Err(WebSocketErrorTemplate::InvalidTimeRange(None).into())
+        };
+        let buckets = duration.as_secs() / bucket.get_minimum_duration().as_secs();
+
+        match buckets {
+            buckets if buckets >= DEVICE_RECORDS_MAXIMUM_BUCKETS => Err(
+                WebSocketErrorTemplate::DeviceRecordsBucketsTooMany(None).into()
             ),
             _ => Ok(())
         }
     }
 }
 
+pub const DEVICE_RECORDS_DEFAULT_LIMIT: i64 = 100;
+pub const DEVICE_RECORDS_MAXIMUM_LIMIT: i64 = 1000;
+pub const DEVICE_RECORDS_MAXIMUM_BUCKETS: u64 = 1000;
+
 pub struct NewDeviceRecord {
     pub device_id: i64,
     pub data: f64,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeviceRecordEntry {
+    pub(crate) id: i64,
+    pub(crate) data: f64,
+    pub(crate) created_at: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeviceRecordImportEntry {
+    pub(crate) data: f64,
+    pub(crate) time: u64,
+}
+
+impl From<DeviceRecord> for DeviceRecordEntry {
+    fn from(device_record: DeviceRecord) -> Self {
+        DeviceRecordEntry {
+            id: device_record.id,
+            data: device_record.data,
+            created_at: device_record.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceRecordsBucket {
+    Minute,
+    Hour,
+    Day,
+    // Weeks start on Monday
+    Week,
+    Month,
+    Year,
+}
+
+impl DeviceRecordsBucket {
+    // Field of `date_trunc` and unit of `INTERVAL`
+    pub fn get_field(&self) -> &'static str {
+        match self {
+            DeviceRecordsBucket::Minute => "minute",
+            DeviceRecordsBucket::Hour => "hour",
+            DeviceRecordsBucket::Day => "day",
+            DeviceRecordsBucket::Week => "week",
+            DeviceRecordsBucket::Month => "month",
+            DeviceRecordsBucket::Year => "year",
+        }
+    }
+
+    // The shortest a bucket can be, days get shorter with the daylight saving time
+    pub fn get_minimum_duration(&self) -> Duration {
+        match self {
+            DeviceRecordsBucket::Minute => Duration::from_secs(60),
+            DeviceRecordsBucket::Hour => Duration::from_secs(60 * 60),
+            DeviceRecordsBucket::Day => Duration::from_secs(23 * 60 * 60),
+            DeviceRecordsBucket::Week => Duration::from_secs((7 * 24 - 1) * 60 * 60),
+            DeviceRecordsBucket::Month => Duration::from_secs(28 * 24 * 60 * 60),
+            DeviceRecordsBucket::Year => Duration::from_secs(365 * 24 * 60 * 60),
+        }
+    }
+
+    // Bounds of every UTC bucket of the range, the same as `date_trunc` makes of records,
+    // so buckets without records can be told apart by their start
+    pub fn get_ranges_between(&self, range: (SystemTime, SystemTime)) -> Vec<(u64, u64)> {
+        let from: DateTime<Utc> = range.0.into();
+        let until: DateTime<Utc> = range.1.into();
+        let date = from.date_naive();
+        let midnight = |date: NaiveDate| Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN));
+        let mut start = match self {
+            DeviceRecordsBucket::Minute =>
+                from.duration_trunc(chrono::Duration::minutes(1)).unwrap_or(from),
+            DeviceRecordsBucket::Hour =>
+                from.duration_trunc(chrono::Duration::hours(1)).unwrap_or(from),
+            DeviceRecordsBucket::Day => midnight(date),
+            DeviceRecordsBucket::Week =>
+                midnight(date - Days::new(date.weekday().num_days_from_monday() as u64)),
+            DeviceRecordsBucket::Month => midnight(date.with_day(1).unwrap_or(date)),
+            DeviceRecordsBucket::Year => midnight(date.with_ordinal(1).unwrap_or(date)),
+        };
+        let mut ranges = vec![];
+
+        while start <= until {
+            let end = match self {
+                DeviceRecordsBucket::Minute => Some(start + chrono::Duration::minutes(1)),
+                DeviceRecordsBucket::Hour => Some(start + chrono::Duration::hours(1)),
+                DeviceRecordsBucket::Day => Some(start + chrono::Duration::days(1)),
+                DeviceRecordsBucket::Week => Some(start + chrono::Duration::weeks(1)),
+                DeviceRecordsBucket::Month => start.checked_add_months(Months::new(1)),
+                DeviceRecordsBucket::Year => start.checked_add_months(Months::new(12)),
+            };
+            let Some(end) = end else { break };
+
+            ranges.push((start.timestamp() as u64, end.timestamp() as u64));
+            start = end;
+        }
+
+        ranges
+    }
+}
+
+// Consecutive ranges that are aggregated from daily rollups, hourly rollups and records.
+// Rollups lag behind records and are made in UTC, so records are used for the last hours
+// and for timezones that are not whole hours away from UTC. Rolled up ranges are widened
+// to whole hours and days, which never splits a bucket as it is at least as long
+struct DeviceRecordsSources {
+    daily: (SystemTime, SystemTime),
+    hourly: (SystemTime, SystemTime),
+    records: (SystemTime, SystemTime),
+}
+
+impl DeviceRecordsSources {
+    fn new(range: (SystemTime, SystemTime), bucket: DeviceRecordsBucket, timezone: Tz) -> Self {
+        let from: DateTime<Utc> = range.0.into();
+        let until: DateTime<Utc> = range.1.into();
+        let hour = chrono::Duration::hours(1);
+        let day = chrono::Duration::days(1);
+        let offsets = [from, until].map(|time| {
+            timezone.offset_from_utc_datetime(&time.naive_utc()).fix().local_minus_utc()
+        });
+
+        let is_hourly = bucket != DeviceRecordsBucket::Minute
+            && offsets.iter().all(|offset| offset % 3600 == 0);
+        let is_daily = is_hourly
+            && bucket != DeviceRecordsBucket::Hour
+            && offsets.iter().all(|offset| *offset == 0);
+
+        if !is_hourly {
+            return DeviceRecordsSources {
+                daily: (range.0, range.0),
+                hourly: (range.0, range.0),
+                records: range,
+            };
+        }
+
+        let truncate = |time: DateTime<Utc>, duration| time.duration_trunc(duration).unwrap_or(time);
+        let rolled_up_until = (truncate(Utc::now(), hour) - hour).min(until);
+        let hourly_from = truncate(from, hour);
+        let hourly_until = truncate(rolled_up_until, hour).max(hourly_from);
+        let daily = match is_daily {
+            true => {
+                let daily_from = truncate(from, day);
+
+                (daily_from, truncate(hourly_until, day).max(daily_from))
+            },
+            false => (hourly_from, hourly_from),
+        };
+
+        DeviceRecordsSources {
+            daily: (daily.0.into(), daily.1.into()),
+            hourly: (daily.1.max(hourly_from).into(), hourly_until.into()),
+            records: (hourly_until.max(from).into(), range.1),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceRecordsAggregate {
+    Avg,
+    Min,
+    Max,
+    Count,
+    Last,
+}
+
+#[derive(Clone, Debug, QueryableByName)]
+pub struct DeviceRecordsBucketAggregates {
+    #[diesel(sql_type = BigInt)]
+    pub bucket_start: i64,
+    #[diesel(sql_type = BigInt)]
+    pub bucket_end: i64,
+    #[diesel(sql_type = Double)]
+    pub average: f64,
+    #[diesel(sql_type = Double)]
+    pub minimum: f64,
+    #[diesel(sql_type = Double)]
+    pub maximum: f64,
+    #[diesel(sql_type = BigInt)]
+    pub count: i64,
+    #[diesel(sql_type = Double)]
+    pub last: f64,
+}
+
+// Only the requested aggregates are sent
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DeviceRecordsBucketEntry {
+    pub(crate) range: (u64, u64),
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) avg: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last: Option<f64>,
+}
+
+impl DeviceRecordsBucketEntry {
+    pub fn new(
+        bucket: DeviceRecordsBucketAggregates,
+        aggregates: &[DeviceRecordsAggregate],
+    ) -> Self {
+        let mut entry = DeviceRecordsBucketEntry {
+            range: (bucket.bucket_start as u64, bucket.bucket_end as u64),
+            ..Default::default()
+        };
+
+        for aggregate in aggregates {
+            match aggregate {
+                DeviceRecordsAggregate::Avg => entry.avg = Some(bucket.average),
+                DeviceRecordsAggregate::Min => entry.min = Some(bucket.minimum),
+                DeviceRecordsAggregate::Max => entry.max = Some(bucket.maximum),
+                DeviceRecordsAggregate::Count => entry.count = Some(bucket.count),
+                DeviceRecordsAggregate::Last => entry.last = Some(bucket.last),
+            }
+        }
+
+        entry
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeviceRecordsAverage {
     pub(crate) data: Option<f64>,
@@ -113,3 +460,28 @@ pub enum DeviceRecordsTimestampRange {
     MonthBeforeLast = 4,
     LastThreeMoths = 5,
 }
+
+// Presets of the web client, they are aggregated in UTC
+impl DeviceRecordsTimestampRange {
+    pub fn get_range_and_bucket(&self) -> ((SystemTime, SystemTime), DeviceRecordsBucket) {
+        let now: DateTime<Utc> = SystemTime::now().into();
+        let months_ago = |months| now.checked_sub_months(Months::new(months)).unwrap_or(now);
+
+        let (from, until, bucket) = match self {
+            DeviceRecordsTimestampRange::Today =>
+                (now - chrono::Duration::days(1), now, DeviceRecordsBucket::Hour),
+            DeviceRecordsTimestampRange::Week =>
+                (now - chrono::Duration::weeks(1), now, DeviceRecordsBucket::Day),
+            DeviceRecordsTimestampRange::Month =>
+                (months_ago(1), now, DeviceRecordsBucket::Week),
+            DeviceRecordsTimestampRange::LastMonth =>
+                (months_ago(2), months_ago(1), DeviceRecordsBucket::Week),
+            DeviceRecordsTimestampRange::MonthBeforeLast =>
+                (months_ago(3), months_ago(2), DeviceRecordsBucket::Week),
+            DeviceRecordsTimestampRange::LastThreeMoths =>
+                (months_ago(3), now, DeviceRecordsBucket::Month),
+        };
+
+        ((from.into(), until.into()), bucket)
+    }
+}
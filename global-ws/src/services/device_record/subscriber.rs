@@ -3,8 +3,8 @@ use actix_web_actors::ws::WebsocketContext;
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
 use crate::messages::{DispatchEvent, DispatchMessage, WebSocketMessage, WebSocketMessageData};
 use crate::server::{Socket, WebSocketConnection};
-use crate::services::device::Device;
-use crate::services::greenhouse::Greenhouse;
+use crate::services::device::{Device, DeviceModel};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
 use crate::services::session::Session;
 
 fn device_records_update(
@@ -1,10 +1,13 @@
 use actix_web_actors::ws::WebsocketContext;
+use amqp::routes::{GreenhouseCreated, GreenhouseDeleted, GreenhouseUpdated, UserMeUpdated, UserUpdated};
+use amqp::RoutedMessage;
 
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
-use crate::messages::{DispatchEvent, DispatchMessage, Method, Opcode, WebSocketMessage, WebSocketMessageData};
+use crate::messages::{DispatchGreenhouse, DispatchGreenhouseCreate, DispatchGreenhouseDelete, DispatchUser, DispatchUserMe, Method, Opcode, WebSocketMessage, WebSocketMessageData};
 use crate::server::{Socket, WebSocketConnection};
+use crate::services::device::DeviceKind;
 use crate::services::device_record::DeviceRecord;
-use crate::services::greenhouse::{Greenhouse, NewGreenhouse};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel, NewGreenhouse};
 use crate::services::session::Session;
 use crate::services::user::User;
 
@@ -59,33 +62,18 @@ fn create_greenhouse(
 
     let responses = vec![
         // Notify all owner sessions
-        DispatchMessage {
-            event: DispatchEvent::GreenhouseCreate {
-                id: Some(greenhouse.id),
-                owner_id: session_user_id,
-            },
-            new_subscribers: None,
-        },
+        RoutedMessage::new::<GreenhouseCreated>(
+            DispatchGreenhouseCreate { id: greenhouse.id, owner_id: session_user_id },
+            None,
+        ),
         // Notify all those who are subscribed to this user
-        DispatchMessage {
-            event: DispatchEvent::UserUpdate { id: session_user_id },
-            new_subscribers: None,
-        },
+        RoutedMessage::new::<UserUpdated>(DispatchUser { id: session_user_id }, None),
         // Notify all user sessions that are subscribed to themselves
-        DispatchMessage {
-            event: DispatchEvent::UserMeUpdate { id: session_user_id },
-            new_subscribers: None,
-        },
+        RoutedMessage::new::<UserMeUpdated>(DispatchUserMe { id: session_user_id }, None),
     ];
 
     for response in responses {
-        Socket::send_message(
-            message.id,
-            response,
-            connection.socket.downgrade().recipient(),
-            connection,
-            context,
-        )?;
+        Socket::publish_dispatch(response, connection, context);
     }
 
     Ok(())
@@ -102,6 +90,8 @@ fn patch_greenhouse(
         token: new_token,
         maximum_average_humidity: new_maximum_average_humidity,
         minimum_average_temperature: new_minimum_average_temperature,
+        gateway_url: new_gateway_url,
+        gateway_auth_scheme: new_gateway_auth_scheme,
     } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
 
     let session = Session::find(connection.session_id.unwrap())?;
@@ -109,6 +99,13 @@ fn patch_greenhouse(
         = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
     let greenhouse
         = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let new_gateway_url = match new_gateway_url {
+        Some(url) if url.is_empty() => None,
+        Some(url) => Some(url.trim_end_matches('/').to_string()),
+        None => greenhouse.gateway_url.to_owned(),
+    };
+    let new_gateway_auth_scheme
+        = new_gateway_auth_scheme.unwrap_or(greenhouse.gateway_auth_scheme);
 
     if new_token != greenhouse.token {
         match Greenhouse::find_by_token(new_token.to_owned()) {
@@ -120,11 +117,23 @@ fn patch_greenhouse(
     if new_name != greenhouse.name
         || new_token != greenhouse.token
         || new_maximum_average_humidity != greenhouse.maximum_average_humidity
-        || new_minimum_average_temperature != greenhouse.minimum_average_temperature {
+        || new_minimum_average_temperature != greenhouse.minimum_average_temperature
+        || new_gateway_url != greenhouse.gateway_url
+        || new_gateway_auth_scheme != greenhouse.gateway_auth_scheme {
         Greenhouse::check_name_length(&new_name)?;
         Greenhouse::check_token_length(&new_token)?;
-        DeviceRecord::check_data_size(&new_maximum_average_humidity.unwrap_or(0.0))?;
-        DeviceRecord::check_data_size(&new_minimum_average_temperature.unwrap_or(0.0))?;
+        DeviceRecord::check_data_size(
+            &DeviceKind::HumiditySensor,
+            &new_maximum_average_humidity.unwrap_or(0.0),
+        )?;
+        DeviceRecord::check_data_size(
+            &DeviceKind::TemperatureSensor,
+            &new_minimum_average_temperature.unwrap_or(0.0),
+        )?;
+
+        if let Some(new_gateway_url) = &new_gateway_url {
+            Greenhouse::check_gateway_url(new_gateway_url)?;
+        }
 
         let greenhouse = Greenhouse::update(
             greenhouse.id,
@@ -132,20 +141,15 @@ fn patch_greenhouse(
             new_token,
             new_maximum_average_humidity,
             new_minimum_average_temperature,
+            new_gateway_url,
+            new_gateway_auth_scheme,
         )?;
 
-        let response = DispatchMessage {
-            event: DispatchEvent::GreenhouseUpdate { id: greenhouse.id },
-            new_subscribers: None,
-        };
-
-        Socket::send_message(
-            message.id,
-            response,
-            connection.socket.downgrade().recipient(),
+        Socket::publish_dispatch(
+            RoutedMessage::new::<GreenhouseUpdated>(DispatchGreenhouse { id: greenhouse.id }, None),
             connection,
             context,
-        )?;
+        );
     }
 
     // Response to request
@@ -218,33 +222,18 @@ fn delete_greenhouse(
 
     let responses = vec![
         // Notify all owner sessions
-        DispatchMessage {
-            event: DispatchEvent::GreenhouseDelete {
-                id: Some(greenhouse.id),
-                owner_id: session_user_id,
-            },
-            new_subscribers: None,
-        },
+        RoutedMessage::new::<GreenhouseDeleted>(
+            DispatchGreenhouseDelete { id: greenhouse.id, owner_id: session_user_id },
+            None,
+        ),
         // Notify all those who are subscribed to this user
-        DispatchMessage {
-            event: DispatchEvent::UserUpdate { id: session_user_id },
-            new_subscribers: None,
-        },
+        RoutedMessage::new::<UserUpdated>(DispatchUser { id: session_user_id }, None),
         // Notify all user sessions that are subscribed to themselves
-        DispatchMessage {
-            event: DispatchEvent::UserMeUpdate { id: session_user_id },
-            new_subscribers: None,
-        },
+        RoutedMessage::new::<UserMeUpdated>(DispatchUserMe { id: session_user_id }, None),
     ];
 
     for response in responses {
-        Socket::send_message(
-            message.id,
-            response,
-            connection.socket.downgrade().recipient(),
-            connection,
-            context,
-        )?;
+        Socket::publish_dispatch(response, connection, context);
     }
 
     Ok(())
@@ -1,27 +1,39 @@
+pub use domain::greenhouse::{Greenhouse, GreenhouseDriver, GreenhouseGatewayAuthScheme};
+
 use std::time::SystemTime;
 
 use db::schema::greenhouses;
-use diesel::{ExpressionMethods, Insertable, Queryable, RunQueryDsl};
+use diesel::{ExpressionMethods, RunQueryDsl};
 use diesel::prelude::*;
-use serde::{Deserialize, Serialize};
 
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
-
-#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
-#[diesel(table_name = greenhouses)]
-pub struct Greenhouse {
-    pub id: i64,
-    pub name: String,
-    pub token: String,
-    pub owner_id: i64,
-    pub created_at: SystemTime,
-    pub maximum_average_humidity: Option<f64>,
-    pub minimum_average_temperature: Option<f64>,
+use crate::utils::dns;
+
+pub trait GreenhouseModel: Sized {
+    fn create(greenhouse: NewGreenhouse) -> Result<Self, WebSocketError>;
+    fn find(id: i64) -> Result<Self, WebSocketError>;
+    fn find_by_id_and_owner_id(id: i64, owner_id: i64) -> Result<Self, WebSocketError>;
+    fn find_by_token(token: String) -> Result<Self, WebSocketError>;
+    fn find_all_by_owner_id(owner_id: i64) -> Result<Vec<Self>, WebSocketError>;
+    fn count_by_owner_id(owner_id: i64) -> Result<i64, WebSocketError>;
+    fn update(
+        id: i64,
+        new_name: String,
+        new_token: String,
+        new_maximum_average_humidity: Option<f64>,
+        new_minimum_average_temperature: Option<f64>,
+        new_gateway_url: Option<String>,
+        new_gateway_auth_scheme: GreenhouseGatewayAuthScheme,
+    ) -> Result<Self, WebSocketError>;
+    fn delete(id: i64) -> Result<usize, WebSocketError>;
+    fn check_name_length(name: &str) -> Result<(), WebSocketError>;
+    fn check_token_length(token: &str) -> Result<(), WebSocketError>;
+    fn check_gateway_url(url: &str) -> Result<(), WebSocketError>;
 }
 
-impl Greenhouse {
+impl GreenhouseModel for Greenhouse {
     // CRUD
-    pub fn create(greenhouse: NewGreenhouse) -> Result<Self, WebSocketError> {
+    fn create(greenhouse: NewGreenhouse) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let greenhouse = Greenhouse {
@@ -32,6 +44,9 @@ impl Greenhouse {
             created_at: SystemTime::now(),
             maximum_average_humidity: Some(80.0),
             minimum_average_temperature: Some(21.0),
+            driver: GreenhouseDriver::default(),
+            gateway_url: None,
+            gateway_auth_scheme: GreenhouseGatewayAuthScheme::default(),
         };
 
         let session = diesel::insert_into(greenhouses::table)
@@ -41,7 +56,7 @@ impl Greenhouse {
         Ok(session)
     }
 
-    pub fn find(id: i64) -> Result<Self, WebSocketError> {
+    fn find(id: i64) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let greenhouse = greenhouses::table
@@ -51,7 +66,7 @@ impl Greenhouse {
         Ok(greenhouse)
     }
 
-    pub fn find_by_id_and_owner_id(id: i64, owner_id: i64) -> Result<Self, WebSocketError> {
+    fn find_by_id_and_owner_id(id: i64, owner_id: i64) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let greenhouse = greenhouses::table
@@ -62,7 +77,7 @@ impl Greenhouse {
         Ok(greenhouse)
     }
 
-    pub fn find_by_token(token: String) -> Result<Self, WebSocketError> {
+    fn find_by_token(token: String) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let greenhouse = greenhouses::table
@@ -72,7 +87,7 @@ impl Greenhouse {
         Ok(greenhouse)
     }
 
-    pub fn find_all_by_owner_id(owner_id: i64) -> Result<Vec<Self>, WebSocketError> {
+    fn find_all_by_owner_id(owner_id: i64) -> Result<Vec<Self>, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let greenhouses = greenhouses::table
@@ -82,7 +97,7 @@ impl Greenhouse {
         Ok(greenhouses)
     }
 
-    pub fn count_by_owner_id(owner_id: i64) -> Result<i64, WebSocketError> {
+    fn count_by_owner_id(owner_id: i64) -> Result<i64, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let greenhouses = greenhouses::table
@@ -93,12 +108,14 @@ impl Greenhouse {
         Ok(greenhouses)
     }
 
-    pub fn update(
+    fn update(
         id: i64,
         new_name: String,
         new_token: String,
         new_maximum_average_humidity: Option<f64>,
         new_minimum_average_temperature: Option<f64>,
+        new_gateway_url: Option<String>,
+        new_gateway_auth_scheme: GreenhouseGatewayAuthScheme,
     ) -> Result<Self, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
@@ -109,13 +126,15 @@ impl Greenhouse {
                 greenhouses::token.eq(new_token),
                 greenhouses::maximum_average_humidity.eq(new_maximum_average_humidity),
                 greenhouses::minimum_average_temperature.eq(new_minimum_average_temperature),
+                greenhouses::gateway_url.eq(new_gateway_url),
+                greenhouses::gateway_auth_scheme.eq(new_gateway_auth_scheme),
             ))
             .get_result(connection)?;
 
         Ok(greenhouse)
     }
 
-    pub fn delete(id: i64) -> Result<usize, WebSocketError> {
+    fn delete(id: i64) -> Result<usize, WebSocketError> {
         let connection = &mut db::get_connection()?;
 
         let result = diesel::delete(
@@ -126,7 +145,7 @@ impl Greenhouse {
     }
 
     // Default implementations
-    pub fn check_name_length(name: &str) -> Result<(), WebSocketError> {
+    fn check_name_length(name: &str) -> Result<(), WebSocketError> {
         let name_length = name.chars().count();
 
         match name_length {
@@ -136,7 +155,7 @@ impl Greenhouse {
         }
     }
 
-    pub fn check_token_length(token: &str) -> Result<(), WebSocketError> {
+    fn check_token_length(token: &str) -> Result<(), WebSocketError> {
         let token_length = token.chars().count();
 
         match token_length {
@@ -145,6 +164,18 @@ impl Greenhouse {
             _ => Ok(())
         }
     }
+
+    fn check_gateway_url(url: &str) -> Result<(), WebSocketError> {
+        if url.chars().count() > 256 {
+            return Err(WebSocketErrorTemplate::GreenhouseGatewayUrlTooLong(None).into());
+        }
+
+        // The worker sends requests to the gateway, so it must not be pointed at internal services
+        match dns::is_public_http_url(url) {
+            true => Ok(()),
+            false => Err(WebSocketErrorTemplate::GreenhouseGatewayUrlInvalid(None).into()),
+        }
+    }
 }
 
 pub struct NewGreenhouse {
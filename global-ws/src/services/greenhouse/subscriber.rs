@@ -3,7 +3,7 @@ use actix_web_actors::ws::WebsocketContext;
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
 use crate::messages::{DispatchEvent, DispatchMessage, WebSocketMessage, WebSocketMessageData};
 use crate::server::{Socket, WebSocketConnection};
-use crate::services::greenhouse::Greenhouse;
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
 use crate::services::session::Session;
 
 fn greenhouse_update(
@@ -1,5 +1,9 @@
+pub(crate) mod alert;
+pub(crate) mod command;
 pub(crate) mod device;
 pub(crate) mod device_record;
 pub(crate) mod greenhouse;
+pub(crate) mod rule;
+pub(crate) mod schedule;
 pub(crate) mod session;
 pub(crate) mod user;
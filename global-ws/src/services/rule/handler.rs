@@ -0,0 +1,197 @@
+use actix_web_actors::ws::WebsocketContext;
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+use crate::messages::{Method, Opcode, WebSocketMessage, WebSocketMessageData};
+use crate::server::{Socket, WebSocketConnection};
+use crate::services::device::{Device, DeviceModel};
+use crate::services::device_record::DeviceRecord;
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::rule::{NewRule, Rule, RuleModel, RULES_MAXIMUM_PER_GREENHOUSE};
+use crate::services::session::Session;
+
+fn get_rules(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestGetGreenhouseRules { greenhouse_id }
+        = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let rules = Rule::find_all_by_greenhouse_id(greenhouse.id)?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::ResponseGreenhouseRules {
+            greenhouse_id: greenhouse.id,
+            rules: rules.into_iter().map(Into::into).collect(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    Ok(())
+}
+
+fn create_rule(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestPostGreenhouseRule {
+        greenhouse_id,
+        sensor_id,
+        condition,
+        threshold,
+        hysteresis,
+        duration,
+        controller_id,
+        state,
+        action_duration,
+    } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    Rule::check_hysteresis(&hysteresis)?;
+    Rule::check_duration(&duration)?;
+
+    if let Some(action_duration) = &action_duration {
+        Device::check_state_duration(action_duration)?;
+    }
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let sensor = Device::find_by_id_and_greenhouse_id(sensor_id, greenhouse.id)?;
+    let controller = Device::find_by_id_and_greenhouse_id(controller_id, greenhouse.id)?;
+
+    // The threshold has to be a value the sensor can report
+    DeviceRecord::check_data_size(&sensor.kind, &threshold)?;
+    Device::check_state(&controller.kind, &state)?;
+
+    if Rule::count_by_greenhouse_id(greenhouse.id)? >= RULES_MAXIMUM_PER_GREENHOUSE {
+        return Err(WebSocketErrorTemplate::RulesTooMany(None).into());
+    }
+
+    // Both durations are checked to be at most a day
+    let duration = i32::try_from(duration)
+        .map_err(|_| WebSocketErrorTemplate::InvalidRuleDuration(None))?;
+    let action_duration = action_duration
+        .map(i32::try_from)
+        .transpose()
+        .map_err(|_| WebSocketErrorTemplate::DeviceStateDurationTooLong(None))?;
+
+    Rule::create(NewRule {
+        greenhouse_id: greenhouse.id,
+        sensor_id: sensor.id,
+        condition,
+        threshold,
+        hysteresis,
+        duration,
+        controller_id: controller.id,
+        state: i16::from(state),
+        action_duration,
+    })?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::Response {
+            code: 200,
+            message: "Successfully created".to_string(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    Ok(())
+}
+
+fn delete_rule(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestDeleteGreenhouseRule { id: rule_id, greenhouse_id }
+        = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let rule = Rule::find_by_id_and_greenhouse_id(rule_id, greenhouse.id)?;
+
+    Rule::delete(rule.id)?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::Response {
+            code: 200,
+            message: "Successfully deleted".to_string(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    Ok(())
+}
+
+pub fn handle(
+    request: String,
+    method: Method,
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    match method {
+        Method::Get => match request.as_str() {
+            "greenhouse/rules" => get_rules(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        Method::Post => match request.as_str() {
+            "greenhouse/rule" => create_rule(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        Method::Delete => match request.as_str() {
+            "greenhouse/rule" => delete_rule(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        _ => return Err(WebSocketErrorTemplate::MethodNotAllowed(None).into()),
+    }
+
+    Ok(())
+}
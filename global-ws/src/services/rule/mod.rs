@@ -0,0 +1,5 @@
+pub use handler::handle;
+pub use model::*;
+
+mod handler;
+mod model;
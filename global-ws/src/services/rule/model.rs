@@ -0,0 +1,154 @@
+pub use domain::rule::{Rule, RuleCondition};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use db::schema::rules;
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+
+pub const RULES_MAXIMUM_PER_GREENHOUSE: i64 = 50;
+
+pub trait RuleModel: Sized {
+    fn create(rule: NewRule) -> Result<Self, WebSocketError>;
+    fn find_by_id_and_greenhouse_id(id: i64, greenhouse_id: i64) -> Result<Self, WebSocketError>;
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError>;
+    fn count_by_greenhouse_id(greenhouse_id: i64) -> Result<i64, WebSocketError>;
+    fn delete(id: i64) -> Result<usize, WebSocketError>;
+    fn check_duration(duration: &u32) -> Result<(), WebSocketError>;
+    fn check_hysteresis(hysteresis: &f64) -> Result<(), WebSocketError>;
+}
+
+impl RuleModel for Rule {
+    // CRUD
+    fn create(rule: NewRule) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let rule = Rule {
+            id: snowflake::generate(),
+            greenhouse_id: rule.greenhouse_id,
+            sensor_id: rule.sensor_id,
+            condition: rule.condition,
+            threshold: rule.threshold,
+            hysteresis: rule.hysteresis,
+            duration: rule.duration,
+            controller_id: rule.controller_id,
+            state: rule.state,
+            action_duration: rule.action_duration,
+            created_at: SystemTime::now(),
+        };
+
+        let rule = diesel::insert_into(rules::table)
+            .values(&rule)
+            .get_result(connection)?;
+
+        Ok(rule)
+    }
+
+    fn find_by_id_and_greenhouse_id(id: i64, greenhouse_id: i64) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let rule = rules::table
+            .filter(rules::id.eq(id))
+            .filter(rules::greenhouse_id.eq(greenhouse_id))
+            .first(connection)?;
+
+        Ok(rule)
+    }
+
+    fn find_all_by_greenhouse_id(greenhouse_id: i64) -> Result<Vec<Self>, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let rules = rules::table
+            .filter(rules::greenhouse_id.eq(greenhouse_id))
+            .order(rules::id)
+            .load(connection)?;
+
+        Ok(rules)
+    }
+
+    fn count_by_greenhouse_id(greenhouse_id: i64) -> Result<i64, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let rules = rules::table
+            .filter(rules::greenhouse_id.eq(greenhouse_id))
+            .count()
+            .get_result(connection)?;
+
+        Ok(rules)
+    }
+
+    fn delete(id: i64) -> Result<usize, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let result = diesel::delete(
+            rules::table.filter(rules::id.eq(id))
+        ).execute(connection)?;
+
+        Ok(result)
+    }
+
+    // Default implementations
+    fn check_duration(duration: &u32) -> Result<(), WebSocketError> {
+        match duration {
+            duration if *duration > 86400 => Err(
+                WebSocketErrorTemplate::InvalidRuleDuration(None).into()
+            ),
+            _ => Ok(())
+        }
+    }
+
+    fn check_hysteresis(hysteresis: &f64) -> Result<(), WebSocketError> {
+        match hysteresis {
+            hysteresis if *hysteresis < 0.0 => Err(
+                WebSocketErrorTemplate::InvalidRuleHysteresis(None).into()
+            ),
+            _ => Ok(())
+        }
+    }
+}
+
+pub struct NewRule {
+    pub greenhouse_id: i64,
+    pub sensor_id: i64,
+    pub condition: RuleCondition,
+    pub threshold: f64,
+    pub hysteresis: f64,
+    pub duration: i32,
+    pub controller_id: i64,
+    pub state: i16,
+    pub action_duration: Option<i32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RuleEntry {
+    pub(crate) id: i64,
+    pub(crate) sensor_id: i64,
+    pub(crate) condition: RuleCondition,
+    pub(crate) threshold: f64,
+    pub(crate) hysteresis: f64,
+    pub(crate) duration: i32,
+    pub(crate) controller_id: i64,
+    pub(crate) state: i16,
+    pub(crate) action_duration: Option<i32>,
+    pub(crate) created_at: u64,
+}
+
+impl From<Rule> for RuleEntry {
+    fn from(rule: Rule) -> Self {
+        RuleEntry {
+            id: rule.id,
+            sensor_id: rule.sensor_id,
+            condition: rule.condition,
+            threshold: rule.threshold,
+            hysteresis: rule.hysteresis,
+            duration: rule.duration,
+            controller_id: rule.controller_id,
+            state: rule.state,
+            action_duration: rule.action_duration,
+            created_at: rule.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        }
+    }
+}
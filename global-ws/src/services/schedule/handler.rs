@@ -0,0 +1,184 @@
+use actix_web_actors::ws::WebsocketContext;
+use amqp::routes::{DeviceScheduleCreated, DeviceScheduleDeleted};
+use amqp::RoutedMessage;
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+use crate::messages::{DispatchScheduleCreate, DispatchScheduleDelete, Method, Opcode, WebSocketMessage, WebSocketMessageData};
+use crate::server::{Socket, WebSocketConnection};
+use crate::services::device::{Device, DeviceModel};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::schedule::{NewSchedule, Schedule, ScheduleKind, ScheduleModel};
+use crate::services::session::Session;
+
+fn create_schedule(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestPostDeviceSchedule {
+        device_id,
+        greenhouse_id,
+        kind,
+        state,
+        start_time,
+        end_time,
+        period,
+        duration,
+    } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    // Only the fields of the schedule kind are stored
+    let schedule = match (kind, start_time, end_time, period, duration) {
+        (ScheduleKind::Daily, Some(start_time), Some(end_time), _, _) => {
+            Schedule::check_daily_time(&start_time, &end_time)?;
+
+            NewSchedule {
+                greenhouse_id,
+                device_id,
+                kind,
+                state: i16::from(state),
+                start_time: Some(start_time),
+                end_time: Some(end_time),
+                period: None,
+                duration: None,
+            }
+        },
+        (ScheduleKind::Periodic, _, _, Some(period), Some(duration)) => {
+            Schedule::check_period(&period, &duration)?;
+
+            NewSchedule {
+                greenhouse_id,
+                device_id,
+                kind,
+                state: i16::from(state),
+                start_time: None,
+                end_time: None,
+                period: Some(period),
+                duration: Some(duration),
+            }
+        },
+        _ => return Err(WebSocketErrorTemplate::BadRequest(None).into()),
+    };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
+
+    Device::check_state(&device.kind, &state)?;
+
+    if Schedule::count_by_device_id(device.id)? >= 10 {
+        return Err(WebSocketErrorTemplate::SchedulesTooMany(None).into());
+    }
+
+    let schedule = Schedule::create(schedule)?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::Response {
+            code: 200,
+            message: "Successfully created".to_string(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    // Notify all those who are subscribed to schedules of this device
+    Socket::publish_dispatch(
+        RoutedMessage::new::<DeviceScheduleCreated>(
+            DispatchScheduleCreate { id: schedule.id, device_id: device.id },
+            None,
+        ),
+        connection,
+        context,
+    );
+
+    Ok(())
+}
+
+fn delete_schedule(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::RequestDeleteDeviceSchedule {
+        id: schedule_id,
+        device_id,
+        greenhouse_id,
+    } = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
+    let schedule = Schedule::find_by_id_and_device_id(schedule_id, device.id)?;
+
+    Schedule::delete(schedule.id)?;
+
+    // Response to request
+    let response = WebSocketMessage {
+        id: message.id,
+        connection_id: connection.id,
+        opcode: Opcode::Response,
+        data: WebSocketMessageData::Response {
+            code: 200,
+            message: "Successfully deleted".to_string(),
+        },
+        ..Default::default()
+    };
+
+    Socket::send_message(
+        message.id,
+        response,
+        connection.socket.downgrade().recipient(),
+        connection,
+        context,
+    )?;
+
+    // Notify all those who are subscribed to schedules of this device
+    Socket::publish_dispatch(
+        RoutedMessage::new::<DeviceScheduleDeleted>(
+            DispatchScheduleDelete { id: schedule.id, device_id: device.id },
+            None,
+        ),
+        connection,
+        context,
+    );
+
+    Ok(())
+}
+
+pub fn handle(
+    request: String,
+    method: Method,
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    match method {
+        Method::Post => match request.as_str() {
+            "device/schedule" => create_schedule(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        Method::Delete => match request.as_str() {
+            "device/schedule" => delete_schedule(message, connection, context)?,
+            _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+        },
+        _ => return Err(WebSocketErrorTemplate::MethodNotAllowed(None).into()),
+    }
+
+    Ok(())
+}
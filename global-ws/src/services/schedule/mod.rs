@@ -0,0 +1,7 @@
+pub use handler::handle;
+pub use model::*;
+pub use subscriber::subscribe;
+
+mod handler;
+mod model;
+mod subscriber;
@@ -0,0 +1,132 @@
+pub use domain::schedule::{Schedule, ScheduleKind};
+
+use std::time::SystemTime;
+
+use db::schema::schedules;
+use diesel::{ExpressionMethods, RunQueryDsl};
+use diesel::prelude::*;
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+
+pub trait ScheduleModel: Sized {
+    fn create(schedule: NewSchedule) -> Result<Self, WebSocketError>;
+    fn find(id: i64) -> Result<Self, WebSocketError>;
+    fn find_by_id_and_device_id(id: i64, device_id: i64) -> Result<Self, WebSocketError>;
+    fn find_all_by_device_id(device_id: i64) -> Result<Vec<Self>, WebSocketError>;
+    fn count_by_device_id(device_id: i64) -> Result<i64, WebSocketError>;
+    fn delete(id: i64) -> Result<usize, WebSocketError>;
+    fn check_daily_time(start_time: &i16, end_time: &i16) -> Result<(), WebSocketError>;
+    fn check_period(period: &i32, duration: &i32) -> Result<(), WebSocketError>;
+}
+
+impl ScheduleModel for Schedule {
+    // CRUD
+    fn create(schedule: NewSchedule) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let schedule = Schedule {
+            id: snowflake::generate(),
+            greenhouse_id: schedule.greenhouse_id,
+            device_id: schedule.device_id,
+            kind: schedule.kind,
+            state: schedule.state,
+            start_time: schedule.start_time,
+            end_time: schedule.end_time,
+            period: schedule.period,
+            duration: schedule.duration,
+            created_at: SystemTime::now(),
+        };
+
+        let schedule = diesel::insert_into(schedules::table)
+            .values(&schedule)
+            .get_result(connection)?;
+
+        Ok(schedule)
+    }
+
+    fn find(id: i64) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let schedule = schedules::table
+            .filter(schedules::id.eq(id))
+            .first(connection)?;
+
+        Ok(schedule)
+    }
+
+    fn find_by_id_and_device_id(id: i64, device_id: i64) -> Result<Self, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let schedule = schedules::table
+            .filter(schedules::id.eq(id))
+            .filter(schedules::device_id.eq(device_id))
+            .first(connection)?;
+
+        Ok(schedule)
+    }
+
+    fn find_all_by_device_id(device_id: i64) -> Result<Vec<Self>, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let schedules = schedules::table
+            .filter(schedules::device_id.eq(device_id))
+            .load(connection)?;
+
+        Ok(schedules)
+    }
+
+    fn count_by_device_id(device_id: i64) -> Result<i64, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let schedules = schedules::table
+            .filter(schedules::device_id.eq(device_id))
+            .count()
+            .get_result(connection)?;
+
+        Ok(schedules)
+    }
+
+    fn delete(id: i64) -> Result<usize, WebSocketError> {
+        let connection = &mut db::get_connection()?;
+
+        let result = diesel::delete(
+            schedules::table.filter(schedules::id.eq(id))
+        ).execute(connection)?;
+
+        Ok(result)
+    }
+
+    // Default implementations
+    fn check_daily_time(start_time: &i16, end_time: &i16) -> Result<(), WebSocketError> {
+        let minutes = 0..1440;
+
+        match minutes.contains(start_time)
+            && minutes.contains(end_time)
+            && start_time != end_time {
+            true => Ok(()),
+            false => Err(WebSocketErrorTemplate::InvalidScheduleTime(None).into()),
+        }
+    }
+
+    fn check_period(period: &i32, duration: &i32) -> Result<(), WebSocketError> {
+        match period {
+            period if *period < 60 => Err(WebSocketErrorTemplate::SchedulePeriodTooShort(None).into()),
+            period if *period > 604800 => Err(WebSocketErrorTemplate::SchedulePeriodTooLong(None).into()),
+            period if *duration < 1 || duration >= period => Err(
+                WebSocketErrorTemplate::InvalidScheduleDuration(None).into()
+            ),
+            _ => Ok(())
+        }
+    }
+}
+
+pub struct NewSchedule {
+    pub greenhouse_id: i64,
+    pub device_id: i64,
+    pub kind: ScheduleKind,
+    pub state: i16,
+    pub start_time: Option<i16>,
+    pub end_time: Option<i16>,
+    pub period: Option<i32>,
+    pub duration: Option<i32>,
+}
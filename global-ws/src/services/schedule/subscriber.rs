@@ -0,0 +1,66 @@
+use actix_web_actors::ws::WebsocketContext;
+
+use crate::error::{WebSocketError, WebSocketErrorTemplate};
+use crate::messages::{DispatchEvent, DispatchMessage, WebSocketMessage, WebSocketMessageData};
+use crate::server::{Socket, WebSocketConnection};
+use crate::services::device::{Device, DeviceModel};
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
+use crate::services::schedule::{Schedule, ScheduleModel};
+use crate::services::session::Session;
+
+fn device_schedules_update(
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    let WebSocketMessageData::SubscribeToDeviceSchedulesUpdate { device_id, greenhouse_id }
+        = message.data else { return Err(WebSocketErrorTemplate::BadRequest(None).into()) };
+
+    let session = Session::find(connection.session_id.unwrap())?;
+    let Some(session_user_id)
+        = session.user_id else { return Err(WebSocketErrorTemplate::Unauthorized(None).into()) };
+    let greenhouse
+        = Greenhouse::find_by_id_and_owner_id(greenhouse_id, session_user_id)?;
+    let device = Device::find_by_id_and_greenhouse_id(device_id, greenhouse.id)?;
+
+    // Existing schedules are listed as if they have just been created
+    let mut events: Vec<DispatchEvent> = Schedule::find_all_by_device_id(device.id)?
+        .into_iter()
+        .map(|schedule| DispatchEvent::ScheduleCreate { id: Some(schedule.id), device_id: device.id })
+        .collect();
+
+    events.push(DispatchEvent::ScheduleCreate { id: None, device_id: device.id });
+    events.push(DispatchEvent::ScheduleDelete { id: None, device_id: device.id });
+    events.push(DispatchEvent::ScheduleFire { id: None, state: 0, device_id: device.id });
+
+    for event in events {
+        let response = DispatchMessage {
+            event,
+            new_subscribers: Some(vec![connection.id]),
+        };
+
+        Socket::send_message(
+            message.id,
+            response,
+            connection.socket.downgrade().recipient(),
+            connection,
+            context,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn subscribe(
+    to: String,
+    message: WebSocketMessage,
+    connection: &mut WebSocketConnection,
+    context: &mut WebsocketContext<WebSocketConnection>,
+) -> Result<(), WebSocketError> {
+    match to.as_str() {
+        "device/schedules" => device_schedules_update(message, connection, context)?,
+        _ => return Err(WebSocketErrorTemplate::InvalidRequestField(None).into()),
+    }
+
+    Ok(())
+}
@@ -1,9 +1,11 @@
 use std::io::ErrorKind;
 
 use actix_web_actors::ws::WebsocketContext;
+use amqp::routes::{UserMeUpdated, UserUpdated};
+use amqp::RoutedMessage;
 
 use crate::error::{WebSocketError, WebSocketErrorKind, WebSocketErrorTemplate};
-use crate::messages::{DispatchEvent, DispatchMessage, Method, Opcode, WebSocketMessage, WebSocketMessageData};
+use crate::messages::{DispatchUser, DispatchUserMe, Method, Opcode, WebSocketMessage, WebSocketMessageData};
 use crate::server::{Socket, WebSocketConnection};
 use crate::services::session::Session;
 use crate::services::user::{User, UserLocale, UserMe, UserPublic};
@@ -140,33 +142,19 @@ fn patch_user(
 
         if current_user != updated_user {
             // Notify all user sessions that are subscribed to themselves
-            let response = DispatchMessage {
-                event: DispatchEvent::UserMeUpdate { id: session_user_id },
-                new_subscribers: None,
-            };
-
-            Socket::send_message(
-                message.id,
-                response,
-                connection.socket.downgrade().recipient(),
+            Socket::publish_dispatch(
+                RoutedMessage::new::<UserMeUpdated>(DispatchUserMe { id: session_user_id }, None),
                 connection,
                 context,
-            )?;
+            );
 
             if UserPublic::from(updated_user) != UserPublic::from(current_user) {
                 // Notify all those who are subscribed to this user
-                let response = DispatchMessage {
-                    event: DispatchEvent::UserUpdate { id: session_user_id },
-                    new_subscribers: None,
-                };
-
-                Socket::send_message(
-                    message.id,
-                    response,
-                    connection.socket.downgrade().recipient(),
+                Socket::publish_dispatch(
+                    RoutedMessage::new::<UserUpdated>(DispatchUser { id: session_user_id }, None),
                     connection,
                     context,
-                )?;
+                );
             }
         }
     }
@@ -17,7 +17,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 use serde_variant::to_variant_name;
 
 use crate::error::{WebSocketError, WebSocketErrorTemplate};
-use crate::services::greenhouse::Greenhouse;
+use crate::services::greenhouse::{Greenhouse, GreenhouseModel};
 
 #[derive(Clone, Deserialize, Serialize, Insertable, Queryable, PartialEq)]
 #[diesel(table_name = users)]
@@ -1,7 +1,9 @@
-use std::net::UdpSocket;
+use std::net::{IpAddr, ToSocketAddrs, UdpSocket};
 use std::time::Duration;
 
+use domain::network::is_public_ip;
 use rustdns::{Class, Extension, Message, Record, Type};
+use url::{Host, Url};
 
 use crate::error::WebSocketError;
 
@@ -23,3 +25,23 @@ pub fn get_mx_records(domain: &str) -> Result<Vec<Record>, WebSocketError> {
 
     Ok(answer.answers)
 }
+
+// Domains are resolved, so a domain of an internal address is refused too
+pub fn is_public_http_url(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else { return false };
+
+    if !matches!(url.scheme(), "http" | "https") { return false; }
+
+    let Some(port) = url.port_or_known_default() else { return false };
+    let addresses: Vec<IpAddr> = match url.host() {
+        Some(Host::Domain(domain)) => match (domain, port).to_socket_addrs() {
+            Ok(addresses) => addresses.map(|address| address.ip()).collect(),
+            Err(_) => return false,
+        },
+        Some(Host::Ipv4(ip)) => vec![ip.into()],
+        Some(Host::Ipv6(ip)) => vec![ip.into()],
+        None => return false,
+    };
+
+    !addresses.is_empty() && addresses.iter().all(is_public_ip)
+}
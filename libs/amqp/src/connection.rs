@@ -0,0 +1,143 @@
+use std::env;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use async_io::Timer;
+use futures::executor::block_on;
+use lapin::{Channel, Connection, ConnectionProperties, ConnectionState, Error, Result};
+use lapin::options::ConfirmSelectOptions;
+use lazy_static::lazy_static;
+
+use crate::{publisher, routes};
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAXIMUM_BACKOFF: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref CONNECTION: RwLock<Option<Arc<Connection>>> = RwLock::new(None);
+    // Shared by publishers, it's created again after a reconnect
+    static ref CHANNEL: Mutex<Option<Channel>> = Mutex::new(None);
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConnectionHealth {
+    Connected,
+    /// The connection was lost, a new one is being established with backoff
+    Reconnecting,
+}
+
+async fn connect() -> Result<Arc<Connection>> {
+    let amqp_url = env::var("AMQP_URL").expect("AMQP_URL not set");
+    let connection = Connection::connect(&amqp_url, ConnectionProperties::default()).await?;
+
+    connection.on_error(|error| warn!("AMQP connection failed: {error}"));
+
+    // A restarted server may have lost the topology, so it's declared on every connect
+    let channel = connection.create_channel().await?;
+
+    routes::declare_topology(&channel).await?;
+    channel.close(200, "OK").await?;
+
+    if let Err(error) = routes::remove_legacy_queues(&connection).await {
+        warn!("Failed to remove legacy AMQP queues: {error}");
+    }
+
+    Ok(Arc::new(connection))
+}
+
+async fn connect_with_backoff() -> Arc<Connection> {
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        match connect().await {
+            Ok(connection) => break connection,
+            Err(error) => {
+                warn!("Failed to connect to the AMQP server, retrying in {backoff:?}: {error}");
+
+                Timer::after(backoff).await;
+                backoff = (backoff * 2).min(MAXIMUM_BACKOFF);
+            },
+        }
+    }
+}
+
+fn set_connection(connection: Arc<Connection>) {
+    *CONNECTION.write().unwrap() = Some(connection);
+    *CHANNEL.lock().unwrap() = None;
+}
+
+async fn supervise() {
+    loop {
+        Timer::after(HEALTH_CHECK_INTERVAL).await;
+
+        if is_connected() {
+            publisher::publish_buffered_messages().await;
+
+            continue;
+        }
+
+        warn!("Lost connection to the AMQP server, reconnecting");
+        set_connection(connect_with_backoff().await);
+        info!("Reconnected to the AMQP server");
+        publisher::publish_buffered_messages().await;
+    }
+}
+
+/// Connects to the server, then watches the connection in its own thread
+/// and reconnects with backoff whenever it's lost
+pub(crate) fn start_connection_supervisor() {
+    set_connection(block_on(connect_with_backoff()));
+
+    thread::spawn(|| block_on(supervise()));
+}
+
+/// Connects to the server in the thread that watches the connection afterwards,
+/// messages published until the first connection are buffered
+pub(crate) fn start_connection_supervisor_in_background() {
+    thread::spawn(|| block_on(async {
+        set_connection(connect_with_backoff().await);
+        info!("Connected to the AMQP server");
+        publisher::publish_buffered_messages().await;
+
+        supervise().await
+    }));
+}
+
+pub fn get_connection() -> Result<Arc<Connection>> {
+    match CONNECTION.read().unwrap().as_ref() {
+        Some(connection) if connection.status().connected() => Ok(connection.clone()),
+        Some(connection) => Err(Error::InvalidConnectionState(connection.status().state())),
+        None => Err(Error::InvalidConnectionState(ConnectionState::Initial)),
+    }
+}
+
+/// Channel shared by publishers, a new one is created if the previous one was closed.
+/// The server confirms every message published on it, so a message it couldn't take isn't lost silently
+pub async fn get_channel() -> Result<Channel> {
+    let channel = CHANNEL.lock().unwrap().clone();
+
+    if let Some(channel) = channel.filter(|channel| channel.status().connected()) {
+        return Ok(channel);
+    }
+
+    let channel = get_connection()?.create_channel().await?;
+
+    channel.confirm_select(ConfirmSelectOptions::default()).await?;
+
+    *CHANNEL.lock().unwrap() = Some(channel.clone());
+
+    Ok(channel)
+}
+
+pub fn get_health() -> ConnectionHealth {
+    match is_connected() {
+        true => ConnectionHealth::Connected,
+        false => ConnectionHealth::Reconnecting,
+    }
+}
+
+pub fn is_connected() -> bool {
+    get_connection().is_ok()
+}
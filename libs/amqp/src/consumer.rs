@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::time::Duration;
+
+use async_io::Timer;
+use domain::amqp::AmqpMessage;
+use futures::StreamExt;
+use lapin::{Consumer, Result};
+use lapin::message::Delivery;
+use lapin::options::{BasicAckOptions, BasicConsumeOptions, BasicNackOptions, BasicPublishOptions, BasicQosOptions};
+use lapin::types::{AMQPValue, FieldTable};
+
+use crate::{get_channel, get_connection, get_prefetch_count, routes, PublishError};
+use crate::routes::Queue;
+
+const RESTART_INTERVAL: Duration = Duration::from_secs(1);
+const ATTEMPT_HEADER: &str = "x-attempt";
+
+async fn start_consumer<Q: Queue>(consumer_name: &str, consumer_tag: &str) -> Result<Consumer> {
+    let channel = get_connection()?.create_channel().await?;
+    let queue = routes::declare_consumer_queue::<Q>(&channel).await?;
+
+    channel.basic_qos(get_prefetch_count(consumer_name), BasicQosOptions::default()).await?;
+    channel.basic_consume(
+        &queue,
+        consumer_tag,
+        BasicConsumeOptions::default(),
+        FieldTable::default(),
+    ).await
+}
+
+/// Decodes the delivered message and narrows its payload down to the payload of the queue,
+/// a message that can't be decoded or narrowed is sent to the dead-letter exchange
+async fn decode<Q: Queue>(delivery: &Delivery, consumer_name: &str) -> Option<AmqpMessage<Q::Payload>> {
+    let message = AmqpMessage::decode(delivery.data.as_slice())
+        .and_then(|message| message.try_into_payload());
+
+    match message {
+        Ok(message) => Some(message),
+        Err(error) => {
+            warn!("Rejected message in {consumer_name} consumer: {error}");
+
+            // After a reconnect the delivery is redelivered anyway
+            if let Err(error) = delivery.nack(BasicNackOptions { requeue: false, ..Default::default() }).await {
+                warn!("Failed to NACK in {consumer_name} consumer: {error}");
+            }
+
+            None
+        },
+    }
+}
+
+/// Number of the attempt to process the delivered message, counted from 1 by `retry()`
+pub fn get_attempt(delivery: &Delivery) -> u32 {
+    let attempt = delivery.properties.headers().as_ref()
+        .and_then(|headers| headers.inner().get(ATTEMPT_HEADER).cloned());
+
+    match attempt {
+        Some(AMQPValue::LongUInt(attempt)) => attempt,
+        _ => 1,
+    }
+}
+
+/// Publishes a copy of the delivered message straight to the queue with the next attempt in its headers,
+/// then acknowledges the delivery once the server confirmed the copy.
+/// A requeued message carries no count, `redelivered` is set after a reconnect too.
+/// Only a shared queue can be given, the name of a per-instance queue is known to its consumer only
+pub async fn retry(delivery: &Delivery, queue: &str) -> std::result::Result<(), PublishError> {
+    let mut headers = delivery.properties.headers().clone().unwrap_or_default();
+
+    headers.insert(ATTEMPT_HEADER.into(), AMQPValue::LongUInt(get_attempt(delivery) + 1));
+
+    // The default exchange routes the copy to the queue only, not to every queue of the route
+    let confirmation = get_channel().await?.basic_publish(
+        "",
+        queue,
+        BasicPublishOptions::default(),
+        delivery.data.as_slice(),
+        delivery.properties.clone().with_headers(headers),
+    ).await?.await?;
+
+    // The delivery isn't acknowledged, so it's requeued instead of lost
+    if !confirmation.is_ack() { return Err(PublishError::NotConfirmed); }
+
+    Ok(delivery.ack(BasicAckOptions::default()).await?)
+}
+
+/// Consumes the queue for as long as the process runs,
+/// the handler is given only messages with the payload of the queue.
+/// After a reconnect the consumer is started again on a new channel,
+/// unacknowledged deliveries of the old channel are redelivered by the server
+pub async fn consume<Q, F, T>(consumer_name: &str, consumer_tag: &str, mut handle: F)
+    where Q: Queue, F: FnMut(Delivery, AmqpMessage<Q::Payload>) -> T, T: Future<Output = ()> {
+    loop {
+        match start_consumer::<Q>(consumer_name, consumer_tag).await {
+            Ok(mut consumer) => {
+                info!("Started AMQP {consumer_name} consumer");
+
+                while let Some(delivery) = consumer.next().await {
+                    match delivery {
+                        Ok(delivery) => {
+                            let Some(message)
+                                = decode::<Q>(&delivery, consumer_name).await else { continue };
+
+                            handle(delivery, message).await;
+                        },
+                        Err(error) => {
+                            warn!("AMQP {consumer_name} consumer stopped: {error}");
+
+                            break;
+                        },
+                    }
+                }
+            },
+            Err(error) => warn!("Failed to start AMQP {consumer_name} consumer: {error}"),
+        }
+
+        Timer::after(RESTART_INTERVAL).await;
+    }
+}
@@ -3,30 +3,39 @@ extern crate log;
 
 use std::env;
 
-use futures::executor::block_on;
-use lapin::{Connection, ConnectionProperties};
+pub use connection::*;
+pub use consumer::*;
 pub use lapin::*;
-use lazy_static::lazy_static;
-
-lazy_static! {
-    static ref CONNECTION: Connection = {
-        let amqp_url = env::var("AMQP_URL").expect("AMQP_URL not set");
-
-        block_on(async move {
-            Connection::connect(
-                &amqp_url,
-                ConnectionProperties::default(),
-            ).await.expect("Failed to connect to the server")
-        })
-    };
-}
+pub use publisher::*;
+
+mod connection;
+mod consumer;
+mod publisher;
+pub mod routes;
+
+/// Number of unacknowledged messages the consumer is given at once.
+/// `AMQP_{CONSUMER_NAME}_PREFETCH_COUNT` overrides `AMQP_PREFETCH_COUNT` for a single consumer
+pub fn get_prefetch_count(consumer_name: &str) -> u16 {
+    let variable = format!(
+        "AMQP_{}_PREFETCH_COUNT",
+        consumer_name.to_uppercase().replace('-', "_"),
+    );
 
-pub fn get_connection() -> &'static Connection {
-    &CONNECTION
+    env::var(&variable)
+        .or_else(|_| env::var("AMQP_PREFETCH_COUNT"))
+        .unwrap_or_else(|_| "10".to_string())
+        .parse::<u16>().unwrap_or_else(|_| panic!("{variable} and AMQP_PREFETCH_COUNT must be u16"))
 }
 
 pub fn init() {
     info!("Initialize AMQP");
 
-    lazy_static::initialize(&CONNECTION);
+    connection::start_connection_supervisor();
+}
+
+/// Doesn't wait for the server, so a module that only publishes starts while it's unreachable
+pub fn init_in_background() {
+    info!("Initialize AMQP in background");
+
+    connection::start_connection_supervisor_in_background();
 }
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+use domain::amqp::{AmqpMessage, AmqpMessageError};
+use lapin::{BasicProperties, Error};
+use lapin::options::BasicPublishOptions;
+use lazy_static::lazy_static;
+
+use crate::{get_channel, is_connected};
+use crate::routes::{Binding, Route};
+
+const MAXIMUM_BUFFERED_MESSAGES: usize = 1000;
+
+lazy_static! {
+    // Messages published while the connection is lost, they are published in order after a reconnect
+    static ref BUFFERED_MESSAGES: Mutex<VecDeque<RoutedMessage>> = Mutex::new(VecDeque::new());
+}
+
+/// A message that can only be built for a route with the payload of the route
+#[derive(Clone, Debug)]
+pub struct RoutedMessage {
+    binding: Binding,
+    message: AmqpMessage,
+}
+
+impl RoutedMessage {
+    pub fn new<R: Route>(payload: R::Payload, correlation_id: Option<i64>) -> Self {
+        RoutedMessage {
+            binding: Binding::of::<R>(),
+            message: AmqpMessage::new(payload.into(), correlation_id),
+        }
+    }
+
+    pub fn get_binding(&self) -> Binding {
+        self.binding
+    }
+
+    pub fn get_message(&self) -> &AmqpMessage {
+        &self.message
+    }
+}
+
+#[derive(Debug)]
+pub enum PublishError {
+    Malformed(AmqpMessageError),
+    Amqp(Error),
+    /// The connection is lost for so long that no more messages are buffered
+    BufferFull,
+    /// The server didn't take the message
+    NotConfirmed,
+}
+
+impl Display for PublishError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PublishError::Malformed(error) => error.fmt(formatter),
+            PublishError::Amqp(error) => error.fmt(formatter),
+            PublishError::BufferFull => write!(formatter, "{MAXIMUM_BUFFERED_MESSAGES} messages are buffered already"),
+            PublishError::NotConfirmed => write!(formatter, "The message wasn't confirmed by the server"),
+        }
+    }
+}
+
+impl From<AmqpMessageError> for PublishError {
+    fn from(error: AmqpMessageError) -> Self {
+        PublishError::Malformed(error)
+    }
+}
+
+impl From<Error> for PublishError {
+    fn from(error: Error) -> Self {
+        PublishError::Amqp(error)
+    }
+}
+
+fn buffer(message: RoutedMessage) -> Result<(), PublishError> {
+    let mut buffered_messages = BUFFERED_MESSAGES.lock().unwrap();
+
+    if buffered_messages.len() >= MAXIMUM_BUFFERED_MESSAGES { return Err(PublishError::BufferFull); }
+
+    buffered_messages.push_back(message);
+
+    Ok(())
+}
+
+/// Publishes the message to the exchange and with the routing key of its route.
+/// While the connection is lost the message is buffered and published after a reconnect,
+/// an error means the message is lost, so the caller must handle it
+pub async fn publish(message: RoutedMessage) -> Result<(), PublishError> {
+    // Buffered messages go first, so messages stay in order
+    if !is_connected() || !BUFFERED_MESSAGES.lock().unwrap().is_empty() {
+        message.message.encode()?;
+
+        return buffer(message);
+    }
+
+    match send(&message).await {
+        // The connection was lost while publishing
+        Err(PublishError::Amqp(_)) if !is_connected() => buffer(message),
+        // Buffered messages are published again while connected too
+        Err(PublishError::NotConfirmed) => buffer(message),
+        result => result,
+    }
+}
+
+/// Publishes buffered messages until the buffer is empty or the connection is lost again
+pub(crate) async fn publish_buffered_messages() {
+    loop {
+        let Some(message) = BUFFERED_MESSAGES.lock().unwrap().pop_front() else { return };
+
+        if let Err(error) = send(&message).await {
+            warn!("Failed to publish buffered message {}: {error}", message.message.id);
+
+            BUFFERED_MESSAGES.lock().unwrap().push_front(message);
+
+            return;
+        }
+    }
+}
+
+async fn send(message: &RoutedMessage) -> Result<(), PublishError> {
+    let RoutedMessage { binding, message } = message;
+    let data = message.encode()?;
+    let mut properties = BasicProperties::default()
+        .with_message_id(message.id.to_string().into())
+        .with_timestamp(message.timestamp)
+        .with_content_type("application/json".into());
+
+    if let Some(correlation_id) = message.correlation_id {
+        properties = properties.with_correlation_id(correlation_id.to_string().into());
+    }
+
+    let confirmation = get_channel().await?.basic_publish(
+        binding.exchange.get_name(),
+        binding.routing_key,
+        BasicPublishOptions::default(),
+        data.as_slice(),
+        properties,
+    ).await?.await?;
+
+    match confirmation.is_ack() {
+        true => Ok(()),
+        false => Err(PublishError::NotConfirmed),
+    }
+}
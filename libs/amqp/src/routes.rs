@@ -0,0 +1,379 @@
+//! Exchanges, routes and queues of the Garthen Project.
+//! A route ties a routing key to the payload it carries,
+//! a queue is bound to routes whose payloads it can consume
+
+use domain::amqp::{
+    AmqpPayload,
+    ChangeControllerState,
+    DispatchAlertCreate,
+    DispatchAlertResolve,
+    DispatchCommand,
+    DispatchData,
+    DispatchDevice,
+    DispatchDeviceCreate,
+    DispatchDeviceDelete,
+    DispatchGreenhouse,
+    DispatchGreenhouseCreate,
+    DispatchGreenhouseDelete,
+    DispatchScheduleCreate,
+    DispatchScheduleDelete,
+    DispatchScheduleFire,
+    DispatchUser,
+    DispatchUserMe,
+    RequestData,
+};
+use lapin::{Channel, Connection, ExchangeKind, Result};
+use lapin::options::{ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions, QueueDeleteOptions};
+use lapin::types::{AMQPValue, FieldTable};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Exchange {
+    Data,
+    Device,
+    Alert,
+    User,
+    Greenhouse,
+    /// Rejected messages are routed here, so they can be inspected instead of being lost
+    DeadLetter,
+}
+
+impl Exchange {
+    pub const ALL: [Exchange; 6] = [
+        Exchange::Data,
+        Exchange::Device,
+        Exchange::Alert,
+        Exchange::User,
+        Exchange::Greenhouse,
+        Exchange::DeadLetter,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Exchange::Data => "data",
+            Exchange::Device => "device",
+            Exchange::Alert => "alert",
+            Exchange::User => "user",
+            Exchange::Greenhouse => "greenhouse",
+            Exchange::DeadLetter => "dead-letter",
+        }
+    }
+
+    pub fn get_kind(&self) -> ExchangeKind {
+        match self {
+            Exchange::DeadLetter => ExchangeKind::Fanout,
+            _ => ExchangeKind::Topic,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Binding {
+    pub exchange: Exchange,
+    pub routing_key: &'static str,
+}
+
+impl Binding {
+    pub const fn of<R: Route>() -> Self {
+        Binding {
+            exchange: R::EXCHANGE,
+            routing_key: R::ROUTING_KEY,
+        }
+    }
+}
+
+pub trait Route {
+    type Payload: Into<AmqpPayload>;
+
+    const EXCHANGE: Exchange;
+    const ROUTING_KEY: &'static str;
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QueueKind {
+    /// Consumers share the queue, so every message is processed by one of them
+    Shared,
+    /// Every consumer declares its own exclusive queue named by the server,
+    /// so every instance of a module gets every message
+    PerInstance,
+}
+
+pub trait Queue {
+    /// Payload of every route the queue is bound to
+    type Payload: TryFrom<AmqpPayload>;
+
+    const NAME: &'static str;
+    const KIND: QueueKind;
+    const BINDINGS: &'static [Binding];
+}
+
+macro_rules! routes {
+    ( $( $name:ident($exchange:ident, $routing_key:literal): $payload:ty; )+ ) => {
+        $(
+            pub struct $name;
+
+            impl Route for $name {
+                type Payload = $payload;
+
+                const EXCHANGE: Exchange = Exchange::$exchange;
+                const ROUTING_KEY: &'static str = $routing_key;
+            }
+        )+
+    };
+}
+
+macro_rules! queues {
+    ( $( $name:ident($queue:literal, $kind:ident): $payload:ty = [$($route:ident),+ $(,)?]; )+ ) => {
+        $(
+            pub struct $name;
+
+            impl Queue for $name {
+                type Payload = $payload;
+
+                const NAME: &'static str = $queue;
+                const KIND: QueueKind = QueueKind::$kind;
+                const BINDINGS: &'static [Binding] = &[$(Binding::of::<$route>(),)+];
+            }
+
+            // Binding a route whose payload the queue can't consume doesn't compile
+            const _: fn() = || {
+                fn check<R: Route>() where R::Payload: Into<$payload> {}
+
+                $(check::<$route>();)+
+            };
+        )+
+
+        const QUEUES: &[(&str, QueueKind, &[Binding])] = &[$(($queue, $name::KIND, $name::BINDINGS),)+];
+    };
+}
+
+routes! {
+    DataRequest(Data, "data.request"): RequestData;
+    DataCreated(Data, "data.created"): DispatchData;
+    DeviceControllerStateChange(Device, "device.controller.state.change"): ChangeControllerState;
+    DeviceControllerStateChanged(Device, "device.controller.state.changed"): DispatchDevice;
+    DeviceStatusChanged(Device, "device.status.changed"): DispatchDevice;
+    DeviceScheduleFired(Device, "device.schedule.fired"): DispatchScheduleFire;
+    DeviceCommandFinished(Device, "device.command.finished"): DispatchCommand;
+    DeviceCreated(Device, "device.created"): DispatchDeviceCreate;
+    DeviceUpdated(Device, "device.updated"): DispatchDevice;
+    DeviceDeleted(Device, "device.deleted"): DispatchDeviceDelete;
+    DeviceScheduleCreated(Device, "device.schedule.created"): DispatchScheduleCreate;
+    DeviceScheduleDeleted(Device, "device.schedule.deleted"): DispatchScheduleDelete;
+    AlertCreated(Alert, "alert.created"): DispatchAlertCreate;
+    AlertResolved(Alert, "alert.resolved"): DispatchAlertResolve;
+    UserUpdated(User, "user.updated"): DispatchUser;
+    UserMeUpdated(User, "user.me.updated"): DispatchUserMe;
+    GreenhouseCreated(Greenhouse, "greenhouse.created"): DispatchGreenhouseCreate;
+    GreenhouseUpdated(Greenhouse, "greenhouse.updated"): DispatchGreenhouse;
+    GreenhouseDeleted(Greenhouse, "greenhouse.deleted"): DispatchGreenhouseDelete;
+}
+
+// Shared queues were named anew with dead-lettering,
+// the server refuses to declare an existing queue with other arguments
+queues! {
+    RequestDataQueue("request-data.v2", Shared): RequestData = [DataRequest];
+    // Subscriptions of clients are kept by every instance of `global-ws` in its memory
+    DispatchDataQueue("dispatch-data", PerInstance): AmqpPayload = [DataCreated];
+    ChangeControllerStateQueue("change-controller-state.v2", Shared): ChangeControllerState = [
+        DeviceControllerStateChange,
+    ];
+    DispatchDeviceQueue("dispatch-device", PerInstance): AmqpPayload = [
+        DeviceControllerStateChanged,
+        DeviceStatusChanged,
+        DeviceScheduleFired,
+        DeviceCommandFinished,
+        DeviceCreated,
+        DeviceUpdated,
+        DeviceDeleted,
+        DeviceScheduleCreated,
+        DeviceScheduleDeleted,
+    ];
+    DispatchAlertQueue("dispatch-alert", PerInstance): AmqpPayload = [AlertCreated, AlertResolved];
+    DispatchUserQueue("dispatch-user", PerInstance): AmqpPayload = [UserUpdated, UserMeUpdated];
+    DispatchGreenhouseQueue("dispatch-greenhouse", PerInstance): AmqpPayload = [
+        GreenhouseCreated,
+        GreenhouseUpdated,
+        GreenhouseDeleted,
+    ];
+}
+
+// Shared queues declared before dead-lettering, the queues that replaced them and their routes.
+// Dispatch queues were shared before they became per-instance, nothing replaces them
+const LEGACY_QUEUES: &[(&str, Option<&str>, &[Binding])] = &[
+    ("request-data", Some(RequestDataQueue::NAME), RequestDataQueue::BINDINGS),
+    ("change-controller-state", Some(ChangeControllerStateQueue::NAME), ChangeControllerStateQueue::BINDINGS),
+    ("dispatch-data", None, &[Binding::of::<DataCreated>()]),
+    ("dispatch-device", None, &[Binding::of::<DeviceControllerStateChanged>()]),
+];
+
+// Rejected messages of every queue go to the dead-letter queue
+fn get_queue_arguments() -> FieldTable {
+    let mut arguments = FieldTable::default();
+
+    arguments.insert(
+        "x-dead-letter-exchange".into(),
+        AMQPValue::LongString(Exchange::DeadLetter.get_name().into()),
+    );
+
+    arguments
+}
+
+async fn bind_queue(channel: &Channel, queue: &str, bindings: &[Binding]) -> Result<()> {
+    for binding in bindings {
+        channel.queue_bind(
+            queue,
+            binding.exchange.get_name(),
+            binding.routing_key,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        ).await?;
+    }
+
+    Ok(())
+}
+
+/// Declares every exchange, shared queue and their bindings
+pub(crate) async fn declare_topology(channel: &Channel) -> Result<()> {
+    let dead_letter = Exchange::DeadLetter.get_name();
+
+    for exchange in Exchange::ALL {
+        channel.exchange_declare(
+            exchange.get_name(),
+            exchange.get_kind(),
+            ExchangeDeclareOptions {
+                durable: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        ).await?;
+    }
+
+    for (queue, _, bindings) in QUEUES.iter().filter(|(_, kind, _)| *kind == QueueKind::Shared) {
+        channel.queue_declare(
+            queue,
+            QueueDeclareOptions {
+                durable: true,
+                ..Default::default()
+            },
+            get_queue_arguments(),
+        ).await?;
+        bind_queue(channel, queue, bindings).await?;
+    }
+
+    channel.queue_declare(
+        dead_letter,
+        QueueDeclareOptions {
+            durable: true,
+            ..Default::default()
+        },
+        FieldTable::default(),
+    ).await?;
+    channel.queue_bind(
+        dead_letter,
+        dead_letter,
+        "",
+        QueueBindOptions::default(),
+        FieldTable::default(),
+    ).await?;
+
+    Ok(())
+}
+
+/// Unbinds legacy queues, so they don't get new messages, and deletes the empty ones.
+/// Messages left in a legacy queue are kept, so they can be moved or inspected
+pub(crate) async fn remove_legacy_queues(connection: &Connection) -> Result<()> {
+    for (queue, new_queue, bindings) in LEGACY_QUEUES {
+        // The server closes the channel when a queue doesn't exist, so every queue gets its own
+        let channel = connection.create_channel().await?;
+        let is_existing = channel.queue_declare(
+            queue,
+            QueueDeclareOptions {
+                passive: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        ).await.is_ok();
+
+        if !is_existing { continue; }
+
+        for binding in bindings.iter() {
+            channel.queue_unbind(
+                queue,
+                binding.exchange.get_name(),
+                binding.routing_key,
+                FieldTable::default(),
+            ).await?;
+        }
+
+        // Counted after unbinding, so no message arrives in between
+        let message_count = channel.queue_declare(
+            queue,
+            QueueDeclareOptions {
+                passive: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        ).await?.message_count();
+
+        match (message_count, new_queue) {
+            (0, _) => {
+                channel.queue_delete(
+                    queue,
+                    QueueDeleteOptions {
+                        if_empty: true,
+                        ..Default::default()
+                    },
+                ).await?;
+            },
+            (_, Some(new_queue)) => {
+                warn!("Legacy queue {queue} still holds {message_count} messages, move them to {new_queue}");
+            },
+            (_, None) => warn!("Legacy queue {queue} still holds {message_count} messages, nothing consumes them"),
+        }
+
+        channel.close(200, "OK").await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the name of the queue the consumer consumes from.
+/// A per-instance queue is declared on the channel of the consumer and is deleted with its connection,
+/// so it's declared again whenever the consumer is started
+pub(crate) async fn declare_consumer_queue<Q: Queue>(channel: &Channel) -> Result<String> {
+    match Q::KIND {
+        QueueKind::Shared => Ok(Q::NAME.to_string()),
+        QueueKind::PerInstance => {
+            let queue = channel.queue_declare(
+                "",
+                QueueDeclareOptions {
+                    exclusive: true,
+                    auto_delete: true,
+                    ..Default::default()
+                },
+                get_queue_arguments(),
+            ).await?;
+            let name = queue.name().to_string();
+
+            bind_queue(channel, &name, Q::BINDINGS).await?;
+
+            Ok(name)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings() {
+        assert_eq!(
+            Binding::of::<DeviceStatusChanged>(),
+            Binding { exchange: Exchange::Device, routing_key: "device.status.changed" },
+        );
+        assert!(DispatchDeviceQueue::BINDINGS.contains(&Binding::of::<DeviceCreated>()));
+        assert_eq!(DispatchDeviceQueue::KIND, QueueKind::PerInstance);
+        assert!(QUEUES.iter().all(|(_, _, bindings)| !bindings.is_empty()));
+    }
+}
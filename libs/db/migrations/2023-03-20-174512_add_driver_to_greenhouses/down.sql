@@ -0,0 +1,2 @@
+ALTER TABLE greenhouses
+    DROP COLUMN driver;
@@ -0,0 +1,2 @@
+ALTER TABLE greenhouses
+    ADD driver SMALLINT NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+ALTER TABLE greenhouses
+    DROP COLUMN gateway_url;
+
+ALTER TABLE greenhouses
+    DROP COLUMN gateway_auth_scheme;
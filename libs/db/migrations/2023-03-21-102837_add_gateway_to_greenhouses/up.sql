@@ -0,0 +1,5 @@
+ALTER TABLE greenhouses
+    ADD gateway_url VARCHAR(256);
+
+ALTER TABLE greenhouses
+    ADD gateway_auth_scheme SMALLINT NOT NULL DEFAULT 0;
@@ -0,0 +1,11 @@
+ALTER TABLE devices
+    DROP CONSTRAINT devices_quiet_hours_check;
+
+ALTER TABLE devices
+    DROP COLUMN polling_interval;
+
+ALTER TABLE devices
+    DROP COLUMN quiet_hours_start;
+
+ALTER TABLE devices
+    DROP COLUMN quiet_hours_end;
@@ -0,0 +1,13 @@
+ALTER TABLE devices
+    ADD polling_interval INTEGER NOT NULL DEFAULT 60;
+
+-- Minutes since midnight (UTC), polling is paused from start until end
+ALTER TABLE devices
+    ADD quiet_hours_start SMALLINT;
+
+ALTER TABLE devices
+    ADD quiet_hours_end SMALLINT;
+
+ALTER TABLE devices
+    ADD CONSTRAINT devices_quiet_hours_check
+        CHECK ((quiet_hours_start IS NULL) = (quiet_hours_end IS NULL));
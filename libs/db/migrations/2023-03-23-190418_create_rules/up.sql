@@ -0,0 +1,29 @@
+CREATE TABLE "rules"
+(
+    id              BIGINT PRIMARY KEY,
+    greenhouse_id   BIGINT           NOT NULL
+        CONSTRAINT rules_greenhouses_id_fk
+            REFERENCES greenhouses
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    sensor_id       BIGINT           NOT NULL
+        CONSTRAINT rules_sensors_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    condition       SMALLINT         NOT NULL,
+    threshold       DOUBLE PRECISION NOT NULL,
+    hysteresis      DOUBLE PRECISION NOT NULL DEFAULT 0,
+    duration        INTEGER          NOT NULL DEFAULT 0,
+    controller_id   BIGINT           NOT NULL
+        CONSTRAINT rules_controllers_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    state           SMALLINT         NOT NULL,
+    action_duration INTEGER,
+    created_at      TIMESTAMP        NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX rules_greenhouse_id_index
+    ON rules (greenhouse_id);
+
+CREATE INDEX rules_sensor_id_index
+    ON rules (sensor_id);
@@ -0,0 +1,2 @@
+DROP TABLE "alert_channels";
+DROP TABLE "alerts";
@@ -0,0 +1,40 @@
+CREATE TABLE "alerts"
+(
+    id            BIGINT PRIMARY KEY,
+    greenhouse_id BIGINT           NOT NULL
+        CONSTRAINT alerts_greenhouses_id_fk
+            REFERENCES greenhouses
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    device_id     BIGINT
+        CONSTRAINT alerts_devices_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    kind          SMALLINT         NOT NULL,
+    data          DOUBLE PRECISION NOT NULL,
+    threshold     DOUBLE PRECISION NOT NULL,
+    created_at    TIMESTAMP        NOT NULL DEFAULT current_timestamp,
+    resolved_at   TIMESTAMP
+);
+
+CREATE INDEX alerts_greenhouse_id_index
+    ON alerts (greenhouse_id);
+
+-- Only one incident of a kind can be open per greenhouse or device
+CREATE UNIQUE INDEX alerts_open_index
+    ON alerts (greenhouse_id, kind, COALESCE(device_id, 0))
+    WHERE resolved_at IS NULL;
+
+CREATE TABLE "alert_channels"
+(
+    id            BIGINT PRIMARY KEY,
+    greenhouse_id BIGINT       NOT NULL
+        CONSTRAINT alert_channels_greenhouses_id_fk
+            REFERENCES greenhouses
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    kind          SMALLINT     NOT NULL,
+    target        VARCHAR(256) NOT NULL,
+    created_at    TIMESTAMP    NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX alert_channels_greenhouse_id_index
+    ON alert_channels (greenhouse_id);
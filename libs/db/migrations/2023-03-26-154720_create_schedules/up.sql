@@ -0,0 +1,31 @@
+CREATE TABLE "schedules"
+(
+    id            BIGINT PRIMARY KEY,
+    greenhouse_id BIGINT    NOT NULL
+        CONSTRAINT schedules_greenhouses_id_fk
+            REFERENCES greenhouses
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    device_id     BIGINT    NOT NULL
+        CONSTRAINT schedules_devices_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    kind          SMALLINT  NOT NULL,
+    state         SMALLINT  NOT NULL,
+    -- Minutes since midnight in UTC, used by daily schedules
+    start_time    SMALLINT,
+    end_time      SMALLINT,
+    -- Seconds, used by periodic schedules
+    period        INTEGER,
+    duration      INTEGER,
+    created_at    TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    CONSTRAINT schedules_daily_check
+        CHECK (kind != 0 OR (start_time IS NOT NULL AND end_time IS NOT NULL)),
+    CONSTRAINT schedules_periodic_check
+        CHECK (kind != 1 OR (period IS NOT NULL AND duration IS NOT NULL AND duration < period))
+);
+
+CREATE INDEX schedules_greenhouse_id_index
+    ON schedules (greenhouse_id);
+
+CREATE INDEX schedules_device_id_index
+    ON schedules (device_id);
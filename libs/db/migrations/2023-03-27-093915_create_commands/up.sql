@@ -0,0 +1,15 @@
+CREATE TABLE "commands"
+(
+    id         BIGINT PRIMARY KEY,
+    device_id  BIGINT    NOT NULL
+        CONSTRAINT commands_devices_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    state      SMALLINT  NOT NULL,
+    status     SMALLINT  NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX commands_device_id_index
+    ON commands (device_id);
@@ -0,0 +1,10 @@
+DROP INDEX devices_revert_at_index;
+
+ALTER TABLE devices
+    DROP CONSTRAINT devices_revert_check;
+
+ALTER TABLE devices
+    DROP COLUMN revert_state;
+
+ALTER TABLE devices
+    DROP COLUMN revert_at;
@@ -0,0 +1,14 @@
+-- A controller that was set for a limited time gets the revert state back at the revert time
+ALTER TABLE devices
+    ADD revert_state SMALLINT;
+
+ALTER TABLE devices
+    ADD revert_at TIMESTAMP;
+
+ALTER TABLE devices
+    ADD CONSTRAINT devices_revert_check
+        CHECK ((revert_state IS NULL) = (revert_at IS NULL));
+
+CREATE INDEX devices_revert_at_index
+    ON devices (revert_at)
+    WHERE revert_at IS NOT NULL;
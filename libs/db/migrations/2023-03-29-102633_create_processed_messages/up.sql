@@ -0,0 +1,12 @@
+-- IDs of AMQP messages that were processed, so their redeliveries are skipped
+CREATE TABLE "processed_messages"
+(
+    id         BIGINT      NOT NULL,
+    consumer   VARCHAR(64) NOT NULL,
+    created_at TIMESTAMP   NOT NULL DEFAULT current_timestamp,
+    CONSTRAINT processed_messages_pk
+        PRIMARY KEY (id, consumer)
+);
+
+CREATE INDEX processed_messages_created_at_index
+    ON processed_messages (created_at);
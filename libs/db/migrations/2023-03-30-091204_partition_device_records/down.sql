@@ -0,0 +1,26 @@
+CREATE TABLE "device_records_partitioned"
+(
+    id         BIGINT PRIMARY KEY,
+    device_id  BIGINT    NOT NULL
+        CONSTRAINT device_records_devices_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    data       FLOAT     NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+);
+
+INSERT INTO device_records_partitioned
+SELECT *
+FROM device_records;
+
+DROP TABLE device_records;
+DROP FUNCTION create_device_records_partitions(TIMESTAMP, TIMESTAMP);
+DROP FUNCTION drop_device_records_partitions_before(TIMESTAMP);
+
+ALTER TABLE device_records_partitioned
+    RENAME TO device_records;
+ALTER INDEX device_records_partitioned_pkey
+    RENAME TO device_records_pkey;
+
+CREATE INDEX device_records_device_id_created_at_index
+    ON device_records (device_id, created_at);
@@ -0,0 +1,85 @@
+-- Records are partitioned by month, so old ones are dropped with their partition
+ALTER TABLE device_records
+    RENAME TO device_records_unpartitioned;
+ALTER INDEX device_records_pkey
+    RENAME TO device_records_unpartitioned_pkey;
+ALTER INDEX device_records_device_id_created_at_index
+    RENAME TO device_records_unpartitioned_device_id_created_at_index;
+
+-- The partition key has to be a part of the primary key
+CREATE TABLE "device_records"
+(
+    id         BIGINT    NOT NULL,
+    device_id  BIGINT    NOT NULL
+        CONSTRAINT device_records_devices_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    data       FLOAT     NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    CONSTRAINT device_records_pk
+        PRIMARY KEY (id, created_at)
+) PARTITION BY RANGE (created_at);
+
+CREATE INDEX device_records_device_id_created_at_index
+    ON device_records (device_id, created_at);
+
+-- Catches records of months that have no partition yet
+CREATE TABLE "device_records_default"
+    PARTITION OF device_records DEFAULT;
+
+-- Creates a partition for every month between the timestamps, the data worker keeps them ahead
+CREATE FUNCTION create_device_records_partitions("from" TIMESTAMP, "until" TIMESTAMP)
+    RETURNS VOID AS
+$$
+DECLARE
+    month TIMESTAMP := date_trunc('month', "from");
+BEGIN
+    WHILE month <= "until"
+        LOOP
+            EXECUTE format(
+                'CREATE TABLE IF NOT EXISTS %I PARTITION OF device_records FOR VALUES FROM (%L) TO (%L)',
+                'device_records_' || to_char(month, 'YYYY_MM'),
+                month,
+                month + INTERVAL '1 month'
+            );
+
+            month := month + INTERVAL '1 month';
+        END LOOP;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Drops partitions of months that ended before the timestamp
+CREATE FUNCTION drop_device_records_partitions_before("before" TIMESTAMP)
+    RETURNS VOID AS
+$$
+DECLARE
+    partition TEXT;
+BEGIN
+    FOR partition IN
+        SELECT child.relname
+        FROM pg_inherits
+                 JOIN pg_class parent ON pg_inherits.inhparent = parent.oid
+                 JOIN pg_class child ON pg_inherits.inhrelid = child.oid
+        WHERE parent.relname = 'device_records'
+          AND child.relname ~ '^device_records_\d{4}_\d{2}$'
+          AND to_date(substring(child.relname FROM 16), 'YYYY_MM') + INTERVAL '1 month' <= "before"
+        LOOP
+            EXECUTE format('DROP TABLE %I', partition);
+        END LOOP;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Custom data can be sent for the last three months, so they always have partitions
+SELECT create_device_records_partitions(
+    least(
+        (SELECT min(created_at) FROM device_records_unpartitioned),
+        current_timestamp::TIMESTAMP - INTERVAL '3 months'
+    ),
+    current_timestamp::TIMESTAMP + INTERVAL '1 month'
+);
+
+INSERT INTO device_records
+SELECT *
+FROM device_records_unpartitioned;
+
+DROP TABLE device_records_unpartitioned;
@@ -0,0 +1,2 @@
+DROP TABLE "device_records_daily";
+DROP TABLE "device_records_hourly";
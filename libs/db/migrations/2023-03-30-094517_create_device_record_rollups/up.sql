@@ -0,0 +1,41 @@
+-- Aggregates of records maintained by the data worker, they outlive the records themselves.
+-- The sum and the count are kept instead of the average, so rollups can be rolled up further
+CREATE TABLE "device_records_hourly"
+(
+    device_id       BIGINT    NOT NULL
+        CONSTRAINT device_records_hourly_devices_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    bucket          TIMESTAMP NOT NULL,
+    sum             FLOAT     NOT NULL,
+    count           BIGINT    NOT NULL,
+    minimum         FLOAT     NOT NULL,
+    maximum         FLOAT     NOT NULL,
+    last            FLOAT     NOT NULL,
+    last_created_at TIMESTAMP NOT NULL,
+    -- The highest ID of the rolled up records, tells which records are new
+    last_record_id  BIGINT    NOT NULL,
+    CONSTRAINT device_records_hourly_pk
+        PRIMARY KEY (device_id, bucket)
+);
+
+CREATE INDEX device_records_hourly_last_record_id_index
+    ON device_records_hourly (last_record_id);
+
+-- Days are in UTC
+CREATE TABLE "device_records_daily"
+(
+    device_id       BIGINT    NOT NULL
+        CONSTRAINT device_records_daily_devices_id_fk
+            REFERENCES devices
+            ON UPDATE RESTRICT ON DELETE CASCADE,
+    bucket          TIMESTAMP NOT NULL,
+    sum             FLOAT     NOT NULL,
+    count           BIGINT    NOT NULL,
+    minimum         FLOAT     NOT NULL,
+    maximum         FLOAT     NOT NULL,
+    last            FLOAT     NOT NULL,
+    last_created_at TIMESTAMP NOT NULL,
+    CONSTRAINT device_records_daily_pk
+        PRIMARY KEY (device_id, bucket)
+);
@@ -0,0 +1,34 @@
+-- Any opening is open
+UPDATE device_records
+SET data = CASE WHEN data > 0 THEN 1 ELSE 0 END
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE device_records_hourly
+SET sum     = sum / 100,
+    minimum = CASE WHEN minimum > 0 THEN 1 ELSE 0 END,
+    maximum = CASE WHEN maximum > 0 THEN 1 ELSE 0 END,
+    last    = CASE WHEN last > 0 THEN 1 ELSE 0 END
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE device_records_daily
+SET sum     = sum / 100,
+    minimum = CASE WHEN minimum > 0 THEN 1 ELSE 0 END,
+    maximum = CASE WHEN maximum > 0 THEN 1 ELSE 0 END,
+    last    = CASE WHEN last > 0 THEN 1 ELSE 0 END
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE devices
+SET revert_state = CASE WHEN revert_state > 0 THEN 1 ELSE 0 END
+WHERE kind = 5;
+
+UPDATE schedules
+SET state = CASE WHEN state > 0 THEN 1 ELSE 0 END
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE rules
+SET state = CASE WHEN state > 0 THEN 1 ELSE 0 END
+WHERE controller_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE commands
+SET state = CASE WHEN state > 0 THEN 1 ELSE 0 END
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
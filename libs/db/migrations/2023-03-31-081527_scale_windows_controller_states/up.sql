@@ -0,0 +1,35 @@
+-- Windows controllers took 0 (closed) and 1 (open), now they take the opening in percent.
+-- Their stored states were either of the two, so scaling them keeps their meaning
+UPDATE device_records
+SET data = data * 100
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE device_records_hourly
+SET sum     = sum * 100,
+    minimum = minimum * 100,
+    maximum = maximum * 100,
+    last    = last * 100
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE device_records_daily
+SET sum     = sum * 100,
+    minimum = minimum * 100,
+    maximum = maximum * 100,
+    last    = last * 100
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE devices
+SET revert_state = revert_state * 100
+WHERE kind = 5;
+
+UPDATE schedules
+SET state = state * 100
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE rules
+SET state = state * 100
+WHERE controller_id IN (SELECT id FROM devices WHERE kind = 5);
+
+UPDATE commands
+SET state = state * 100
+WHERE device_id IN (SELECT id FROM devices WHERE kind = 5);
@@ -0,0 +1,17 @@
+-- Devices deleted by their owners, so device discovery doesn't create them again
+CREATE TABLE "ignored_devices"
+(
+    id            BIGINT    NOT NULL
+        CONSTRAINT ignored_devices_pk
+            PRIMARY KEY,
+    external_id   SMALLINT,
+    kind          SMALLINT  NOT NULL,
+    greenhouse_id BIGINT    NOT NULL
+        CONSTRAINT ignored_devices_greenhouses_id_fk
+            REFERENCES greenhouses
+            ON DELETE CASCADE,
+    created_at    TIMESTAMP NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX ignored_devices_greenhouse_id_index
+    ON ignored_devices (greenhouse_id);
@@ -0,0 +1,19 @@
+CREATE OR REPLACE FUNCTION create_device_records_partitions("from" TIMESTAMP, "until" TIMESTAMP)
+    RETURNS VOID AS
+$$
+DECLARE
+    month TIMESTAMP := date_trunc('month', "from");
+BEGIN
+    WHILE month <= "until"
+        LOOP
+            EXECUTE format(
+                'CREATE TABLE IF NOT EXISTS %I PARTITION OF device_records FOR VALUES FROM (%L) TO (%L)',
+                'device_records_' || to_char(month, 'YYYY_MM'),
+                month,
+                month + INTERVAL '1 month'
+            );
+
+            month := month + INTERVAL '1 month';
+        END LOOP;
+END;
+$$ LANGUAGE plpgsql;
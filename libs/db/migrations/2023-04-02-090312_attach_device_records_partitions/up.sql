@@ -0,0 +1,41 @@
+-- A partition can't be created for a range the default partition has rows of,
+-- so they are moved into a new table first, which is attached as the partition then
+CREATE OR REPLACE FUNCTION create_device_records_partitions("from" TIMESTAMP, "until" TIMESTAMP)
+    RETURNS VOID AS
+$$
+DECLARE
+    month     TIMESTAMP := date_trunc('month', "from");
+    partition TEXT;
+BEGIN
+    WHILE month <= "until"
+        LOOP
+            partition := 'device_records_' || to_char(month, 'YYYY_MM');
+
+            IF to_regclass(partition) IS NULL THEN
+                -- Records can't be added to the default partition while they are moved
+                LOCK TABLE device_records_default;
+
+                EXECUTE format(
+                    'CREATE TABLE %I (LIKE device_records INCLUDING DEFAULTS)',
+                    partition
+                );
+                EXECUTE format(
+                    'WITH moved AS ('
+                        'DELETE FROM device_records_default WHERE created_at >= %L AND created_at < %L RETURNING *'
+                    ') INSERT INTO %I SELECT * FROM moved',
+                    month,
+                    month + INTERVAL '1 month',
+                    partition
+                );
+                EXECUTE format(
+                    'ALTER TABLE device_records ATTACH PARTITION %I FOR VALUES FROM (%L) TO (%L)',
+                    partition,
+                    month,
+                    month + INTERVAL '1 month'
+                );
+            END IF;
+
+            month := month + INTERVAL '1 month';
+        END LOOP;
+END;
+$$ LANGUAGE plpgsql;
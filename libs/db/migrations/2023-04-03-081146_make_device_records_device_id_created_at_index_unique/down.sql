@@ -0,0 +1,3 @@
+DROP INDEX device_records_device_id_created_at_index;
+CREATE INDEX device_records_device_id_created_at_index
+    ON device_records (device_id, created_at);
@@ -0,0 +1,11 @@
+-- A device has one record of a time, so imports skip the records it already has even when they run concurrently
+DELETE
+FROM device_records duplicate
+    USING device_records original
+WHERE duplicate.device_id = original.device_id
+  AND duplicate.created_at = original.created_at
+  AND duplicate.id > original.id;
+
+DROP INDEX device_records_device_id_created_at_index;
+CREATE UNIQUE INDEX device_records_device_id_created_at_index
+    ON device_records (device_id, created_at);
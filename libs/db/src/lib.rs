@@ -2,6 +2,7 @@
 extern crate log;
 
 use std::env;
+use std::time::Duration;
 
 use diesel::pg::PgConnection;
 use diesel::r2d2::ConnectionManager;
@@ -24,16 +25,34 @@ lazy_static! {
         manager.connect().expect("Failed to connect to the database");
         Pool::new(manager).expect("Failed to create database pool")
     };
+
+    // Raw records are kept forever without it, rollups are kept forever anyway
+    static ref DEVICE_RECORDS_RETENTION: Option<Duration> = {
+        env::var("DEVICE_RECORDS_RETENTION").ok().map(|retention| {
+            let retention = retention.parse::<u64>()
+                .expect("DEVICE_RECORDS_RETENTION must be u64");
+
+            assert!(retention >= 2, "DEVICE_RECORDS_RETENTION must be at least 2 days");
+
+            Duration::from_secs(retention * 86400)
+        })
+    };
 }
 
 pub fn get_connection() -> Result<DbConnection, Error> {
     POOL.get()
 }
 
+// Every module that stores device records has to know it, so it's shared with the database
+pub fn get_device_records_retention() -> Option<Duration> {
+    *DEVICE_RECORDS_RETENTION
+}
+
 pub fn init() {
     info!("Initialize DB");
 
     lazy_static::initialize(&POOL);
+    lazy_static::initialize(&DEVICE_RECORDS_RETENTION);
     let connection = &mut get_connection()
         .expect("Failed to get database connection");
     connection.run_pending_migrations(MIGRATIONS)
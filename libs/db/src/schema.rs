@@ -1,5 +1,39 @@
 diesel::table! {
-    device_records (id) {
+    alert_channels (id) {
+        id -> Int8,
+        greenhouse_id -> Int8,
+        kind -> Int2,
+        target -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    alerts (id) {
+        id -> Int8,
+        greenhouse_id -> Int8,
+        device_id -> Nullable<Int8>,
+        kind -> Int2,
+        data -> Float8,
+        threshold -> Float8,
+        created_at -> Timestamp,
+        resolved_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    commands (id) {
+        id -> Int8,
+        device_id -> Int8,
+        state -> Int2,
+        status -> Int2,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    device_records (id, created_at) {
         id -> Int8,
         device_id -> Int8,
         data -> Float8,
@@ -7,6 +41,33 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    device_records_daily (device_id, bucket) {
+        device_id -> Int8,
+        bucket -> Timestamp,
+        sum -> Float8,
+        count -> Int8,
+        minimum -> Float8,
+        maximum -> Float8,
+        last -> Float8,
+        last_created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    device_records_hourly (device_id, bucket) {
+        device_id -> Int8,
+        bucket -> Timestamp,
+        sum -> Float8,
+        count -> Int8,
+        minimum -> Float8,
+        maximum -> Float8,
+        last -> Float8,
+        last_created_at -> Timestamp,
+        last_record_id -> Int8,
+    }
+}
+
 diesel::table! {
     devices (id) {
         id -> Int8,
@@ -17,6 +78,11 @@ diesel::table! {
         greenhouse_id -> Int8,
         created_at -> Timestamp,
         maximum_data_value -> Nullable<Float8>,
+        polling_interval -> Int4,
+        quiet_hours_start -> Nullable<Int2>,
+        quiet_hours_end -> Nullable<Int2>,
+        revert_state -> Nullable<Int2>,
+        revert_at -> Nullable<Timestamp>,
     }
 }
 
@@ -29,6 +95,58 @@ diesel::table! {
         created_at -> Timestamp,
         maximum_average_humidity -> Nullable<Float8>,
         minimum_average_temperature -> Nullable<Float8>,
+        driver -> Int2,
+        gateway_url -> Nullable<Varchar>,
+        gateway_auth_scheme -> Int2,
+    }
+}
+
+diesel::table! {
+    ignored_devices (id) {
+        id -> Int8,
+        external_id -> Nullable<Int2>,
+        kind -> Int2,
+        greenhouse_id -> Int8,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    processed_messages (id, consumer) {
+        id -> Int8,
+        consumer -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    rules (id) {
+        id -> Int8,
+        greenhouse_id -> Int8,
+        sensor_id -> Int8,
+        condition -> Int2,
+        threshold -> Float8,
+        hysteresis -> Float8,
+        duration -> Int4,
+        controller_id -> Int8,
+        state -> Int2,
+        action_duration -> Nullable<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    schedules (id) {
+        id -> Int8,
+        greenhouse_id -> Int8,
+        device_id -> Int8,
+        kind -> Int2,
+        state -> Int2,
+        start_time -> Nullable<Int2>,
+        end_time -> Nullable<Int2>,
+        period -> Nullable<Int4>,
+        duration -> Nullable<Int4>,
+        created_at -> Timestamp,
     }
 }
 
@@ -53,15 +171,34 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(alert_channels -> greenhouses (greenhouse_id));
+diesel::joinable!(alerts -> devices (device_id));
+diesel::joinable!(alerts -> greenhouses (greenhouse_id));
+diesel::joinable!(commands -> devices (device_id));
 diesel::joinable!(device_records -> devices (device_id));
+diesel::joinable!(device_records_daily -> devices (device_id));
+diesel::joinable!(device_records_hourly -> devices (device_id));
 diesel::joinable!(devices -> greenhouses (greenhouse_id));
 diesel::joinable!(greenhouses -> users (owner_id));
+diesel::joinable!(ignored_devices -> greenhouses (greenhouse_id));
+diesel::joinable!(rules -> greenhouses (greenhouse_id));
+diesel::joinable!(schedules -> devices (device_id));
+diesel::joinable!(schedules -> greenhouses (greenhouse_id));
 diesel::joinable!(sessions -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    alert_channels,
+    alerts,
+    commands,
     device_records,
+    device_records_daily,
+    device_records_hourly,
     devices,
     greenhouses,
+    ignored_devices,
+    processed_messages,
+    rules,
+    schedules,
     sessions,
     users,
 );
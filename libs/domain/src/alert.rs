@@ -0,0 +1,49 @@
+use std::time::SystemTime;
+
+use db::schema::{alert_channels, alerts};
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[diesel(table_name = alerts)]
+pub struct Alert {
+    pub id: i64,
+    pub greenhouse_id: i64,
+    pub device_id: Option<i64>,
+    pub kind: AlertKind,
+    pub data: f64,
+    pub threshold: f64,
+    pub created_at: SystemTime,
+    pub resolved_at: Option<SystemTime>,
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum AlertKind {
+        DataTooHigh = 0,
+        AverageHumidityTooHigh = 1,
+        AverageTemperatureTooLow = 2,
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[diesel(table_name = alert_channels)]
+pub struct AlertChannel {
+    pub id: i64,
+    pub greenhouse_id: i64,
+    pub kind: AlertChannelKind,
+    /// Email address or webhook URL
+    pub target: String,
+    pub created_at: SystemTime,
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum AlertChannelKind {
+        Email = 0,
+        Webhook = 1,
+    }
+}
@@ -0,0 +1,223 @@
+use std::fmt::{Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the envelope and payloads, bumped on every breaking change of them
+pub const AMQP_MESSAGE_VERSION: u16 = 1;
+
+/// Envelope of every message sent between the modules through the message broker.
+/// A consumer narrows `AmqpPayload` down to the payload of its queue
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AmqpMessage<P = AmqpPayload> {
+    pub version: u16,
+    pub id: i64,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// ID of the message this one was sent because of
+    pub correlation_id: Option<i64>,
+    pub payload: P,
+}
+
+impl AmqpMessage {
+    pub fn new(payload: AmqpPayload, correlation_id: Option<i64>) -> Self {
+        AmqpMessage {
+            version: AMQP_MESSAGE_VERSION,
+            id: snowflake::generate(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            correlation_id,
+            payload,
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, AmqpMessageError> {
+        serde_json::to_vec(self).map_err(AmqpMessageError::Malformed)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, AmqpMessageError> {
+        let message: AmqpMessage = serde_json::from_slice(data)
+            .map_err(AmqpMessageError::Malformed)?;
+
+        match message.version {
+            AMQP_MESSAGE_VERSION => Ok(message),
+            version => Err(AmqpMessageError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+impl<P> AmqpMessage<P> {
+    pub fn try_into_payload<T: TryFrom<P>>(self) -> Result<AmqpMessage<T>, AmqpMessageError> {
+        let payload = T::try_from(self.payload)
+            .map_err(|_| AmqpMessageError::UnexpectedPayload)?;
+
+        Ok(AmqpMessage {
+            version: self.version,
+            id: self.id,
+            timestamp: self.timestamp,
+            correlation_id: self.correlation_id,
+            payload,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum AmqpMessageError {
+    Malformed(serde_json::Error),
+    UnsupportedVersion(u16),
+    /// The payload isn't one the queue is bound to
+    UnexpectedPayload,
+}
+
+impl Display for AmqpMessageError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmqpMessageError::Malformed(error) => write!(formatter, "Malformed message: {error}"),
+            AmqpMessageError::UnsupportedVersion(version) => {
+                write!(formatter, "Unsupported message version {version}")
+            },
+            AmqpMessageError::UnexpectedPayload => write!(formatter, "Unexpected payload"),
+        }
+    }
+}
+
+amqp_payloads! {
+    pub struct DispatchData {
+        pub device_id: i64,
+    }
+
+    pub struct RequestData {
+        pub device_id: Option<i64>,
+        pub greenhouse_id: Option<i64>,
+    }
+
+    pub struct ChangeControllerState {
+        pub command_id: i64,
+        pub device_id: i64,
+        pub state: u8,
+        /// Seconds after which the previous state is restored
+        pub duration: Option<u32>,
+    }
+
+    pub struct DispatchDevice {
+        pub id: i64,
+    }
+
+    pub struct DispatchDeviceCreate {
+        pub id: i64,
+        pub greenhouse_id: i64,
+    }
+
+    pub struct DispatchAlertCreate {
+        pub id: i64,
+        pub greenhouse_id: i64,
+    }
+
+    pub struct DispatchAlertResolve {
+        pub id: i64,
+        pub greenhouse_id: i64,
+    }
+
+    pub struct DispatchCommand {
+        pub id: i64,
+        pub device_id: i64,
+    }
+
+    pub struct DispatchScheduleFire {
+        pub id: i64,
+        pub device_id: i64,
+        pub state: u8,
+    }
+
+    // Events caused by requests to `global-ws`,
+    // they go through the broker, so every instance dispatches them to its subscribers
+    pub struct DispatchUser {
+        pub id: i64,
+    }
+
+    pub struct DispatchUserMe {
+        pub id: i64,
+    }
+
+    pub struct DispatchGreenhouse {
+        pub id: i64,
+    }
+
+    pub struct DispatchGreenhouseCreate {
+        pub id: i64,
+        pub owner_id: i64,
+    }
+
+    pub struct DispatchGreenhouseDelete {
+        pub id: i64,
+        pub owner_id: i64,
+    }
+
+    pub struct DispatchDeviceDelete {
+        pub id: i64,
+        pub greenhouse_id: i64,
+    }
+
+    pub struct DispatchScheduleCreate {
+        pub id: i64,
+        pub device_id: i64,
+    }
+
+    pub struct DispatchScheduleDelete {
+        pub id: i64,
+        pub device_id: i64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_message(version: u16) -> AmqpMessage {
+        AmqpMessage {
+            version,
+            id: 1,
+            timestamp: 0,
+            correlation_id: None,
+            payload: DispatchDevice { id: 2 }.into(),
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let data = get_message(AMQP_MESSAGE_VERSION).encode().unwrap();
+
+        assert!(matches!(
+            AmqpMessage::decode(&data),
+            Ok(AmqpMessage { payload: AmqpPayload::DispatchDevice(DispatchDevice { id: 2 }), .. }),
+        ));
+    }
+
+    #[test]
+    fn test_decode_unsupported() {
+        let data = get_message(AMQP_MESSAGE_VERSION + 1).encode().unwrap();
+
+        assert!(matches!(
+            AmqpMessage::decode(&data),
+            Err(AmqpMessageError::UnsupportedVersion(_)),
+        ));
+        assert!(matches!(
+            AmqpMessage::decode(br#"{"dispatch_device":{"id":2}}"#),
+            Err(AmqpMessageError::Malformed(_)),
+        ));
+    }
+
+    #[test]
+    fn test_try_into_payload() {
+        let message = get_message(AMQP_MESSAGE_VERSION);
+
+        assert_eq!(
+            message.clone().try_into_payload::<DispatchDevice>().unwrap().payload,
+            DispatchDevice { id: 2 },
+        );
+        assert!(matches!(
+            message.try_into_payload::<DispatchData>(),
+            Err(AmqpMessageError::UnexpectedPayload),
+        ));
+    }
+}
@@ -0,0 +1,28 @@
+use std::time::SystemTime;
+
+use db::schema::commands;
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[diesel(table_name = commands)]
+pub struct Command {
+    pub id: i64,
+    pub device_id: i64,
+    pub state: i16,
+    pub status: CommandStatus,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum CommandStatus {
+        Pending = 0,
+        Applied = 1,
+        Failed = 2,
+        Timeout = 3,
+    }
+}
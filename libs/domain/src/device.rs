@@ -0,0 +1,207 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use db::schema::{devices, ignored_devices};
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[diesel(table_name = devices)]
+pub struct Device {
+    pub id: i64,
+    pub external_id: Option<i16>,
+    pub name: Option<String>,
+    pub status: DeviceStatus,
+    pub kind: DeviceKind,
+    pub greenhouse_id: i64,
+    pub created_at: SystemTime,
+    pub maximum_data_value: Option<f64>,
+    pub polling_interval: i32,
+    pub quiet_hours_start: Option<i16>,
+    pub quiet_hours_end: Option<i16>,
+    pub revert_state: Option<i16>,
+    pub revert_at: Option<SystemTime>,
+}
+
+impl Device {
+    pub fn get_quiet_hours(&self) -> Option<DeviceQuietHours> {
+        match (self.quiet_hours_start, self.quiet_hours_end) {
+            (Some(start), Some(end)) => Some(DeviceQuietHours { start, end }),
+            _ => None,
+        }
+    }
+
+    pub fn is_in_quiet_hours(&self, time: SystemTime) -> bool {
+        let Some(quiet_hours) = self.get_quiet_hours() else { return false };
+        let minute = (time.duration_since(UNIX_EPOCH).unwrap().as_secs() / 60 % 1440) as i16;
+
+        match quiet_hours.start <= quiet_hours.end {
+            true => quiet_hours.start <= minute && minute < quiet_hours.end,
+            // Quiet hours go over midnight
+            false => minute >= quiet_hours.start || minute < quiet_hours.end,
+        }
+    }
+
+    // Seconds until the controller gets its previous state back
+    pub fn get_state_remaining_time(&self) -> Option<u64> {
+        let revert_at = self.revert_at?;
+
+        match revert_at.duration_since(SystemTime::now()) {
+            Ok(remaining_time) => Some(remaining_time.as_secs()),
+            Err(_) => Some(0),
+        }
+    }
+}
+
+/// Device deleted by its owner, device discovery doesn't create it again
+#[derive(Clone, Debug, Insertable, Queryable)]
+#[diesel(table_name = ignored_devices)]
+pub struct IgnoredDevice {
+    pub id: i64,
+    pub external_id: Option<i16>,
+    pub kind: DeviceKind,
+    pub greenhouse_id: i64,
+    pub created_at: SystemTime,
+}
+
+/// Minutes since midnight (UTC) during which the device isn't polled
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct DeviceQuietHours {
+    pub start: i16,
+    pub end: i16,
+}
+
+/// States a controller accepts, e.g. the opening of windows in percent
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct DeviceStateRange {
+    pub minimum: u8,
+    pub maximum: u8,
+}
+
+impl DeviceStateRange {
+    pub fn contains(&self, state: &u8) -> bool {
+        (self.minimum..=self.maximum).contains(state)
+    }
+}
+
+/// Data a sensor can report in its unit, e.g. 0-14 for pH
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DeviceDataRange {
+    pub minimum: f64,
+    pub maximum: f64,
+}
+
+impl DeviceDataRange {
+    pub fn contains(&self, data: &f64) -> bool {
+        (self.minimum..=self.maximum).contains(data)
+    }
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum DeviceStatus {
+        Offline = 0,
+        Online = 1,
+        Disabled = 2,
+    }
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum DeviceKind {
+        HumiditySensor = 0,
+        SoilMoistureSensor = 1,
+        TemperatureSensor = 2,
+        HumidificationController = 3,
+        IrrigationController = 4,
+        WindowsController = 5,
+        Co2Sensor = 6,
+        LightSensor = 7,
+        PhSensor = 8,
+        EcSensor = 9,
+        WaterLevelSensor = 10,
+    }
+}
+
+impl DeviceKind {
+    // Sensors don't have a state
+    pub fn get_state_range(&self) -> Option<DeviceStateRange> {
+        match self {
+            // Off and power levels
+            DeviceKind::HumidificationController => Some(DeviceStateRange { minimum: 0, maximum: 3 }),
+            DeviceKind::IrrigationController => Some(DeviceStateRange { minimum: 0, maximum: 1 }),
+            // Opening in percent
+            DeviceKind::WindowsController => Some(DeviceStateRange { minimum: 0, maximum: 100 }),
+            _ => None,
+        }
+    }
+
+    pub fn is_valid_state(&self, state: &u8) -> bool {
+        match self.get_state_range() {
+            Some(state_range) => state_range.contains(state),
+            None => false,
+        }
+    }
+
+    /// A stored state, e.g. of a schedule, if a controller of this kind accepts it
+    pub fn get_checked_state(&self, state: i16) -> Option<u8> {
+        let state = u8::try_from(state).ok()?;
+
+        self.is_valid_state(&state).then_some(state)
+    }
+
+    // Controllers don't have data
+    pub fn get_data_range(&self) -> Option<DeviceDataRange> {
+        let (minimum, maximum) = match self {
+            DeviceKind::HumiditySensor => (0.0, 100.0),
+            DeviceKind::SoilMoistureSensor => (0.0, 100.0),
+            DeviceKind::TemperatureSensor => (-100.0, 100.0),
+            DeviceKind::Co2Sensor => (0.0, 10000.0),
+            DeviceKind::LightSensor => (0.0, 200000.0),
+            DeviceKind::PhSensor => (0.0, 14.0),
+            DeviceKind::EcSensor => (0.0, 20.0),
+            DeviceKind::WaterLevelSensor => (0.0, 100.0),
+            _ => return None,
+        };
+
+        Some(DeviceDataRange { minimum, maximum })
+    }
+
+    pub fn get_unit(&self) -> Option<&'static str> {
+        match self {
+            DeviceKind::HumiditySensor
+            | DeviceKind::SoilMoistureSensor
+            | DeviceKind::WaterLevelSensor => Some("%"),
+            DeviceKind::TemperatureSensor => Some("°C"),
+            DeviceKind::Co2Sensor => Some("ppm"),
+            DeviceKind::LightSensor => Some("lx"),
+            DeviceKind::PhSensor => Some("pH"),
+            DeviceKind::EcSensor => Some("mS/cm"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_conversion() {
+        let kind = DeviceKind::WaterLevelSensor;
+
+        assert_eq!(DeviceKind::try_from(kind as i16), Ok(kind));
+        assert!(DeviceKind::try_from(11).is_err());
+        assert!(DeviceStatus::try_from(-1).is_err());
+    }
+
+    #[test]
+    fn test_checked_state() {
+        assert_eq!(DeviceKind::WindowsController.get_checked_state(100), Some(100));
+        assert_eq!(DeviceKind::IrrigationController.get_checked_state(100), None);
+        assert_eq!(DeviceKind::HumidificationController.get_checked_state(-1), None);
+        assert_eq!(DeviceKind::HumiditySensor.get_checked_state(0), None);
+    }
+}
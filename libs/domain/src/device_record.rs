@@ -0,0 +1,154 @@
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use db::schema::device_records;
+use diesel::{Insertable, Queryable};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::device::DeviceKind;
+
+// An average month of the Gregorian calendar
+const MONTH: Duration = Duration::from_secs(2629743);
+const DAY: Duration = Duration::from_secs(86400);
+
+// Custom data can't be older, partitions of these months always exist
+pub const DEVICE_RECORDS_MAXIMUM_CUSTOM_AGE: Duration = Duration::from_secs(MONTH.as_secs() * 3);
+pub const DEVICE_RECORDS_MAXIMUM_IMPORT: usize = 10000;
+
+#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[diesel(table_name = device_records)]
+pub struct DeviceRecord {
+    pub id: i64,
+    pub device_id: i64,
+    pub data: f64,
+    pub created_at: SystemTime,
+}
+
+// Imports are the same in every module, so they are checked and stored here
+impl DeviceRecord {
+    // Records with the time of an existing record of the device or of an earlier one in the list are skipped,
+    // only the created records are returned
+    pub fn create_all_with_custom_time(
+        connection: &mut PgConnection,
+        device_id: i64,
+        records: Vec<(SystemTime, f64)>,
+    ) -> QueryResult<Vec<Self>> {
+        let device_records: Vec<DeviceRecord> = records.into_iter()
+            .map(|(time, data)| DeviceRecord {
+                id: snowflake::generate(),
+                device_id,
+                data,
+                created_at: time,
+            })
+            .collect();
+
+        if device_records.is_empty() { return Ok(device_records) }
+
+        // Imports are limited, so all records fit into the bind parameters of one query.
+        // The unique index skips existing times, also of records another import adds meanwhile
+        diesel::insert_into(device_records::table)
+            .values(device_records)
+            .on_conflict((device_records::device_id, device_records::created_at))
+            .do_nothing()
+            .get_results(connection)
+    }
+
+    // Seconds beyond the range of a time are as much in the future as any other
+    pub fn get_custom_time(secs: u64) -> Result<SystemTime, DeviceRecordError> {
+        let time = UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+            .ok_or(DeviceRecordError::FutureTime)?;
+
+        DeviceRecord::check_custom_time(&time)?;
+
+        Ok(time)
+    }
+
+    pub fn check_data_size(kind: &DeviceKind, data: &f64) -> Result<(), DeviceRecordError> {
+        let Some(data_range) = kind.get_data_range() else {
+            return Err(DeviceRecordError::DeviceIsNotSensor)
+        };
+
+        match data {
+            size if size < &data_range.minimum => Err(DeviceRecordError::DataTooSmall),
+            size if size > &data_range.maximum => Err(DeviceRecordError::DataTooBig),
+            _ => Ok(())
+        }
+    }
+
+    pub fn check_custom_time(time: &SystemTime) -> Result<(), DeviceRecordError> {
+        let now = SystemTime::now();
+        let oldest = now - DEVICE_RECORDS_MAXIMUM_CUSTOM_AGE;
+        // Days that lost records to the retention aren't rolled up anymore,
+        // so records are taken only for days that are rolled up before the retention reaches them
+        let oldest = match db::get_device_records_retention() {
+            Some(retention) => oldest.max(now - retention + DAY),
+            None => oldest,
+        };
+
+        match time {
+            time if time < &oldest => Err(DeviceRecordError::TooLongAgo),
+            time if time > &now => Err(DeviceRecordError::FutureTime),
+            _ => Ok(())
+        }
+    }
+
+    pub fn check_import_size(size: &usize) -> Result<(), DeviceRecordError> {
+        match size {
+            0 => Err(DeviceRecordError::ImportTooSmall),
+            size if size > &DEVICE_RECORDS_MAXIMUM_IMPORT => Err(DeviceRecordError::ImportTooBig),
+            _ => Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum DeviceRecordError {
+    DeviceIsNotSensor,
+    DataTooSmall,
+    DataTooBig,
+    TooLongAgo,
+    FutureTime,
+    ImportTooSmall,
+    ImportTooBig,
+}
+
+impl Display for DeviceRecordError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceRecordError::DeviceIsNotSensor => write!(formatter, "The device is not a sensor"),
+            DeviceRecordError::DataTooSmall => write!(formatter, "The data is too small"),
+            DeviceRecordError::DataTooBig => write!(formatter, "The data is too big"),
+            DeviceRecordError::TooLongAgo => write!(formatter, "Too long ago"),
+            DeviceRecordError::FutureTime => write!(formatter, "Can't be the future"),
+            DeviceRecordError::ImportTooSmall => write!(formatter, "There are no records to import"),
+            DeviceRecordError::ImportTooBig => write!(formatter, "There are too many records to import"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_time() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        assert!(DeviceRecord::get_custom_time(now - 60).is_ok());
+        assert_eq!(DeviceRecord::get_custom_time(u64::MAX), Err(DeviceRecordError::FutureTime));
+        assert_eq!(DeviceRecord::get_custom_time(now + 60), Err(DeviceRecordError::FutureTime));
+        assert_eq!(DeviceRecord::get_custom_time(0), Err(DeviceRecordError::TooLongAgo));
+    }
+
+    #[test]
+    fn test_import_size() {
+        assert_eq!(DeviceRecord::check_import_size(&0), Err(DeviceRecordError::ImportTooSmall));
+        assert!(DeviceRecord::check_import_size(&DEVICE_RECORDS_MAXIMUM_IMPORT).is_ok());
+        assert_eq!(
+            DeviceRecord::check_import_size(&(DEVICE_RECORDS_MAXIMUM_IMPORT + 1)),
+            Err(DeviceRecordError::ImportTooBig),
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::SystemTime;
+
+use db::schema::greenhouses;
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[diesel(table_name = greenhouses)]
+pub struct Greenhouse {
+    pub id: i64,
+    pub name: String,
+    pub token: String,
+    pub owner_id: i64,
+    pub created_at: SystemTime,
+    pub maximum_average_humidity: Option<f64>,
+    pub minimum_average_temperature: Option<f64>,
+    pub driver: GreenhouseDriver,
+    pub gateway_url: Option<String>,
+    pub gateway_auth_scheme: GreenhouseGatewayAuthScheme,
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Default, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum GreenhouseDriver {
+        #[default]
+        Http = 0,
+    }
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Default, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum GreenhouseGatewayAuthScheme {
+        /// The token is sent in the `x-auth-token` header
+        #[default]
+        Token = 0,
+        /// The token is sent in the `Authorization` header as a bearer token
+        Bearer = 1,
+    }
+}
@@ -0,0 +1,14 @@
+extern crate snowflake_generator as snowflake;
+
+#[macro_use]
+mod macros;
+
+pub mod alert;
+pub mod amqp;
+pub mod command;
+pub mod device;
+pub mod device_record;
+pub mod greenhouse;
+pub mod network;
+pub mod rule;
+pub mod schedule;
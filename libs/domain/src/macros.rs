@@ -0,0 +1,99 @@
+/// Declares a `#[repr(i16)]` enum stored as `SMALLINT`.
+/// Values are checked when read, so an unknown value is an error instead of an invalid enum
+macro_rules! small_int_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(diesel::AsExpression, diesel::FromSqlRow)]
+        #[diesel(sql_type = diesel::sql_types::SmallInt)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant = $value,)*
+        }
+
+        impl TryFrom<i16> for $name {
+            type Error = String;
+
+            fn try_from(value: i16) -> Result<Self, Self::Error> {
+                match value {
+                    $($value => Ok($name::$variant),)*
+                    _ => Err(format!("Unknown {} {value}", stringify!($name))),
+                }
+            }
+        }
+
+        impl diesel::deserialize::FromSql<diesel::sql_types::SmallInt, diesel::pg::Pg> for $name {
+            fn from_sql(bytes: diesel::pg::PgValue<'_>) -> diesel::deserialize::Result<Self> {
+                let value = <i16 as diesel::deserialize::FromSql<
+                    diesel::sql_types::SmallInt,
+                    diesel::pg::Pg,
+                >>::from_sql(bytes)?;
+
+                Ok($name::try_from(value)?)
+            }
+        }
+
+        impl diesel::serialize::ToSql<diesel::sql_types::SmallInt, diesel::pg::Pg> for $name {
+            fn to_sql<'a>(
+                &'a self,
+                out: &mut diesel::serialize::Output<'a, '_, diesel::pg::Pg>,
+            ) -> diesel::serialize::Result {
+                <i16 as diesel::serialize::ToSql<
+                    diesel::sql_types::SmallInt,
+                    diesel::pg::Pg,
+                >>::to_sql(&(*self as i16), &mut out.reborrow())
+            }
+        }
+    };
+}
+
+/// Declares every payload of AMQP messages as its own struct and `AmqpPayload` wrapping them,
+/// so a route can only be given the payload it carries.
+/// The wire format is the same as of an enum with struct variants
+macro_rules! amqp_payloads {
+    (
+        $(
+            $(#[$meta:meta])*
+            pub struct $name:ident {
+                $($(#[$field_meta:meta])* pub $field:ident: $field_type:ty,)*
+            }
+        )*
+    ) => {
+        $(
+            $(#[$meta])*
+            #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+            #[serde(deny_unknown_fields)]
+            pub struct $name {
+                $($(#[$field_meta])* pub $field: $field_type,)*
+            }
+
+            impl From<$name> for AmqpPayload {
+                fn from(payload: $name) -> Self {
+                    AmqpPayload::$name(payload)
+                }
+            }
+
+            impl TryFrom<AmqpPayload> for $name {
+                type Error = AmqpMessageError;
+
+                fn try_from(payload: AmqpPayload) -> Result<Self, Self::Error> {
+                    match payload {
+                        AmqpPayload::$name(payload) => Ok(payload),
+                        _ => Err(AmqpMessageError::UnexpectedPayload),
+                    }
+                }
+            }
+        )*
+
+        #[derive(Clone, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+        #[serde(rename_all = "snake_case", deny_unknown_fields)]
+        pub enum AmqpPayload {
+            $($name($name),)*
+            #[default]
+            Ping,
+        }
+    };
+}
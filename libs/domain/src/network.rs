@@ -0,0 +1,54 @@
+use std::net::IpAddr;
+
+// Loopback, private, link-local and other addresses that aren't reachable from the internet
+pub fn is_public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || first == 0
+                // Shared address space of carrier-grade NAT
+                || (first == 100 && (64..128).contains(&second)))
+        },
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() { return is_public_ip(&IpAddr::V4(ip)) }
+
+            let first_segment = ip.segments()[0];
+
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local
+                || (first_segment & 0xfe00) == 0xfc00
+                // Link-local
+                || (first_segment & 0xffc0) == 0xfe80)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_ip() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+        assert!(is_public_ip(&ip("1.1.1.1")));
+        assert!(is_public_ip(&ip("2606:4700:4700::1111")));
+        assert!(!is_public_ip(&ip("127.0.0.1")));
+        assert!(!is_public_ip(&ip("10.0.0.1")));
+        assert!(!is_public_ip(&ip("169.254.169.254")));
+        assert!(!is_public_ip(&ip("100.64.0.1")));
+        assert!(!is_public_ip(&ip("::1")));
+        assert!(!is_public_ip(&ip("fd00::1")));
+        assert!(!is_public_ip(&ip("::ffff:192.168.0.1")));
+    }
+}
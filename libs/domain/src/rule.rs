@@ -0,0 +1,70 @@
+use std::time::SystemTime;
+
+use db::schema::rules;
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[diesel(table_name = rules)]
+pub struct Rule {
+    pub id: i64,
+    pub greenhouse_id: i64,
+    pub sensor_id: i64,
+    pub condition: RuleCondition,
+    pub threshold: f64,
+    pub hysteresis: f64,
+    /// Seconds the condition has to be met before the rule fires
+    pub duration: i32,
+    pub controller_id: i64,
+    pub state: i16,
+    /// Seconds after which the controller gets its previous state back
+    pub action_duration: Option<i32>,
+    pub created_at: SystemTime,
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum RuleCondition {
+        LessThan = 0,
+        GreaterThan = 1,
+    }
+}
+
+impl RuleCondition {
+    pub fn is_met(&self, data: f64, threshold: f64) -> bool {
+        match self {
+            RuleCondition::LessThan => data < threshold,
+            RuleCondition::GreaterThan => data > threshold,
+        }
+    }
+
+    // The data has to move away from the threshold by the hysteresis to clear the condition
+    pub fn is_cleared(&self, data: f64, threshold: f64, hysteresis: f64) -> bool {
+        match self {
+            RuleCondition::LessThan => data >= threshold + hysteresis,
+            RuleCondition::GreaterThan => data <= threshold - hysteresis,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condition_conversion() {
+        assert_eq!(RuleCondition::try_from(1), Ok(RuleCondition::GreaterThan));
+        assert!(RuleCondition::try_from(2).is_err());
+    }
+
+    #[test]
+    fn test_condition_hysteresis() {
+        let condition = RuleCondition::GreaterThan;
+
+        assert!(condition.is_met(30.5, 30.0));
+        assert!(!condition.is_cleared(29.5, 30.0, 1.0));
+        assert!(condition.is_cleared(29.0, 30.0, 1.0));
+    }
+}
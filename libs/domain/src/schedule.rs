@@ -0,0 +1,125 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use db::schema::schedules;
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[derive(Clone, Debug, Deserialize, Serialize, Insertable, Queryable)]
+#[diesel(table_name = schedules)]
+pub struct Schedule {
+    pub id: i64,
+    pub greenhouse_id: i64,
+    pub device_id: i64,
+    pub kind: ScheduleKind,
+    pub state: i16,
+    /// Minutes since midnight in UTC
+    pub start_time: Option<i16>,
+    pub end_time: Option<i16>,
+    /// Seconds
+    pub period: Option<i32>,
+    pub duration: Option<i32>,
+    pub created_at: SystemTime,
+}
+
+impl Schedule {
+    /// Whether the controller has to be in the state of the schedule at the given time
+    pub fn is_active(&self, time: SystemTime) -> bool {
+        let seconds = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        match self.kind {
+            ScheduleKind::Daily => {
+                let (Some(start), Some(end))
+                    = (self.start_time, self.end_time) else { return false };
+                let minutes = (seconds % 86400 / 60) as i16;
+
+                // The time range may go through midnight
+                match start < end {
+                    true => start <= minutes && minutes < end,
+                    false => minutes >= start || minutes < end,
+                }
+            },
+            ScheduleKind::Periodic => {
+                let (Some(period), Some(duration))
+                    = (self.period, self.duration) else { return false };
+                // Periods are counted from the creation of the schedule
+                let created_at = self.created_at
+                    .duration_since(UNIX_EPOCH).unwrap()
+                    .as_secs();
+
+                (seconds.saturating_sub(created_at) % period as u64) < duration as u64
+            },
+        }
+    }
+
+    /// How long the schedule stays active from the given time, `None` if it isn't active
+    pub fn get_remaining_time(&self, time: SystemTime) -> Option<Duration> {
+        if !self.is_active(time) { return None; }
+
+        let seconds = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let remaining = match self.kind {
+            ScheduleKind::Daily => {
+                let end = self.end_time? as u64 * 60;
+
+                // The same start and end time is the whole day
+                match (end + 86400 - seconds % 86400) % 86400 {
+                    0 => 86400,
+                    remaining => remaining,
+                }
+            },
+            ScheduleKind::Periodic => {
+                let created_at = self.created_at
+                    .duration_since(UNIX_EPOCH).unwrap()
+                    .as_secs();
+
+                self.duration? as u64 - seconds.saturating_sub(created_at) % self.period? as u64
+            },
+        };
+
+        Some(Duration::from_secs(remaining))
+    }
+}
+
+small_int_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq)]
+    #[repr(i16)]
+    pub enum ScheduleKind {
+        /// The controller is in the state between the start and the end time every day
+        Daily = 0,
+        /// The controller is in the state for the duration once every period
+        Periodic = 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_schedule(kind: ScheduleKind) -> Schedule {
+        Schedule {
+            id: 1,
+            greenhouse_id: 2,
+            device_id: 3,
+            kind,
+            state: 1,
+            start_time: Some(23 * 60),
+            end_time: Some(60),
+            period: Some(3600),
+            duration: Some(600),
+            created_at: UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_remaining_time() {
+        let time = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        let daily = get_schedule(ScheduleKind::Daily);
+        let periodic = get_schedule(ScheduleKind::Periodic);
+
+        assert_eq!(daily.get_remaining_time(time(86400 + 23 * 3600 + 30)), Some(Duration::from_secs(7170)));
+        assert_eq!(daily.get_remaining_time(time(86400 + 1800)), Some(Duration::from_secs(1800)));
+        assert_eq!(daily.get_remaining_time(time(86400 + 3600)), None);
+        assert_eq!(periodic.get_remaining_time(time(3600 + 100)), Some(Duration::from_secs(500)));
+        assert_eq!(periodic.get_remaining_time(time(3600 + 600)), None);
+    }
+}
@@ -28,6 +28,12 @@ pub fn generate() -> i64 {
     SNOWFLAKE_ID_GENERATOR.lock().unwrap().real_time_generate()
 }
 
+// The milliseconds since the epoch are kept above the lower 22 bits of an ID,
+// so IDs generated the duration apart differ by at least this much
+pub fn get_span(duration: Duration) -> i64 {
+    (duration.as_millis() as i64) << 22
+}
+
 pub fn init() {
     info!("Initialize Snowflake Generator");
 